@@ -0,0 +1,268 @@
+// Cardano address parsing and conversion shared by the auth and Blockfrost paths
+use cardano_serialization_lib::address::{
+    Address, BaseAddress, EnterpriseAddress, PointerAddress, RewardAddress,
+};
+use cardano_serialization_lib::crypto::Ed25519KeyHash;
+use std::fmt;
+
+/// Longer than any valid Cardano address (bech32 base addresses are ~103 characters, their hex
+/// form 114), so obvious junk is rejected before it reaches CSL parsing or the challenge map
+pub const MAX_ADDRESS_LENGTH: usize = 256;
+
+/// Shape of an address string accepted by the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressKind {
+    /// addr1... / addr_test1...
+    Bech32Payment,
+    /// stake1... / stake_test1...
+    Bech32Stake,
+    /// Raw address bytes as hex, as returned by CIP-30 wallets
+    Hex,
+}
+
+/// Why an address string was rejected
+#[derive(Debug)]
+pub enum AddressError {
+    Empty,
+    /// Input length, when over MAX_ADDRESS_LENGTH
+    TooLong(usize),
+    InvalidBech32(String),
+    /// Hex that doesn't decode to a valid address
+    InvalidBytes(String),
+    /// Neither bech32 nor even-length hex
+    Unrecognized,
+    /// Script, Byron or reward address, which has no payment key hash
+    NoPaymentKey(&'static str),
+    /// Bech32 prefix other than addr / addr_test / stake / stake_test
+    UnexpectedPrefix(String),
+    /// Address network (mainnet/testnet) differs from the server's
+    NetworkMismatch {
+        address: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Address is empty"),
+            Self::TooLong(len) => write!(
+                f,
+                "Address is {} characters, the maximum is {}",
+                len, MAX_ADDRESS_LENGTH
+            ),
+            Self::InvalidBech32(e) => write!(f, "Invalid bech32 address: {}", e),
+            Self::InvalidBytes(e) => write!(f, "Invalid address bytes: {}", e),
+            Self::Unrecognized => write!(f, "Address is neither bech32 nor hex"),
+            Self::NoPaymentKey(reason) => write!(f, "{}", reason),
+            Self::UnexpectedPrefix(prefix) => write!(f, "Unexpected bech32 prefix '{}'", prefix),
+            Self::NetworkMismatch { address, expected } => write!(
+                f,
+                "Address is for {} but this server is configured for {}",
+                address, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Check an address string is well-formed before doing any work with it
+pub fn validate_cardano_address(address: &str) -> Result<AddressKind, AddressError> {
+    if address.is_empty() {
+        return Err(AddressError::Empty);
+    }
+    if address.len() > MAX_ADDRESS_LENGTH {
+        return Err(AddressError::TooLong(address.len()));
+    }
+
+    if address.starts_with("addr") || address.starts_with("stake") {
+        let parsed = Address::from_bech32(address)
+            .map_err(|e| AddressError::InvalidBech32(format!("{}", e)))?;
+        return Ok(if RewardAddress::from_address(&parsed).is_some() {
+            AddressKind::Bech32Stake
+        } else {
+            AddressKind::Bech32Payment
+        });
+    }
+
+    if is_hex(address) {
+        Ok(AddressKind::Hex)
+    } else {
+        Err(AddressError::Unrecognized)
+    }
+}
+
+fn is_hex(address: &str) -> bool {
+    address.len().is_multiple_of(2) && address.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse a bech32 or hex address string
+pub fn from_any(address: &str) -> Result<Address, AddressError> {
+    match validate_cardano_address(address)? {
+        AddressKind::Hex => {
+            let bytes = hex::decode(address).map_err(|e| AddressError::InvalidBytes(e.to_string()))?;
+            Address::from_bytes(bytes).map_err(|e| AddressError::InvalidBytes(format!("{}", e)))
+        }
+        AddressKind::Bech32Payment | AddressKind::Bech32Stake => Address::from_bech32(address)
+            .map_err(|e| AddressError::InvalidBech32(format!("{}", e))),
+    }
+}
+
+/// Bech32 form of an address, with the prefix implied by its type and network
+pub fn to_bech32(address: &Address) -> Result<String, AddressError> {
+    address
+        .to_bech32(None)
+        .map_err(|e| AddressError::InvalidBytes(format!("{}", e)))
+}
+
+/// Returns "mainnet", "testnet", or "unknown"; testnets share network id 0
+pub fn network_of(address: &Address) -> &'static str {
+    match address.network_id() {
+        Ok(1) => "mainnet",
+        Ok(0) => "testnet",
+        _ => "unknown",
+    }
+}
+
+/// Check a bech32 address has a known prefix for the `expected` network ("mainnet" or "testnet")
+pub fn check_network(bech32: &str, expected: &'static str) -> Result<(), AddressError> {
+    let prefix = bech32.rsplit_once('1').map_or(bech32, |(prefix, _)| prefix);
+    let network = match prefix {
+        "addr" | "stake" => "mainnet",
+        "addr_test" | "stake_test" => "testnet",
+        other => return Err(AddressError::UnexpectedPrefix(other.to_string())),
+    };
+
+    if network == expected {
+        Ok(())
+    } else {
+        Err(AddressError::NetworkMismatch {
+            address: network,
+            expected,
+        })
+    }
+}
+
+/// Blake2b-224 hash of the key behind the payment credential of a base, enterprise or pointer address
+pub fn payment_key_hash(address: &Address) -> Result<Ed25519KeyHash, AddressError> {
+    let payment_cred = if let Some(base) = BaseAddress::from_address(address) {
+        base.payment_cred()
+    } else if let Some(enterprise) = EnterpriseAddress::from_address(address) {
+        enterprise.payment_cred()
+    } else if let Some(pointer) = PointerAddress::from_address(address) {
+        pointer.payment_cred()
+    } else {
+        return Err(AddressError::NoPaymentKey(
+            "Byron and reward addresses have no payment key credential",
+        ));
+    };
+
+    payment_cred.to_keyhash().ok_or(AddressError::NoPaymentKey(
+        "address uses a script credential, not a key credential",
+    ))
+}
+
+/// Hash of the stake key of a base address; None for other address types or script stake credentials
+pub fn stake_key_hash(address: &Address) -> Option<Ed25519KeyHash> {
+    BaseAddress::from_address(address)?.stake_cred().to_keyhash()
+}
+
+/// Bech32 stake (reward) address sharing the stake credential of a base address
+pub fn stake_address_of(address: &Address) -> Option<String> {
+    let base = BaseAddress::from_address(address)?;
+    let network = address.network_id().ok()?;
+    RewardAddress::new(network, &base.stake_cred())
+        .to_address()
+        .to_bech32(None)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{base_address, bech32, enterprise_address, key_credential};
+    use cardano_serialization_lib::address::Pointer;
+    use cardano_serialization_lib::utils::BigNum;
+
+    fn reward_address(network: u8, key_byte: u8) -> Address {
+        RewardAddress::new(network, &key_credential(key_byte)).to_address()
+    }
+
+    #[test]
+    fn accepts_each_supported_address_shape() {
+        let mainnet = bech32(&enterprise_address(1, 1));
+        let testnet = bech32(&enterprise_address(0, 1));
+        let stake = bech32(&reward_address(1, 2));
+        let hex_address = hex::encode(enterprise_address(0, 1).to_bytes());
+        assert!(mainnet.starts_with("addr1") && testnet.starts_with("addr_test1") && stake.starts_with("stake1"));
+
+        assert_eq!(validate_cardano_address(&mainnet).unwrap(), AddressKind::Bech32Payment);
+        assert_eq!(validate_cardano_address(&testnet).unwrap(), AddressKind::Bech32Payment);
+        assert_eq!(validate_cardano_address(&stake).unwrap(), AddressKind::Bech32Stake);
+        assert_eq!(validate_cardano_address(&hex_address).unwrap(), AddressKind::Hex);
+        assert_eq!(validate_cardano_address(&hex_address.to_uppercase()).unwrap(), AddressKind::Hex);
+    }
+
+    #[test]
+    fn rejects_junk() {
+        let testnet = bech32(&enterprise_address(0, 1));
+        let mut corrupted = testnet.clone();
+        // Flip the last checksum character
+        let last = if corrupted.ends_with('q') { 'p' } else { 'q' };
+        corrupted.pop();
+        corrupted.push(last);
+
+        assert!(matches!(validate_cardano_address(""), Err(AddressError::Empty)));
+        assert!(matches!(validate_cardano_address(&"a".repeat(300)), Err(AddressError::TooLong(300))));
+        assert!(matches!(validate_cardano_address(&corrupted), Err(AddressError::InvalidBech32(_))));
+        assert!(matches!(validate_cardano_address("addr_test1"), Err(AddressError::InvalidBech32(_))));
+        for junk in ["hello", "abc", "0xdeadbeef", "zz00", "addr_test1 with spaces"] {
+            assert!(validate_cardano_address(junk).is_err(), "{}", junk);
+        }
+    }
+
+    #[test]
+    fn network_is_detected_for_all_four_prefixes() {
+        let cases = [
+            (bech32(&enterprise_address(1, 1)), "addr1", "mainnet"),
+            (bech32(&enterprise_address(0, 1)), "addr_test1", "testnet"),
+            (bech32(&reward_address(1, 2)), "stake1", "mainnet"),
+            (bech32(&reward_address(0, 2)), "stake_test1", "testnet"),
+        ];
+
+        for (address, prefix, network) in cases {
+            assert!(address.starts_with(prefix), "{}", address);
+            let parsed = from_any(&address).unwrap();
+            assert_eq!(network_of(&parsed), network, "{}", address);
+            // Already bech32, so normalizing leaves it unchanged
+            assert_eq!(to_bech32(&parsed).unwrap(), address);
+            assert!(check_network(&address, network).is_ok());
+            let other = if network == "mainnet" { "testnet" } else { "mainnet" };
+            assert!(matches!(check_network(&address, other), Err(AddressError::NetworkMismatch { .. })));
+        }
+    }
+
+    #[test]
+    fn base_enterprise_and_pointer_addresses_round_trip() {
+        // Stake registered by the first certificate of transaction 1 in slot 42
+        let certificate = Pointer::new_pointer(&BigNum::from(42u64), &BigNum::from(1u64), &BigNum::zero());
+        let pointer = PointerAddress::new(0, &key_credential(1), &certificate).to_address();
+        let cases = [
+            ("base", base_address(0, &key_credential(1), &key_credential(2)), Some([2; 28])),
+            ("enterprise", enterprise_address(0, 1), None),
+            ("pointer", pointer, None),
+        ];
+
+        for (kind, address, stake_key) in cases {
+            let hex_address = hex::encode(address.to_bytes());
+            let bech32_address = to_bech32(&from_any(&hex_address).unwrap()).unwrap();
+            assert_eq!(bech32_address, bech32(&address), "{}", kind);
+            assert_eq!(from_any(&bech32_address).unwrap().to_bytes(), address.to_bytes(), "{}", kind);
+
+            assert_eq!(payment_key_hash(&address).unwrap().to_bytes(), [1; 28], "{}", kind);
+            assert_eq!(stake_key_hash(&address).map(|hash| hash.to_bytes()), stake_key.map(Vec::from), "{}", kind);
+            assert_eq!(stake_address_of(&address).is_some(), stake_key.is_some(), "{}", kind);
+        }
+    }
+}
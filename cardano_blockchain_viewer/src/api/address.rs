@@ -0,0 +1,92 @@
+// src/api/address.rs
+use axum::{http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use super::extract::ApiQuery;
+use crate::address;
+
+#[derive(Debug, Deserialize)]
+pub struct ConvertQuery {
+    pub address: String,
+    // "bech32" (default) or "hex"
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConvertedAddress {
+    pub input: String,
+    pub output: String,
+    // "mainnet", "testnet" or "unknown"
+    pub network: &'static str,
+}
+
+// Convert a CIP-30 hex address to bech32, or back
+pub async fn convert_address(
+    ApiQuery(query): ApiQuery<ConvertQuery>,
+) -> Result<Json<ConvertedAddress>, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request = |error: String, code: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": error, "code": code })),
+        )
+    };
+
+    let parsed = address::from_any(query.address.trim())
+        .map_err(|e| bad_request(format!("Invalid address: {}", e), "INVALID_ADDRESS"))?;
+
+    let output = match query.to.as_deref().unwrap_or("bech32") {
+        "bech32" => address::to_bech32(&parsed)
+            .map_err(|e| bad_request(format!("Invalid address: {}", e), "INVALID_ADDRESS"))?,
+        "hex" => hex::encode(parsed.to_bytes()),
+        other => {
+            return Err(bad_request(
+                format!("Unknown target format '{}' (expected bech32 or hex)", other),
+                "INVALID_QUERY",
+            ));
+        }
+    };
+
+    Ok(Json(ConvertedAddress {
+        network: address::network_of(&parsed),
+        input: query.address,
+        output,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{base_address, bech32, key_credential};
+
+    async fn convert(address: &str, to: Option<&str>) -> Result<ConvertedAddress, (StatusCode, serde_json::Value)> {
+        let query = ConvertQuery { address: address.to_string(), to: to.map(str::to_string) };
+        convert_address(ApiQuery(query))
+            .await
+            .map(|Json(converted)| converted)
+            .map_err(|(status, Json(body))| (status, body))
+    }
+
+    #[tokio::test]
+    async fn hex_and_bech32_convert_back_and_forth() {
+        for network in [0, 1] {
+            let address = base_address(network, &key_credential(1), &key_credential(2));
+            let hex_address = hex::encode(address.to_bytes());
+
+            let to_bech32 = convert(&hex_address, None).await.unwrap();
+            assert_eq!(to_bech32.output, bech32(&address));
+            let back = convert(&to_bech32.output, Some("hex")).await.unwrap();
+            assert_eq!((back.input, back.output), (bech32(&address), hex_address));
+            assert_eq!(back.network, if network == 1 { "mainnet" } else { "testnet" });
+        }
+    }
+
+    #[tokio::test]
+    async fn garbage_and_unknown_targets_are_400s() {
+        let (status, body) = convert("not-an-address", None).await.unwrap_err();
+        assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("INVALID_ADDRESS")));
+
+        let address = bech32(&base_address(0, &key_credential(1), &key_credential(2)));
+        let (status, body) = convert(&address, Some("base58")).await.unwrap_err();
+        assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("INVALID_QUERY")));
+    }
+}
@@ -0,0 +1,241 @@
+// src/api/admin.rs
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, warn};
+
+use super::auth::{auth_error, codes};
+use crate::models::{AppState, BufferStats, OuraStatus, OuraStatusHandle};
+use crate::services::event_processor::broadcast_stats;
+use crate::websocket::protocol::Frame;
+
+// Shorter operator tokens are accepted but easy to guess
+const MIN_ADMIN_TOKEN_LENGTH: usize = 16;
+
+/// Operator credential for the destructive admin routes (ADMIN_TOKEN). A wallet JWT isn't
+/// enough: anyone can get one by signing a challenge with any wallet
+#[derive(Clone)]
+pub struct AdminToken {
+    // Only the SHA-256 digest is kept, see matches
+    digest: Arc<[u8; 32]>,
+}
+
+impl AdminToken {
+    pub fn new(token: &str) -> Self {
+        Self {
+            digest: Arc::new(Sha256::digest(token.as_bytes()).into()),
+        }
+    }
+
+    // None when ADMIN_TOKEN is unset or blank, in which case the admin routes aren't mounted (404)
+    pub fn from_env() -> Option<Self> {
+        let token = std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.trim().is_empty());
+        match &token {
+            Some(token) if token.len() < MIN_ADMIN_TOKEN_LENGTH => warn!(
+                "⚠️  ADMIN_TOKEN is shorter than {} characters, use a long random value",
+                MIN_ADMIN_TOKEN_LENGTH
+            ),
+            Some(_) => info!("Admin endpoints enabled (ADMIN_TOKEN)"),
+            None => info!("ADMIN_TOKEN not set, admin endpoints are disabled"),
+        }
+        token.map(|token| Self::new(token.trim()))
+    }
+
+    // Digests are compared rather than the tokens, so the constant-time comparison doesn't
+    // leak the token length either
+    fn matches(&self, presented: &str) -> bool {
+        let presented: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        presented.ct_eq(&*self.digest).into()
+    }
+}
+
+// `Authorization: Bearer <ADMIN_TOKEN>` required on every admin route
+pub async fn require_admin(
+    State(token): State<AdminToken>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let presented = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            auth_error(StatusCode::UNAUTHORIZED, codes::MISSING_TOKEN, "Missing admin token")
+        })?;
+
+    if !token.matches(presented) {
+        warn!("Rejected admin request with a wrong token");
+        return Err(auth_error(StatusCode::UNAUTHORIZED, codes::INVALID_TOKEN, "Invalid admin token"));
+    }
+    Ok(next.run(request).await)
+}
+
+// Routes behind ADMIN_TOKEN
+pub fn routes(token: AdminToken, reset: ResetState, oura_status: OuraStatusHandle) -> Router {
+    let status_routes = Router::new()
+        .route("/api/admin/oura", get(get_oura_status))
+        .with_state(oura_status);
+
+    Router::new()
+        .route("/api/admin/reset", post(reset_state))
+        .with_state(reset)
+        .merge(status_routes)
+        .layer(middleware::from_fn_with_state(token, require_admin))
+}
+
+#[derive(Clone)]
+pub struct ResetState {
+    pub app_state: Arc<Mutex<AppState>>,
+    pub ws_tx: broadcast::Sender<Frame>,
+}
+
+// Oura child process PID, restart count and whether it is running (ADMIN_TOKEN required)
+pub async fn get_oura_status(State(status): State<OuraStatusHandle>) -> Json<OuraStatus> {
+    let status = status.lock().unwrap_or_else(|e| e.into_inner());
+    Json(status.clone())
+}
+
+// Clear the buffer and every counter without touching the Oura connection (ADMIN_TOKEN required).
+// Connected clients get the zeroed stats right away
+pub async fn reset_state(State(reset): State<ResetState>) -> Json<BufferStats> {
+    let mut state = reset.app_state.lock().await;
+    state.reset();
+    if let Err(e) = broadcast_stats(&mut state, &reset.ws_tx) {
+        tracing::error!("Failed to broadcast stats after reset: {}", e);
+    }
+    tracing::warn!("🧹 AppState reset via /api/admin/reset");
+    Json(state.get_stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockchainEvent;
+    use crate::test_support::call;
+    use axum::body::Body;
+    use axum::http::Request;
+
+    const TOKEN: &str = "operator-token-0123456789";
+
+    fn seeded_state() -> Arc<Mutex<AppState>> {
+        let mut state = AppState::new(10);
+        state.add_event(
+            BlockchainEvent::Block {
+                slot: 10,
+                hash: "ab".repeat(32),
+                number: 5,
+                epoch: 1,
+                era: "Conway".to_string(),
+                tx_count: 1,
+                timestamp: 1_700_000_000,
+                details: serde_json::json!({}),
+            },
+            10,
+        );
+        state.add_event(
+            BlockchainEvent::Transaction {
+                hash: "cd".repeat(32),
+                slot: 10,
+                fee: 170_000,
+                inputs: 1,
+                outputs: 2,
+                total_output: 5_000_000,
+                mint_count: 0,
+                is_mint: false,
+                timestamp: 1_700_000_000,
+                details: serde_json::json!({}),
+            },
+            10,
+        );
+        Arc::new(Mutex::new(state))
+    }
+
+    fn reset_request(authorization: Option<&str>) -> Request<Body> {
+        let mut request = Request::post("/api/admin/reset");
+        if let Some(value) = authorization {
+            request = request.header("authorization", value);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn router(app_state: &Arc<Mutex<AppState>>) -> (Router, broadcast::Receiver<Frame>) {
+        let (ws_tx, ws_rx) = broadcast::channel(8);
+        let reset = ResetState {
+            app_state: Arc::clone(app_state),
+            ws_tx,
+        };
+        (routes(AdminToken::new(TOKEN), reset, OuraStatusHandle::default()), ws_rx)
+    }
+
+    #[tokio::test]
+    async fn reset_zeroes_counts_and_broadcasts_them() {
+        let app_state = seeded_state();
+        let (router, mut ws_rx) = router(&app_state);
+
+        let (status, body) = call(&router, reset_request(Some(&format!("Bearer {}", TOKEN)))).await;
+
+        assert_eq!(status, StatusCode::OK);
+        for field in ["total_events", "blocks_count", "transactions_count", "buffer_size", "total_fees_lovelace"] {
+            assert_eq!(body[field], 0, "{} in {}", field, body);
+        }
+        let state = app_state.lock().await;
+        assert!(state.buffer.is_empty());
+        assert_eq!((state.blocks_count, state.transactions_count), (0, 0));
+
+        let stats: serde_json::Value = serde_json::from_str(ws_rx.try_recv().unwrap().json()).unwrap();
+        assert_eq!(stats["type"], "stats");
+        assert_eq!(stats["data"]["total_events"], 0);
+    }
+
+    #[tokio::test]
+    async fn reset_without_the_admin_token_is_rejected() {
+        let app_state = seeded_state();
+        let (router, _ws_rx) = router(&app_state);
+
+        let (missing, body) = call(&router, reset_request(None)).await;
+        assert_eq!((missing, body["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::MISSING_TOKEN)));
+
+        // A wallet JWT (or any other bearer) is not the admin token
+        let (wrong, body) = call(&router, reset_request(Some("Bearer eyJhbGciOiJIUzI1NiJ9.e30.x"))).await;
+        assert_eq!((wrong, body["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::INVALID_TOKEN)));
+
+        assert_eq!(app_state.lock().await.total_events, 2);
+    }
+
+    #[tokio::test]
+    async fn oura_status_requires_the_admin_token() {
+        let (router, _ws_rx) = router(&seeded_state());
+        let request = |authorization: &str| {
+            Request::get("/api/admin/oura")
+                .header("authorization", authorization)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (wrong, _) = call(&router, request("Bearer not-the-admin-token")).await;
+        assert_eq!(wrong, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = call(&router, request(&format!("Bearer {}", TOKEN))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["restarts"].as_u64(), body["connected"].as_bool()), (Some(0), Some(false)));
+    }
+
+    #[test]
+    fn token_comparison_requires_an_exact_match() {
+        let token = AdminToken::new(TOKEN);
+        assert!(token.matches(TOKEN));
+        assert!(!token.matches(&TOKEN[..TOKEN.len() - 1]));
+        assert!(!token.matches(&format!("{}x", TOKEN)));
+        assert!(!token.matches(""));
+    }
+}
@@ -1,18 +1,112 @@
-use crate::auth::JwtManager;
-use axum::{extract::State, http::StatusCode, Json};
+use crate::address::{self, validate_cardano_address, AddressError};
+use super::extract::ApiJson;
+use crate::auth::{Claims, JwtManager};
+use axum::{extract::State, http::StatusCode, Extension, Json};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 
 // It creates multi thread shared mutable hashmap
-pub type ChallengeStore = Arc<Mutex<HashMap<String, ChallengeData>>>;
+// Each address can have several outstanding challenges (e.g. one per open tab)
+pub type ChallengeStore = Arc<Mutex<HashMap<String, Vec<ChallengeData>>>>;
+
+// Newest challenges kept per address; older ones are dropped when more are requested
+const MAX_CHALLENGES_PER_ADDRESS: usize = 5;
+
+// How long a challenge can be answered after it was issued
+pub const CHALLENGE_TTL_SECS: i64 = 300;
+
+// Stable `code` values on auth error bodies, so clients don't have to match on the `error` text
+pub mod codes {
+    pub const MISSING_FIELDS: &str = "MISSING_FIELDS";
+    pub const INVALID_ADDRESS: &str = "INVALID_ADDRESS";
+    // Never issued, or already used: the client needs a first challenge
+    pub const CHALLENGE_NOT_FOUND: &str = "CHALLENGE_NOT_FOUND";
+    // Issued but older than CHALLENGE_TTL_SECS: the client should request a new one
+    pub const CHALLENGE_EXPIRED: &str = "CHALLENGE_EXPIRED";
+    pub const INVALID_SIGNATURE: &str = "INVALID_SIGNATURE";
+    // The signing key doesn't belong to the claimed address
+    pub const ADDRESS_MISMATCH: &str = "ADDRESS_MISMATCH";
+    pub const UNSUPPORTED_ADDRESS_TYPE: &str = "UNSUPPORTED_ADDRESS_TYPE";
+    pub const VERIFICATION_ERROR: &str = "VERIFICATION_ERROR";
+    pub const TOKEN_ISSUE_FAILED: &str = "TOKEN_ISSUE_FAILED";
+    // The address belongs to a different network than the server
+    pub const NETWORK_MISMATCH: &str = "NETWORK_MISMATCH";
+    pub const MISSING_TOKEN: &str = "MISSING_TOKEN";
+    pub const INVALID_TOKEN: &str = "INVALID_TOKEN";
+}
+
+// `{"error": ..., "code": ...}` body shared by the auth handlers
+pub fn auth_error(
+    status: StatusCode,
+    code: &str,
+    error: impl Into<String>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(serde_json::json!({ "error": error.into(), "code": code })),
+    )
+}
 
 #[derive(Clone)]
 pub struct AuthState {
     pub jwt_manager: Arc<JwtManager>,
     pub challenges: ChallengeStore,
+    // "mainnet" or "testnet"; tokens are only issued for addresses on this network
+    pub network: &'static str,
+    // Used when a challenge request doesn't pick a format (CHALLENGE_FORMAT)
+    pub challenge_format: ChallengeFormat,
+}
+
+/// Shape of the challenge message a wallet is asked to sign
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeFormat {
+    /// Human-readable text, shown as-is by most wallets
+    #[default]
+    PlainText,
+    /// Canonical JSON (sorted keys, no whitespace) for integrations that expect a structured payload
+    Cip8,
+}
+
+impl ChallengeFormat {
+    /// `CHALLENGE_FORMAT=plain_text|cip8`, defaulting to plain text
+    pub fn from_env() -> Self {
+        match std::env::var("CHALLENGE_FORMAT").as_deref() {
+            Ok("cip8") => Self::Cip8,
+            Ok("plain_text") | Err(_) => Self::PlainText,
+            Ok(other) => {
+                warn!("Unknown CHALLENGE_FORMAT '{}', using plain_text", other);
+                Self::PlainText
+            }
+        }
+    }
+
+    // The exact text the wallet signs; stored with the challenge and verified byte for byte
+    fn message(self, address: &str, nonce: &str, issued_at: i64) -> String {
+        match self {
+            Self::PlainText => format!(
+                "Sign this message to authenticate with Cardano Blockchain Viewer\n\nNonce: {}\nTimestamp: {}",
+                nonce,
+                chrono::DateTime::from_timestamp(issued_at, 0)
+                    .unwrap_or_default()
+                    .to_rfc3339()
+            ),
+            // serde_json objects serialize with sorted keys, so the encoding is canonical
+            Self::Cip8 => serde_json::json!({
+                "domain": "Cardano Blockchain Viewer",
+                "statement": "Sign in with your Cardano wallet",
+                "address": address,
+                "nonce": nonce,
+                "issued_at": issued_at,
+                "expires_at": issued_at + CHALLENGE_TTL_SECS,
+            })
+            .to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -20,31 +114,56 @@ pub struct ChallengeData {
     pub nonce: String,
     pub message: String,
     pub timestamp: i64,
+    pub format: ChallengeFormat,
+}
+
+impl ChallengeData {
+    // Single expiry rule shared by pruning and verification
+    fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at()
+    }
+
+    fn expires_at(&self) -> i64 {
+        self.timestamp + CHALLENGE_TTL_SECS
+    }
+}
+
+// Drop expired challenges and addresses left with none
+fn prune_expired(challenges: &mut HashMap<String, Vec<ChallengeData>>, now: i64) {
+    challenges.retain(|_, pending| {
+        pending.retain(|data| !data.is_expired(now));
+        !pending.is_empty()
+    });
 }
 
 // ChallengeRequest → client asks for a login challenge (wallet address).
 
 // ChallengeResponse → server returns a nonce + message to sign.
 
-// VerifyRequest → client returns signed message + optional stake address.
+// VerifyRequest → client returns the signed message and its COSE key.
 
 // VerifyResponse → server returns a JWT after successful verification.
 
 #[derive(Debug, Deserialize)]
 pub struct ChallengeRequest {
     pub address: String,
+    // Overrides the server's CHALLENGE_FORMAT for this challenge
+    pub format: Option<ChallengeFormat>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ChallengeResponse {
     pub message: String,
     pub nonce: String,
+    // Unix seconds after which verify answers CHALLENGE_EXPIRED
+    pub expires_at: i64,
+    pub format: ChallengeFormat,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyRequest {
+    // A `stake_address` sent by older clients is ignored: the token's is derived from `address`
     pub address: String,
-    pub stake_address: Option<String>,
     pub signature: String,
     pub key: String,
 }
@@ -55,6 +174,16 @@ pub struct VerifyResponse {
     pub address: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub wallet_address: String,
+    pub stake_address: Option<String>,
+    pub iat: usize,
+    pub exp: usize,
+    // Seconds until exp, 0 once it has passed (the JWT leeway can still let it through)
+    pub expires_in_secs: u64,
+}
+
 pub async fn create_challenge(
     // Axum sees you asked for State<AuthState> in your function.
     // It grabs the shared state you registered in .with_state(auth_state) and gives it to your function.
@@ -62,13 +191,20 @@ pub async fn create_challenge(
     State(state): State<AuthState>,
     // Axum sees you asked for Json<ChallengeRequest>.
     // It reads the HTTP request body, parses the JSON, and deserializes it into your struct
-    Json(payload): Json<ChallengeRequest>,
+    ApiJson(payload): ApiJson<ChallengeRequest>,
 ) -> Result<Json<ChallengeResponse>, (StatusCode, Json<serde_json::Value>)> {
     if payload.address.is_empty() {
-        return Err((
+        return Err(auth_error(
+            StatusCode::BAD_REQUEST,
+            codes::MISSING_FIELDS,
+            "Address is required",
+        ));
+    }
+    if let Err(e) = validate_cardano_address(&payload.address) {
+        return Err(auth_error(
             StatusCode::BAD_REQUEST,
-            // It is used to send response without defining a struct
-            Json(serde_json::json!({ "error": "Address is required" })),
+            codes::INVALID_ADDRESS,
+            format!("Invalid address: {}", e),
         ));
     }
 
@@ -76,53 +212,64 @@ pub async fn create_challenge(
     let normalized_address = normalize_address_format(&payload.address);
     info!(
         "Address received: {} (normalized: {})",
-        &payload.address[..16],
-        &normalized_address[..16]
+        &payload.address[..payload.address.len().min(16)],
+        &normalized_address[..normalized_address.len().min(16)]
     );
 
     let nonce: u64 = rand::random();
     let nonce_str = nonce.to_string();
     let timestamp = chrono::Utc::now().timestamp();
 
-    let message = format!(
-        "Sign this message to authenticate with Cardano Blockchain Viewer\n\nNonce: {}\nTimestamp: {}",
-        nonce_str,
-        chrono::Utc::now().to_rfc3339()
-    );
+    let format = payload.format.unwrap_or(state.challenge_format);
+    let message = format.message(&payload.address, &nonce_str, timestamp);
 
     // Here challenges is a shared pool so editing it will result in editing of the ChallengeStore
     let mut challenges = state.challenges.lock().await;
-    challenges.insert(
-        normalized_address.clone(),
-        ChallengeData {
-            nonce: nonce_str.clone(),
-            message: message.clone(),
-            timestamp,
-        },
-    );
+    let pending = challenges.entry(normalized_address.clone()).or_default();
+    let challenge = ChallengeData {
+        nonce: nonce_str.clone(),
+        message: message.clone(),
+        timestamp,
+        format,
+    };
+    let expires_at = challenge.expires_at();
+    pending.push(challenge);
+    if pending.len() > MAX_CHALLENGES_PER_ADDRESS {
+        let excess = pending.len() - MAX_CHALLENGES_PER_ADDRESS;
+        pending.drain(..excess);
+    }
 
-    let cutoff = timestamp - 300;
-    challenges.retain(|_, data| data.timestamp > cutoff);
+    prune_expired(&mut challenges, timestamp);
 
     info!(
         "Challenge created for normalized address: {}",
-        &normalized_address[..16]
+        &normalized_address[..normalized_address.len().min(16)]
     );
 
     Ok(Json(ChallengeResponse {
         message,
         nonce: nonce_str,
+        expires_at,
+        format,
     }))
 }
 
 pub async fn verify_signature(
     State(state): State<AuthState>,
-    Json(payload): Json<VerifyRequest>,
+    ApiJson(payload): ApiJson<VerifyRequest>,
 ) -> Result<Json<VerifyResponse>, (StatusCode, Json<serde_json::Value>)> {
     if payload.address.is_empty() || payload.signature.is_empty() {
-        return Err((
+        return Err(auth_error(
             StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Address and signature are required" })),
+            codes::MISSING_FIELDS,
+            "Address and signature are required",
+        ));
+    }
+    if let Err(e) = validate_cardano_address(&payload.address) {
+        return Err(auth_error(
+            StatusCode::BAD_REQUEST,
+            codes::INVALID_ADDRESS,
+            format!("Invalid address: {}", e),
         ));
     }
 
@@ -136,40 +283,47 @@ pub async fn verify_signature(
 
     let challenges = state.challenges.lock().await;
     // Try both normalized and original address for lookup
-    let challenge_data = challenges.get(&normalized_address)
+    let pending = challenges.get(&normalized_address)
         .or_else(|| challenges.get(&payload.address))
-        .cloned();
+        .cloned()
+        .unwrap_or_default();
     drop(challenges);
 
-    let challenge_data = challenge_data.ok_or_else(|| {
+    if pending.is_empty() {
         warn!(
             "No challenge found for address: {} (normalized: {})",
             &payload.address[..payload.address.len().min(16)],
             &normalized_address[..normalized_address.len().min(16)]
         );
-        (
+        return Err(auth_error(
             StatusCode::UNAUTHORIZED,
-            Json(
-                serde_json::json!({"error": "No challenge found. Please request a new challenge."}),
-            ),
-        )
-    })?;
+            codes::CHALLENGE_NOT_FOUND,
+            "No challenge found. Please request a new challenge.",
+        ));
+    }
 
-    // Check if challenge has expired (5 minutes)
+    // Only challenges that haven't expired can be answered
     let now = chrono::Utc::now().timestamp();
-    if now - challenge_data.timestamp > 300 {
-        warn!("Challenge expired for address: {}", &payload.address[..16]);
-        return Err((
+    let pending: Vec<ChallengeData> = pending
+        .into_iter()
+        .filter(|data| !data.is_expired(now))
+        .collect();
+    if pending.is_empty() {
+        prune_expired(&mut *state.challenges.lock().await, now);
+        warn!(
+            "Challenge expired for address: {}",
+            &payload.address[..payload.address.len().min(16)]
+        );
+        return Err(auth_error(
             StatusCode::UNAUTHORIZED,
-            Json(
-                serde_json::json!({ "error": "Challenge expired. Please request a new challenge." }),
-            ),
+            codes::CHALLENGE_EXPIRED,
+            "Challenge expired. Please request a new challenge.",
         ));
     }
 
     info!(
         "Verifying signature for address: {}",
-        &payload.address[..16]
+        &payload.address[..payload.address.len().min(16)]
     );
 
     // ========================================================================
@@ -178,7 +332,7 @@ pub async fn verify_signature(
 
     info!(
         "🔍 Starting signature verification for address: {}",
-        &payload.address[..16]
+        &payload.address[..payload.address.len().min(16)]
     );
     info!(
         "📊 Signature data length: {} bytes",
@@ -187,76 +341,125 @@ pub async fn verify_signature(
     info!("🔑 Key data length: {} bytes", payload.key.len());
 
     info!("🔐 Starting cryptographic signature verification...");
-    match verify_cardano_signature(
-        &normalized_address,
-        &challenge_data.message,
-        &payload.signature,
-        &payload.key,
-    ) {
-        Ok(true) => {
-            info!(
-                "✅ Signature verification PASSED for: {}",
-                &normalized_address[..normalized_address.len().min(16)]
-            );
-        }
-        Ok(false) => {
-            warn!(
-                "❌ Signature verification FAILED for: {}",
-                &normalized_address[..normalized_address.len().min(16)]
-            );
-            warn!("📊 Debug info:");
-            warn!("   - Address: {}", &normalized_address[..normalized_address.len().min(32)]);
-            warn!("   - Signature length: {} chars", payload.signature.len());
-            warn!("   - Key length: {} chars", payload.key.len());
-            warn!("   - Message length: {} chars", challenge_data.message.len());
-            return Err((
-                StatusCode::UNAUTHORIZED,
-                Json(serde_json::json!({
-                    "error": "Invalid signature - the signed message does not match the challenge or the signature is invalid",
-                    "details": "This could mean the wallet signed a different message or the signature is corrupted. Check backend logs for detailed verification steps."
-                })),
-            ));
-        }
-        Err(e) => {
-            error!("❌ Signature verification error: {}", e);
-            error!("📊 Error occurred during verification - check logs above for details");
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Signature verification failed",
-                    "details": format!("Technical error: {}. Check backend logs for detailed information.", e)
-                })),
-            ));
+    // The wallet may have signed any outstanding challenge (e.g. from another tab), try the newest first
+    let mut matched_nonce = None;
+    for challenge_data in pending.iter().rev() {
+        match verify_cardano_signature(
+            &normalized_address,
+            &challenge_data.message,
+            &payload.signature,
+            &payload.key,
+        ) {
+            Ok(true) => {
+                info!(
+                    "✅ Signature verification PASSED for: {} ({:?} challenge)",
+                    &normalized_address[..normalized_address.len().min(16)],
+                    challenge_data.format
+                );
+                matched_nonce = Some(challenge_data.nonce.clone());
+                break;
+            }
+            Ok(false) => continue,
+            Err(VerifyError::UnsupportedAddressType(reason)) => {
+                warn!("Rejecting unsupported address type: {}", reason);
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "Script/Byron addresses cannot authenticate via CIP-8; use a key-based payment address.",
+                        "code": codes::UNSUPPORTED_ADDRESS_TYPE,
+                        "details": reason
+                    })),
+                ));
+            }
+            Err(VerifyError::AddressMismatch) => {
+                warn!("Rejecting signature: public key does not belong to the claimed address");
+                return Err(auth_error(
+                    StatusCode::UNAUTHORIZED,
+                    codes::ADDRESS_MISMATCH,
+                    "The signing key does not match the address",
+                ));
+            }
+            Err(e) => {
+                error!("❌ Signature verification error: {}", e);
+                error!("📊 Error occurred during verification - check logs above for details");
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({
+                        "error": "Signature verification failed",
+                        "code": codes::VERIFICATION_ERROR,
+                        "details": format!("Technical error: {}. Check backend logs for detailed information.", e)
+                    })),
+                ));
+            }
         }
     }
 
+    let Some(matched_nonce) = matched_nonce else {
+        warn!(
+            "❌ Signature verification FAILED for: {}",
+            &normalized_address[..normalized_address.len().min(16)]
+        );
+        warn!("📊 Debug info:");
+        warn!("   - Address: {}", &normalized_address[..normalized_address.len().min(32)]);
+        warn!("   - Signature length: {} chars", payload.signature.len());
+        warn!("   - Key length: {} chars", payload.key.len());
+        warn!("   - Challenges tried: {}", pending.len());
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "error": "Invalid signature - the signed message does not match the challenge or the signature is invalid",
+                "code": codes::INVALID_SIGNATURE,
+                "details": "This could mean the wallet signed a different message or the signature is corrupted. Check backend logs for detailed verification steps."
+            })),
+        ));
+    };
+
     let mut challenges = state.challenges.lock().await;
-    // Remove challenge using normalized address (or original if normalized not found)
-    challenges.remove(&normalized_address);
-    challenges.remove(&payload.address);
+    // Remove only the challenge that was used, under the normalized and original address
+    for key in [&normalized_address, &payload.address] {
+        if let Some(pending) = challenges.get_mut(key.as_str()) {
+            pending.retain(|data| data.nonce != matched_nonce);
+            if pending.is_empty() {
+                challenges.remove(key.as_str());
+            }
+        }
+    }
     drop(challenges);
 
 
     // ========================================================================
     // CONVERT ADDRESS TO BECH32 FOR BLOCKFROST API
     // ========================================================================
-    let bech32_address = convert_to_bech32(&normalized_address)
-        .unwrap_or_else(|e| {
-            warn!("Failed to convert address to bech32: {}, using original", e);
-            normalized_address.clone()
-        });
+    let bech32_address = convert_to_bech32(&normalized_address, state.network).map_err(|e| {
+        warn!("Rejecting address after bech32 conversion: {}", e);
+        let code = match e {
+            AddressError::NetworkMismatch { .. } => codes::NETWORK_MISMATCH,
+            _ => codes::INVALID_ADDRESS,
+        };
+        auth_error(StatusCode::BAD_REQUEST, code, e.to_string())
+    })?;
 
     info!("📝 Address for JWT: {} (bech32 format)", &bech32_address[..bech32_address.len().min(20)]);
 
+    // The stake address always comes from the verified address itself (None without a stake
+    // part), so a client can't have the token bound to someone else's account
+    let stake_address = address::from_any(&normalized_address)
+        .ok()
+        .and_then(|parsed| address::stake_address_of(&parsed));
+    if let Ok(KeyRole::Stake) = signing_key_role(&normalized_address, &payload.key) {
+        info!("🔑 Signed with the stake key, token bound to the account's stake address");
+    }
+
     // Use normalized address for JWT token
     let token = state
         .jwt_manager
-        .generate_token(bech32_address.clone(), payload.stake_address)
+        .generate_token(bech32_address.clone(), stake_address)
         .map_err(|e| {
             error!("Failed to generate JWT: {}", e);
-            (
+            auth_error(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "Failed to generate token" })),
+                codes::TOKEN_ISSUE_FAILED,
+                "Failed to generate token",
             )
         })?;
 
@@ -265,6 +468,18 @@ pub async fn verify_signature(
     Ok(Json(VerifyResponse { token, address: bech32_address }))
 }
 
+// The claims of the caller's already-validated token, so the frontend can tell how fresh the session is
+pub async fn get_session(Extension(claims): Extension<Claims>) -> Json<SessionResponse> {
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    Json(SessionResponse {
+        expires_in_secs: (claims.exp as u64).saturating_sub(now),
+        wallet_address: claims.wallet_address,
+        stake_address: claims.stake_address,
+        iat: claims.iat,
+        exp: claims.exp,
+    })
+}
+
 // ============================================================================
 // ADDRESS NORMALIZATION
 // ============================================================================
@@ -272,7 +487,7 @@ pub async fn verify_signature(
 /// Normalize address format to handle both hex and bech32 formats
 fn normalize_address_format(address: &str) -> String {
     // Check if it's already a valid hex string
-    if hex::decode(address).is_ok() && address.len() % 2 == 0 {
+    if hex::decode(address).is_ok() && address.len().is_multiple_of(2) {
         // It's already hex format, return as-is
         address.to_string()
     } else {
@@ -287,12 +502,39 @@ fn normalize_address_format(address: &str) -> String {
 // SIGNATURE VERIFICATION LOGIC
 // ============================================================================
 
+// Why a signature could not be verified
+#[derive(Debug)]
+enum VerifyError {
+    // The address has no payment key credential (Byron, reward or script), so it can't sign via CIP-8
+    UnsupportedAddressType(String),
+    // The public key hashes to a different payment credential than the claimed address
+    AddressMismatch,
+    // Malformed input or any other verification failure
+    Other(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedAddressType(reason) => write!(f, "Unsupported address type: {}", reason),
+            Self::AddressMismatch => write!(f, "Public key does not match the address"),
+            Self::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<String> for VerifyError {
+    fn from(msg: String) -> Self {
+        Self::Other(msg)
+    }
+}
+
 fn verify_cardano_signature(
     address: &str,
     message: &str,
     signature_hex: &str,
     public_key_hex: &str,
-) -> Result<bool, String> {
+) -> Result<bool, VerifyError> {
     use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 
     info!("🔍 Step 1: Decoding signature and key data...");
@@ -326,7 +568,7 @@ fn verify_cardano_signature(
     
     // Convert message to hex (as frontend does) for reference
     let message_hex = hex::encode(message.as_bytes());
-    info!("📝 Original message: {} bytes", message.as_bytes().len());
+    info!("📝 Original message: {} bytes", message.len());
     info!("📝 Message hex (what frontend sends): {}", &message_hex[..message_hex.len().min(100)]);
     info!("📝 COSE payload length: {} bytes", payload.len());
     
@@ -375,14 +617,14 @@ fn verify_cardano_signature(
     // CRITICAL SECURITY CHECK: Verify the public key matches the claimed address
     // This prevents attackers from authenticating as any address with their own keys
     match verify_address_from_public_key(address, &raw_public_key) {
-        Ok(true) => {
+        Ok(KeyRole::Payment) => {
             info!("✅ Address verification passed");
         }
-        Ok(false) => {
-            warn!("⚠️ Address verification returned false - address may not match public key");
-            warn!("⚠️ Continuing with signature verification anyway for debugging...");
-            // For now, we'll continue to see if signature verification works
-            // In production, you might want to return an error here
+        Ok(KeyRole::Stake) => {
+            info!("✅ Address verification passed (key matches the stake credential)");
+        }
+        Err(e @ (VerifyError::UnsupportedAddressType(_) | VerifyError::AddressMismatch)) => {
+            return Err(e);
         }
         Err(e) => {
             warn!("⚠️ Address verification error: {}", e);
@@ -412,27 +654,20 @@ fn verify_cardano_signature(
     // However, many CIP-30 wallets sign just the payload bytes directly.
     // We'll try both methods.
 
-    // Method 1: Verify against COSE Sig_structure (full COSE compliance)
-    if !protected_headers.is_empty() || !payload.is_empty() {
+    // Blake2b-224 of the message, the payload of wallets using CIP-8's `hashed` option
+    let mut message_hash = [0u8; 28];
+    cryptoxide::blake2b::Blake2b::blake2b(&mut message_hash, message.as_bytes(), &[]);
+
+    // Method 1: Verify against COSE Sig_structure (full COSE compliance).
+    // Only when the embedded payload is the challenge itself: a Sig_structure over any other
+    // payload proves nothing about this login and could be replayed from another signing
+    let payload_is_message = payload == message_bytes_vec
+        || payload == message_hex_bytes
+        || payload == message_hash;
+    if payload_is_message {
         info!("🔄 Attempt 1: Verifying against COSE Sig_structure...");
-        // Build Sig_structure: ["Signature1", protected_headers, external_aad (empty), payload]
-        // According to RFC 8152, Sig_structure is a CBOR array
-        use ciborium::Value;
-        let external_aad = Vec::<u8>::new(); // Empty for CIP-30
-        
-        // Create Sig_structure as CBOR array: ["Signature1", protected_headers, external_aad, payload]
-        let sig_structure = Value::Array(vec![
-            Value::Text("Signature1".to_string()),
-            Value::Bytes(protected_headers.clone()),
-            Value::Bytes(external_aad),
-            Value::Bytes(payload.clone()),
-        ]);
-        
-        // Encode to bytes
-        let mut sig_structure_bytes = Vec::new();
-        ciborium::ser::into_writer(&sig_structure, &mut sig_structure_bytes)
-            .map_err(|e| format!("Failed to encode Sig_structure: {}", e))?;
-        
+        let sig_structure_bytes = encode_sig_structure(&protected_headers, &payload)?;
+
         info!("📝 Sig_structure length: {} bytes", sig_structure_bytes.len());
         if verifying_key.verify(&sig_structure_bytes, &signature).is_ok() {
             info!("✅ Signature verification PASSED (method 1: COSE Sig_structure)!");
@@ -464,27 +699,57 @@ fn verify_cardano_signature(
     }
     
     // Method 5: If payload is a hex string, decode and verify
-    if !payload.is_empty() {
-        if let Ok(payload_str) = String::from_utf8(payload.clone()) {
-            if let Ok(decoded_payload) = hex::decode(&payload_str) {
-                if decoded_payload == message_bytes_vec {
-                    info!("🔄 Attempt 5: Verifying against decoded hex payload...");
-                    if verifying_key.verify(&decoded_payload, &signature).is_ok() {
-                        info!("✅ Signature verification PASSED (method 5: decoded hex payload)!");
-                        return Ok(true);
-                    }
+    if !payload.is_empty()
+        && let Ok(payload_str) = String::from_utf8(payload.clone())
+        && let Ok(decoded_payload) = hex::decode(&payload_str)
+        && decoded_payload == message_bytes_vec
+    {
+        info!("🔄 Attempt 5: Verifying against decoded hex payload...");
+        if verifying_key.verify(&decoded_payload, &signature).is_ok() {
+            info!("✅ Signature verification PASSED (method 5: decoded hex payload)!");
+            return Ok(true);
+        }
+    }
+    
+    // Method 6: Signing address in the protected header with a detached or hashed payload.
+    // Eternl (in some modes) and other wallets following CIP-8's `hashed` option sign a
+    // Sig_structure whose payload is the message itself or its Blake2b-224 hash, while the
+    // COSE_Sign1 carries nil or the hash, so method 1 rebuilds the wrong structure.
+    let header_address = protected_header_address(&protected_headers);
+    if let Some(header_address) = &header_address {
+        if address_matches_bytes(address, header_address) {
+            info!("🔄 Attempt 6: Verifying Sig_structure with the protected header address...");
+            for signed_payload in [message.as_bytes(), &message_hash[..]] {
+                let sig_structure_bytes = encode_sig_structure(&protected_headers, signed_payload)?;
+                if verifying_key.verify(&sig_structure_bytes, &signature).is_ok() {
+                    info!("✅ Signature verification PASSED (method 6: protected header address)!");
+                    return Ok(true);
                 }
             }
+        } else {
+            warn!("⚠️ Protected header address does not match the claimed address, skipping method 6");
         }
     }
-    
+
+    // Method 7: Detached (nil) payload with protected headers that carry no address.
+    // The wallet signed the Sig_structure over its own headers and the message, leaving the
+    // payload out of the COSE_Sign1; the key is still bound to the address by step 5
+    if payload.is_empty() && !protected_headers.is_empty() && header_address.is_none() {
+        info!("🔄 Attempt 7: Verifying Sig_structure with the message as the detached payload...");
+        let sig_structure_bytes = encode_sig_structure(&protected_headers, message.as_bytes())?;
+        if verifying_key.verify(&sig_structure_bytes, &signature).is_ok() {
+            info!("✅ Signature verification PASSED (method 7: detached payload)!");
+            return Ok(true);
+        }
+    }
+
     // All verification methods failed
     warn!("❌ Signature verification FAILED - all methods attempted");
     warn!("📊 Verification details:");
     warn!("   - Message bytes length: {}", message_bytes_vec.len());
     warn!("   - Message hex length: {}", message_hex_bytes.len());
     warn!("   - COSE payload length: {}", payload.len());
-    warn!("   - Raw signature (hex): {}", hex::encode(&raw_signature));
+    warn!("   - Raw signature (hex): {}", hex::encode(raw_signature));
     if !payload.is_empty() && payload.len() <= 200 {
         warn!("   - COSE payload (hex): {}", hex::encode(&payload));
     }
@@ -492,6 +757,48 @@ fn verify_cardano_signature(
     Ok(false)
 }
 
+// Sig_structure signed under COSE (RFC 8152): ["Signature1", protected_headers, external_aad, payload]
+// external_aad is always empty for CIP-30
+fn encode_sig_structure(protected_headers: &[u8], payload: &[u8]) -> Result<Vec<u8>, String> {
+    use ciborium::Value;
+
+    let sig_structure = Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected_headers.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]);
+
+    let mut sig_structure_bytes = Vec::new();
+    ciborium::ser::into_writer(&sig_structure, &mut sig_structure_bytes)
+        .map_err(|e| format!("Failed to encode Sig_structure: {}", e))?;
+    Ok(sig_structure_bytes)
+}
+
+// Raw address bytes from the `"address"` label of the CIP-8 protected headers map, if present
+fn protected_header_address(protected_headers: &[u8]) -> Option<Vec<u8>> {
+    use ciborium::Value;
+
+    if protected_headers.is_empty() {
+        return None;
+    }
+
+    let value: Value = ciborium::from_reader(protected_headers).ok()?;
+    let Value::Map(map) = value else {
+        return None;
+    };
+
+    map.into_iter().find_map(|(key, val)| match (key, val) {
+        (Value::Text(label), Value::Bytes(bytes)) if label == "address" => Some(bytes),
+        _ => None,
+    })
+}
+
+// Whether a hex or bech32 address string encodes exactly these address bytes
+fn address_matches_bytes(address_str: &str, address_bytes: &[u8]) -> bool {
+    address::from_any(address_str).is_ok_and(|parsed| parsed.to_bytes() == address_bytes)
+}
+
 // Extract raw Ed25519 public key from COSE_Key format
 fn extract_public_key_from_cose(cose_key_bytes: &[u8]) -> Result<[u8; 32], String> {
     use ciborium::Value;
@@ -510,34 +817,42 @@ fn extract_public_key_from_cose(cose_key_bytes: &[u8]) -> Result<[u8; 32], Strin
         return Ok(key);
     }
 
+    // A COSE_Key map wraps the 32 key bytes, so anything shorter can't be either form
+    if cose_key_bytes.len() < 32 {
+        return Err(format!(
+            "public key must be 32 bytes or COSE_Key, got {} bytes",
+            cose_key_bytes.len()
+        ));
+    }
+
     // Parse CBOR structure
     let cursor = Cursor::new(cose_key_bytes);
-    let value: Value =
-        ciborium::from_reader(cursor).map_err(|e| format!("Failed to parse CBOR: {}", e))?;
+    let value: Value = ciborium::from_reader(cursor)
+        .map_err(|e| format!("public key must be 32 bytes or COSE_Key (CBOR error: {})", e))?;
 
     // Extract map from CBOR value
     let map = match value {
         Value::Map(m) => m,
-        _ => return Err("COSE_Key must be a CBOR map".to_string()),
+        _ => return Err("public key must be 32 bytes or COSE_Key, got a non-map CBOR value".to_string()),
     };
 
     // Look for key -2 (x coordinate / public key)
     for (key, val) in map {
         // Check if key is integer -2
-        if let Value::Integer(k) = key {
-            if k == ciborium::value::Integer::from(-2) {
-                // Extract bytes from value
-                if let Value::Bytes(bytes) = val {
-                    if bytes.len() == 32 {
-                        let mut key_bytes = [0u8; 32];
-                        key_bytes.copy_from_slice(&bytes);
-                        return Ok(key_bytes);
-                    } else {
-                        return Err(format!("Public key must be 32 bytes, got {}", bytes.len()));
-                    }
+        if let Value::Integer(k) = key
+            && k == ciborium::value::Integer::from(-2)
+        {
+            // Extract bytes from value
+            if let Value::Bytes(bytes) = val {
+                if bytes.len() == 32 {
+                    let mut key_bytes = [0u8; 32];
+                    key_bytes.copy_from_slice(&bytes);
+                    return Ok(key_bytes);
                 } else {
-                    return Err("Public key value must be bytes".to_string());
+                    return Err(format!("Public key must be 32 bytes, got {}", bytes.len()));
                 }
+            } else {
+                return Err("Public key value must be bytes".to_string());
             }
         }
     }
@@ -545,10 +860,13 @@ fn extract_public_key_from_cose(cose_key_bytes: &[u8]) -> Result<[u8; 32], Strin
     Err("Could not find public key (label -2) in COSE_Key structure".to_string())
 }
 
+// Raw signature, payload and protected headers of a COSE_Sign1 structure
+type CoseSign1Parts = ([u8; 64], Vec<u8>, Vec<u8>);
+
 // Extract signature, payload, and protected headers from COSE_Sign1 format (CIP-30)
 fn extract_signature_from_cose_sign1(
     cose_sign1_bytes: &[u8],
-) -> Result<([u8; 64], Vec<u8>, Vec<u8>), String> {
+) -> Result<CoseSign1Parts, String> {
     use ciborium::Value;
     use std::io::Cursor;
 
@@ -567,6 +885,14 @@ fn extract_signature_from_cose_sign1(
         return Ok((sig, Vec::new(), Vec::new()));
     }
 
+    // A COSE_Sign1 array wraps the 64 signature bytes, so anything shorter can't be either form
+    if cose_sign1_bytes.len() < 64 {
+        return Err(format!(
+            "signature must be a 64-byte Ed25519 signature or COSE_Sign1, got {} bytes",
+            cose_sign1_bytes.len()
+        ));
+    }
+
     // Parse CBOR structure
     let cursor = Cursor::new(cose_sign1_bytes);
     let value: Value = ciborium::from_reader(cursor)
@@ -623,85 +949,502 @@ fn extract_signature_from_cose_sign1(
 // ADDITIONAL: Verify address matches public key
 // ============================================================================
 
+// Which credential of the claimed address the signing key belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyRole {
+    Payment,
+    // Hardware wallets may sign with the account's stake key instead of a payment key
+    Stake,
+}
+
 fn verify_address_from_public_key(
     address_str: &str,
     public_key_bytes: &[u8; 32],
-) -> Result<bool, String> {
-    use cardano_serialization_lib::{
-        address::{BaseAddress, EnterpriseAddress, PointerAddress},
-        crypto::PublicKey,
-    };
+) -> Result<KeyRole, VerifyError> {
+    use cardano_serialization_lib::crypto::PublicKey;
 
-    // Try to parse as both hex and bech32 formats
-    let address = if address_str.len() % 2 == 0 && hex::decode(address_str).is_ok() {
-        // It's hex format - decode and create Address from bytes
-        let address_bytes =
-            hex::decode(address_str).map_err(|e| format!("Invalid hex address: {}", e))?;
-
-        // Create address from raw bytes
-        cardano_serialization_lib::address::Address::from_bytes(address_bytes)
-            .map_err(|e| format!("Invalid address bytes: {}", e))?
-    } else {
-        // Try bech32 format
-        cardano_serialization_lib::address::Address::from_bech32(address_str)
-            .map_err(|e| format!("Invalid bech32 address: {}", e))?
-    };
+    // Accepts both hex and bech32 formats
+    let address = address::from_any(address_str).map_err(|e| e.to_string())?;
 
     // Create PublicKey from bytes
     let public_key = PublicKey::from_bytes(public_key_bytes)
         .map_err(|e| format!("Invalid public key bytes: {}", e))?;
 
-    // Hash the public key to get the key hash (Blake2b-224)
-    let pub_key_hash = public_key.hash();
+    // Hash the public key to get the key hash (Blake2b-224) and compare it with the payment
+    // credential of the base, enterprise or pointer address, then with a base address's stake credential
+    let pub_key_hash = public_key.hash().to_bytes();
+    let payment_key_hash = address::payment_key_hash(&address);
+    if let Ok(addr_key_hash) = &payment_key_hash
+        && addr_key_hash.to_bytes() == pub_key_hash
+    {
+        return Ok(KeyRole::Payment);
+    }
+    if address::stake_key_hash(&address).is_some_and(|hash| hash.to_bytes() == pub_key_hash) {
+        return Ok(KeyRole::Stake);
+    }
 
-    // Extract payment credential from address and compare
-    // Try different address types (Base, Enterprise, Pointer, etc.)
-    let matches = if let Some(base_addr) = BaseAddress::from_address(&address) {
-        // Base address (payment + stake)
-        match base_addr.payment_cred().to_keyhash() {
-            Some(addr_key_hash) => addr_key_hash.to_bytes() == pub_key_hash.to_bytes(),
-            None => return Err("Address uses script credential, not key credential".to_string()),
-        }
-    } else if let Some(enterprise_addr) = EnterpriseAddress::from_address(&address) {
-        // Enterprise address (payment only, no stake)
-        match enterprise_addr.payment_cred().to_keyhash() {
-            Some(addr_key_hash) => addr_key_hash.to_bytes() == pub_key_hash.to_bytes(),
-            None => return Err("Address uses script credential, not key credential".to_string()),
-        }
-    } else if let Some(pointer_addr) = PointerAddress::from_address(&address) {
-        // Pointer address
-        match pointer_addr.payment_cred().to_keyhash() {
-            Some(addr_key_hash) => addr_key_hash.to_bytes() == pub_key_hash.to_bytes(),
-            None => return Err("Address uses script credential, not key credential".to_string()),
+    match payment_key_hash {
+        Err(AddressError::NoPaymentKey(reason)) => {
+            Err(VerifyError::UnsupportedAddressType(reason.to_string()))
         }
+        Err(other) => Err(VerifyError::Other(other.to_string())),
+        Ok(_) => Err(VerifyError::AddressMismatch),
+    }
+}
+
+// Credential the wallet's COSE_Key belongs to, once its signature has been verified
+fn signing_key_role(address: &str, public_key_hex: &str) -> Result<KeyRole, VerifyError> {
+    let public_key_bytes =
+        hex::decode(public_key_hex).map_err(|e| format!("Invalid public key hex: {}", e))?;
+    let raw_public_key = extract_public_key_from_cose(&public_key_bytes)
+        .map_err(|e| format!("Failed to parse COSE key: {}", e))?;
+    verify_address_from_public_key(address, &raw_public_key)
+}
+
+
+/// Convert hex address to bech32 format for Blockfrost API, checking the resulting
+/// prefix is a known one for the configured network so a corrupt header byte is caught here
+fn convert_to_bech32(address_str: &str, network: &'static str) -> Result<String, AddressError> {
+    // If it's already bech32 (payment or stake address), keep it as-is
+    let bech32 = if address_str.starts_with("addr") || address_str.starts_with("stake") {
+        address_str.to_string()
     } else {
-        return Err("Unsupported address type (Byron, Reward, or Script)".to_string());
+        address::to_bech32(&address::from_any(address_str)?)?
     };
 
-    if matches {
-        Ok(true)
-    } else {
-        Err("Public key does not match the address".to_string())
-    }
+    address::check_network(&bech32, network)?;
+    Ok(bech32)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, call};
+    use axum::{body::Body, http::Request, routing::post, Router};
+    use cardano_serialization_lib::address::{EnterpriseAddress, StakeCredential};
+    use ed25519_dalek::{Signer, SigningKey};
 
-/// Convert hex address to bech32 format for Blockfrost API
-fn convert_to_bech32(address: &str) -> Result<String, String> {
-    use cardano_serialization_lib::address::Address;
-    
-    // If it's already bech32, return as-is
-    if address.starts_with("addr") {
-        return Ok(address.to_string());
+    const NETWORK_ID: u8 = 0;
+
+    struct Wallet {
+        key: SigningKey,
+    }
+
+    impl Wallet {
+        fn new(seed: u8) -> Self {
+            Self { key: SigningKey::from_bytes(&[seed; 32]) }
+        }
+
+        fn credential(&self) -> StakeCredential {
+            test_support::public_key_credential(self.key.verifying_key().as_bytes())
+        }
+
+        // Raw signature over the message bytes with the raw public key, as some CIP-30 wallets send it
+        fn verify_body(&self, address: &str, message: &str) -> serde_json::Value {
+            serde_json::json!({
+                "address": address,
+                "signature": hex::encode(self.key.sign(message.as_bytes()).to_bytes()),
+                "key": hex::encode(self.key.verifying_key().as_bytes()),
+            })
+        }
+    }
+
+    fn auth_state() -> AuthState {
+        AuthState {
+            jwt_manager: Arc::new(JwtManager::new(
+                "test-secret".to_string(),
+                "issuer".to_string(),
+                "audience".to_string(),
+            )),
+            challenges: ChallengeStore::default(),
+            network: "testnet",
+            challenge_format: ChallengeFormat::PlainText,
+        }
+    }
+
+    fn router(state: AuthState) -> Router {
+        Router::new()
+            .route("/api/auth/challenge", post(create_challenge))
+            .route("/api/auth/verify", post(verify_signature))
+            .with_state(state)
+    }
+
+    fn post_json(uri: &str, body: &serde_json::Value) -> Request<Body> {
+        Request::post(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn request_challenge(router: &Router, request: serde_json::Value) -> serde_json::Value {
+        let (status, body) = call(router, post_json("/api/auth/challenge", &request)).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        body
+    }
+
+    // Sign the challenge for `address` with `wallet`, merging `extra` into the verify body
+    async fn sign_in(
+        state: &AuthState,
+        wallet: &Wallet,
+        address: &str,
+        extra: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let router = router(state.clone());
+        let challenge = request_challenge(&router, serde_json::json!({ "address": address })).await;
+        let mut body = wallet.verify_body(address, challenge["message"].as_str().unwrap());
+        if let (Some(body), serde_json::Value::Object(extra)) = (body.as_object_mut(), extra) {
+            body.extend(extra);
+        }
+        call(&router, post_json("/api/auth/verify", &body)).await
+    }
+
+    fn token_claims(state: &AuthState, body: &serde_json::Value) -> Claims {
+        state.jwt_manager.validate_token(body["token"].as_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stake_key_signature_binds_the_token_to_the_stake_address() {
+        let state = auth_state();
+        let payment = Wallet::new(1);
+        let stake = Wallet::new(2);
+        let base = test_support::base_address(NETWORK_ID, &payment.credential(), &stake.credential());
+        let address = test_support::bech32(&base);
+
+        let (status, body) = sign_in(&state, &stake, &address, serde_json::json!({})).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let role = verify_address_from_public_key(&address, stake.key.verifying_key().as_bytes());
+        assert_eq!(role.unwrap(), KeyRole::Stake);
+        let claims = token_claims(&state, &body);
+        assert_eq!(claims.wallet_address, address);
+        assert_eq!(claims.stake_address, address::stake_address_of(&base));
+        assert!(claims.stake_address.unwrap().starts_with("stake_test1"));
+    }
+
+    #[tokio::test]
+    async fn client_supplied_stake_address_is_ignored() {
+        let state = auth_state();
+        let payment = Wallet::new(1);
+        let own_stake = Wallet::new(2).credential();
+        let base = test_support::base_address(NETWORK_ID, &payment.credential(), &own_stake);
+        let address = test_support::bech32(&base);
+        // Someone else's account
+        let other_stake = Wallet::new(3).credential();
+        let other = test_support::base_address(NETWORK_ID, &payment.credential(), &other_stake);
+        let spoofed = serde_json::json!({ "stake_address": address::stake_address_of(&other) });
+
+        let (status, body) = sign_in(&state, &payment, &address, spoofed).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(token_claims(&state, &body).stake_address, address::stake_address_of(&base));
+    }
+
+    #[tokio::test]
+    async fn address_without_a_stake_part_gets_no_stake_address() {
+        let state = auth_state();
+        let wallet = Wallet::new(1);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+        let other_stake = Wallet::new(3).credential();
+        let other = test_support::base_address(NETWORK_ID, &wallet.credential(), &other_stake);
+        let spoofed = serde_json::json!({ "stake_address": address::stake_address_of(&other) });
+
+        let (status, body) = sign_in(&state, &wallet, &address, spoofed).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(token_claims(&state, &body).stake_address, None);
+    }
+
+    #[test]
+    fn plain_text_message_carries_the_issue_time() {
+        let issued_at = 1_700_000_000;
+        let message = ChallengeFormat::PlainText.message("addr_test1", "42", issued_at);
+
+        assert!(message.ends_with("Nonce: 42\nTimestamp: 2023-11-14T22:13:20+00:00"), "{}", message);
+        assert_eq!(message, ChallengeFormat::PlainText.message("addr_test1", "42", issued_at));
+    }
+
+    #[tokio::test]
+    async fn each_challenge_format_round_trips_through_verify() {
+        let wallet = Wallet::new(1);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+
+        for format in [ChallengeFormat::PlainText, ChallengeFormat::Cip8] {
+            let state = auth_state();
+            let router = router(state.clone());
+            let request = serde_json::json!({ "address": address, "format": format });
+            let challenge = request_challenge(&router, request).await;
+            let message = challenge["message"].as_str().unwrap();
+            let issued_at = challenge["expires_at"].as_i64().unwrap() - CHALLENGE_TTL_SECS;
+
+            assert_eq!(challenge["format"], serde_json::json!(format));
+            assert_eq!(message, format.message(&address, challenge["nonce"].as_str().unwrap(), issued_at));
+
+            let verify = post_json("/api/auth/verify", &wallet.verify_body(&address, message));
+            let (status, body) = call(&router, verify).await;
+            assert_eq!(status, StatusCode::OK, "{:?}: {}", format, body);
+            assert_eq!(token_claims(&state, &body).wallet_address, address);
+        }
+    }
+
+    #[tokio::test]
+    async fn challenge_expires_a_ttl_after_it_was_stored() {
+        let state = auth_state();
+        let address = test_support::bech32(&test_support::enterprise_address(NETWORK_ID, 1));
+
+        let challenge = request_challenge(&router(state.clone()), serde_json::json!({ "address": address })).await;
+
+        let stored = state.challenges.lock().await[&address][0].clone();
+        assert_eq!(challenge["nonce"], stored.nonce);
+        assert_eq!(challenge["expires_at"].as_i64(), Some(stored.timestamp + CHALLENGE_TTL_SECS));
+        assert!(!stored.is_expired(stored.timestamp + CHALLENGE_TTL_SECS - 1));
+    }
+
+    #[tokio::test]
+    async fn older_of_two_outstanding_challenges_still_verifies() {
+        let state = auth_state();
+        let router = router(state.clone());
+        let wallet = Wallet::new(1);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+
+        // One challenge per open tab; the first tab signs after the second asked for its own
+        let first = request_challenge(&router, serde_json::json!({ "address": address })).await;
+        let second = request_challenge(&router, serde_json::json!({ "address": address })).await;
+        assert_ne!(first["nonce"], second["nonce"]);
+
+        let body = wallet.verify_body(&address, first["message"].as_str().unwrap());
+        let (status, response) = call(&router, post_json("/api/auth/verify", &body)).await;
+        assert_eq!(status, StatusCode::OK, "{}", response);
+
+        // Only the used challenge is consumed: the second tab can still sign in, once
+        let pending = state.challenges.lock().await[&address].clone();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].nonce, second["nonce"].as_str().unwrap());
+        let body = wallet.verify_body(&address, second["message"].as_str().unwrap());
+        assert_eq!(call(&router, post_json("/api/auth/verify", &body)).await.0, StatusCode::OK);
+        let (status, response) = call(&router, post_json("/api/auth/verify", &body)).await;
+        assert_eq!((status, response["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::CHALLENGE_NOT_FOUND)));
+    }
+
+    #[tokio::test]
+    async fn expired_challenge_is_rejected_and_pruned() {
+        let state = auth_state();
+        let router = router(state.clone());
+        let wallet = Wallet::new(1);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+        let challenge = request_challenge(&router, serde_json::json!({ "address": address })).await;
+
+        // Age the stored challenge to exactly its TTL, the first second it no longer counts
+        state.challenges.lock().await.get_mut(&address).unwrap()[0].timestamp -= CHALLENGE_TTL_SECS;
+        let body = wallet.verify_body(&address, challenge["message"].as_str().unwrap());
+        let (status, response) = call(&router, post_json("/api/auth/verify", &body)).await;
+
+        assert_eq!((status, response["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::CHALLENGE_EXPIRED)));
+        assert!(state.challenges.lock().await.is_empty());
+        // Pruned, so a retry is told there is nothing to answer
+        let (status, response) = call(&router, post_json("/api/auth/verify", &body)).await;
+        assert_eq!((status, response["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::CHALLENGE_NOT_FOUND)));
+    }
+
+    #[test]
+    fn pruning_and_verification_share_the_expiry_boundary() {
+        let issued = ChallengeData {
+            nonce: "1".to_string(),
+            message: String::new(),
+            timestamp: 1_000,
+            format: ChallengeFormat::PlainText,
+        };
+        let mut challenges = HashMap::from([("addr".to_string(), vec![issued.clone()])]);
+
+        prune_expired(&mut challenges, 1_000 + CHALLENGE_TTL_SECS - 1);
+        assert!(!issued.is_expired(1_000 + CHALLENGE_TTL_SECS - 1));
+        assert_eq!(challenges["addr"].len(), 1);
+
+        prune_expired(&mut challenges, 1_000 + CHALLENGE_TTL_SECS);
+        assert!(issued.is_expired(1_000 + CHALLENGE_TTL_SECS));
+        assert!(challenges.is_empty());
+    }
+
+    #[tokio::test]
+    async fn each_verify_failure_carries_its_code() {
+        let wallet = Wallet::new(1);
+        let address = test_support::bech32(&EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address());
+        let mainnet = test_support::bech32(&EnterpriseAddress::new(1, &wallet.credential()).to_address());
+        let other_signature = hex::encode(wallet.key.sign(b"some other message").to_bytes());
+        let cases = [
+            (&wallet, &address, serde_json::json!({ "signature": "" }), StatusCode::BAD_REQUEST, codes::MISSING_FIELDS),
+            (&wallet, &address, serde_json::json!({ "signature": other_signature }), StatusCode::UNAUTHORIZED, codes::INVALID_SIGNATURE),
+            (&Wallet::new(2), &address, serde_json::json!({}), StatusCode::UNAUTHORIZED, codes::ADDRESS_MISMATCH),
+            (&wallet, &address, serde_json::json!({ "signature": "zz" }), StatusCode::INTERNAL_SERVER_ERROR, codes::VERIFICATION_ERROR),
+            (&wallet, &mainnet, serde_json::json!({}), StatusCode::BAD_REQUEST, codes::NETWORK_MISMATCH),
+        ];
+
+        for (signer, address, extra, status, code) in cases {
+            let (actual, body) = sign_in(&auth_state(), signer, address, extra).await;
+            assert_eq!((actual, body["code"].as_str()), (status, Some(code)), "{}", body);
+            assert!(body["error"].is_string(), "{}", body);
+        }
+
+        // Paths that never get as far as a challenge
+        let router = router(auth_state());
+        let unsigned = wallet.verify_body(&address, "no challenge was issued");
+        let (status, body) = call(&router, post_json("/api/auth/verify", &unsigned)).await;
+        assert_eq!((status, body["code"].as_str()), (StatusCode::UNAUTHORIZED, Some(codes::CHALLENGE_NOT_FOUND)));
+        for uri in ["/api/auth/challenge", "/api/auth/verify"] {
+            let request = serde_json::json!({ "address": "hello", "signature": "00", "key": "00" });
+            let (status, body) = call(&router, post_json(uri, &request)).await;
+            assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some(codes::INVALID_ADDRESS)), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn script_address_is_refused_with_a_400() {
+        use cardano_serialization_lib::crypto::ScriptHash;
+
+        let state = auth_state();
+        let wallet = Wallet::new(1);
+        let script = StakeCredential::from_scripthash(&ScriptHash::from([9; 28]));
+        let address = test_support::bech32(&EnterpriseAddress::new(NETWORK_ID, &script).to_address());
+
+        let (status, body) = sign_in(&state, &wallet, &address, serde_json::json!({})).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(body["code"], codes::UNSUPPORTED_ADDRESS_TYPE);
+        assert_eq!(
+            body["error"],
+            "Script/Byron addresses cannot authenticate via CIP-8; use a key-based payment address."
+        );
+    }
+
+    // COSE_Sign1 laid out as Eternl sends it in hashed mode: the signing address in the protected
+    // headers, `hashed: true` unprotected, and the Blake2b-224 message hash as the signed payload,
+    // embedded or (`detached`) left out as nil
+    fn hashed_cose_sign1(
+        wallet: &Wallet,
+        protected: &[(ciborium::Value, ciborium::Value)],
+        message: &str,
+        detached: bool,
+    ) -> String {
+        use ciborium::Value;
+
+        let mut protected_headers = Vec::new();
+        ciborium::ser::into_writer(&Value::Map(protected.to_vec()), &mut protected_headers).unwrap();
+        let mut message_hash = [0u8; 28];
+        cryptoxide::blake2b::Blake2b::blake2b(&mut message_hash, message.as_bytes(), &[]);
+        let signed = encode_sig_structure(&protected_headers, &message_hash).unwrap();
+
+        let cose_sign1 = Value::Array(vec![
+            Value::Bytes(protected_headers),
+            Value::Map(vec![(Value::Text("hashed".to_string()), Value::Bool(true))]),
+            if detached { Value::Null } else { Value::Bytes(message_hash.to_vec()) },
+            Value::Bytes(wallet.key.sign(&signed).to_bytes().to_vec()),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&cose_sign1, &mut bytes).unwrap();
+        hex::encode(bytes)
+    }
+
+    #[test]
+    fn hashed_payload_verifies_through_the_protected_header_address() {
+        use ciborium::Value;
+
+        let wallet = Wallet::new(1);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+        let message = ChallengeFormat::Cip8.message(&address, "00112233", 1_700_000_000);
+        let public_key = hex::encode(wallet.key.verifying_key().as_bytes());
+        let algorithm = (Value::Integer(1.into()), Value::Integer((-8).into()));
+        let header_address = (Value::Text("address".to_string()), Value::Bytes(enterprise.to_bytes()));
+
+        let headers = [algorithm.clone(), header_address];
+        for detached in [false, true] {
+            let signature = hashed_cose_sign1(&wallet, &headers, &message, detached);
+            assert!(verify_cardano_signature(&address, &message, &signature, &public_key).unwrap(), "{}", detached);
+            let other_message = format!("{}!", message);
+            assert!(!verify_cardano_signature(&address, &other_message, &signature, &public_key).unwrap(), "{}", detached);
+        }
+
+        // A COSE_Sign1 signed for another message is refused, even though its Sig_structure is valid
+        let replayed = hashed_cose_sign1(&wallet, std::slice::from_ref(&algorithm), "an older challenge", false);
+        assert!(!verify_cardano_signature(&address, &message, &replayed, &public_key).unwrap());
+
+        // Detached, only the header address tells the hash apart, and it must name the signer
+        let other = test_support::enterprise_address(NETWORK_ID, 7);
+        let other_address = (Value::Text("address".to_string()), Value::Bytes(other.to_bytes()));
+        let mismatched = hashed_cose_sign1(&wallet, &[algorithm, other_address], &message, true);
+        assert!(!verify_cardano_signature(&address, &message, &mismatched, &public_key).unwrap());
+    }
+
+    #[test]
+    fn short_keys_and_signatures_are_rejected_before_cbor_parsing() {
+        assert_eq!(
+            extract_public_key_from_cose(&[7; 31]).unwrap_err(),
+            "public key must be 32 bytes or COSE_Key, got 31 bytes"
+        );
+        assert_eq!(
+            extract_public_key_from_cose(&[]).unwrap_err(),
+            "public key must be 32 bytes or COSE_Key, got 0 bytes"
+        );
+        assert_eq!(
+            extract_signature_from_cose_sign1(&[7; 10]).unwrap_err(),
+            "signature must be a 64-byte Ed25519 signature or COSE_Sign1, got 10 bytes"
+        );
+
+        // Through the handler's entry point the short key surfaces with that message
+        let wallet = Wallet::new(1);
+        let address = test_support::bech32(&EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address());
+        let signature = hex::encode(wallet.key.sign(b"message").to_bytes());
+        let error = verify_cardano_signature(&address, "message", &signature, &hex::encode([7; 31])).unwrap_err();
+        assert!(error.to_string().contains("public key must be 32 bytes or COSE_Key"), "{}", error);
+    }
+
+    #[test]
+    fn mainnet_hex_on_a_testnet_server_is_a_network_mismatch() {
+        let mainnet_hex = hex::encode(test_support::enterprise_address(1, 7).to_bytes());
+        let testnet_hex = hex::encode(test_support::enterprise_address(0, 7).to_bytes());
+
+        assert!(matches!(
+            convert_to_bech32(&mainnet_hex, "testnet"),
+            Err(AddressError::NetworkMismatch { address: "mainnet", expected: "testnet" })
+        ));
+        assert!(convert_to_bech32(&testnet_hex, "testnet").unwrap().starts_with("addr_test1"));
+        assert!(convert_to_bech32(&mainnet_hex, "mainnet").unwrap().starts_with("addr1"));
+    }
+
+    #[test]
+    fn nil_payload_verifies_against_the_message_under_the_wallet_headers() {
+        use ciborium::Value;
+
+        let wallet = Wallet::new(3);
+        let enterprise = EnterpriseAddress::new(NETWORK_ID, &wallet.credential()).to_address();
+        let address = test_support::bech32(&enterprise);
+        let message = ChallengeFormat::Cip8.message(&address, "8899aabb", 1_700_000_000);
+        let public_key = hex::encode(wallet.key.verifying_key().as_bytes());
+        let algorithm = (Value::Integer(1.into()), Value::Integer((-8).into()));
+        let header_address = (Value::Text("address".to_string()), Value::Bytes(enterprise.to_bytes()));
+
+        // The wallet signs the Sig_structure over its protected headers and the message itself, then
+        // sends the COSE_Sign1 with a nil payload, with and without the address in those headers
+        for protected in [vec![algorithm.clone()], vec![algorithm.clone(), header_address.clone()]] {
+            let mut protected_headers = Vec::new();
+            ciborium::ser::into_writer(&Value::Map(protected), &mut protected_headers).unwrap();
+            let signed = encode_sig_structure(&protected_headers, message.as_bytes()).unwrap();
+            let cose_sign1 = Value::Array(vec![
+                Value::Bytes(protected_headers.clone()),
+                Value::Map(vec![(Value::Text("hashed".to_string()), Value::Bool(false))]),
+                Value::Null,
+                Value::Bytes(wallet.key.sign(&signed).to_bytes().to_vec()),
+            ]);
+            let mut signature = Vec::new();
+            ciborium::ser::into_writer(&cose_sign1, &mut signature).unwrap();
+            let signature = hex::encode(signature);
+
+            assert!(verify_cardano_signature(&address, &message, &signature, &public_key).unwrap(), "{}", signature);
+            let other_message = format!("{}!", message);
+            assert!(!verify_cardano_signature(&address, &other_message, &signature, &public_key).unwrap());
+        }
     }
-    
-    // Try to convert hex to bech32
-    let address_bytes = hex::decode(address)
-        .map_err(|e| format!("Invalid hex address: {}", e))?;
-    
-    let addr = Address::from_bytes(address_bytes)
-        .map_err(|e| format!("Invalid address bytes: {}", e))?;
-    
-    addr.to_bech32(None)
-        .map_err(|e| format!("Failed to convert to bech32: {}", e))
 }
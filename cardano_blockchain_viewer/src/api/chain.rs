@@ -0,0 +1,360 @@
+// src/api/chain.rs
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::extract::ApiPath;
+use super::{blockfrost_error_response, require_blockfrost};
+use super::user::Transaction;
+use crate::blockfrost::{BlockInfo, BlockfrostClient, EpochInfo, EpochParams, TxMetadatum, TxUtxos};
+
+// Public, read-only chain lookups backed by Blockfrost
+#[derive(Clone)]
+pub struct ChainState {
+    // None when BLOCKFROST_API_KEY isn't set; handlers then answer 503
+    pub blockfrost: Option<Arc<BlockfrostClient>>,
+}
+
+impl ChainState {
+    fn blockfrost(&self) -> Result<&BlockfrostClient, (StatusCode, Json<serde_json::Value>)> {
+        require_blockfrost(&self.blockfrost)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionDetail {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    // CIP-20 messages, CIP-25 NFT metadata, ...
+    pub metadata: Vec<TxMetadatum>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpochOverview {
+    #[serde(flatten)]
+    pub epoch: EpochInfo,
+    pub parameters: EpochParams,
+}
+
+// Current chain tip, to compare against the last slot seen on the Oura stream
+#[derive(Debug, Serialize)]
+pub struct TipInfo {
+    pub hash: String,
+    pub height: Option<u64>,
+    pub slot: Option<u64>,
+    pub epoch: Option<u64>,
+    pub time: i64,
+}
+
+// Transaction and block hashes are 32 bytes of hex
+fn is_tx_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn invalid_tx_hash() -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": "Transaction hash must be 64 hex characters" })),
+    )
+}
+
+pub async fn get_transaction(
+    State(state): State<ChainState>,
+    ApiPath(hash): ApiPath<String>,
+) -> Result<Json<TransactionDetail>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    if !is_tx_hash(&hash) {
+        return Err(invalid_tx_hash());
+    }
+
+    let transaction = blockfrost
+        .get_transaction(&hash)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch transaction"))?;
+
+    let metadata = blockfrost
+        .get_transaction_metadata(&hash)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch transaction metadata"))?;
+
+    Ok(Json(TransactionDetail {
+        transaction,
+        metadata,
+    }))
+}
+
+pub async fn get_transaction_utxos(
+    State(state): State<ChainState>,
+    ApiPath(hash): ApiPath<String>,
+) -> Result<Json<TxUtxos>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    if !is_tx_hash(&hash) {
+        return Err(invalid_tx_hash());
+    }
+
+    let utxos = blockfrost
+        .get_transaction_utxos(&hash)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch transaction UTXOs"))?;
+
+    Ok(Json(utxos))
+}
+
+// Accepts a block hash or a block height
+pub async fn get_block(
+    State(state): State<ChainState>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<Json<BlockInfo>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    let is_height = !id.is_empty() && id.len() <= 20 && id.chars().all(|c| c.is_ascii_digit());
+    if !is_tx_hash(&id) && !is_height {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "Block id must be a 64 hex character hash or a block height"
+            })),
+        ));
+    }
+
+    let block = blockfrost
+        .get_block(&id)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch block"))?;
+
+    Ok(Json(block))
+}
+
+pub async fn get_tip(
+    State(state): State<ChainState>,
+) -> Result<Json<TipInfo>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    let block = blockfrost
+        .get_latest_block()
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch chain tip"))?;
+
+    Ok(Json(TipInfo {
+        hash: block.hash,
+        height: block.height,
+        slot: block.slot,
+        epoch: block.epoch,
+        time: block.time,
+    }))
+}
+
+// Current epoch with its protocol parameters, for a network-state sidebar
+pub async fn get_latest_epoch(
+    State(state): State<ChainState>,
+) -> Result<Json<EpochOverview>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    let (epoch, parameters) = tokio::join!(blockfrost.get_latest_epoch(), blockfrost.get_epoch_params());
+    let epoch = epoch.map_err(|e| blockfrost_error_response(e, "Failed to fetch latest epoch"))?;
+    let parameters =
+        parameters.map_err(|e| blockfrost_error_response(e, "Failed to fetch epoch parameters"))?;
+
+    Ok(Json(EpochOverview { epoch, parameters }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockBlockfrost;
+    use axum::extract::Path;
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+
+    fn chain_state(mock: &MockBlockfrost) -> ChainState {
+        ChainState {
+            blockfrost: Some(Arc::new(mock.client())),
+        }
+    }
+
+    #[tokio::test]
+    async fn utxos_carry_addresses_and_lovelace() {
+        // Trimmed from a preprod /txs/{hash}/utxos response
+        let payload = json!({
+            "hash": "1e043f100dce12d107f679685acd2fc0610e10f72a92d412794c9773d11d8477",
+            "inputs": [{
+                "address": "addr_test1vrx8ryq6h7ka4hrfqsd5ctyxgghfw2pxg45y8yk8m6fwrpcfp7ju3",
+                "amount": [
+                    { "unit": "lovelace", "quantity": "42000000" },
+                    { "unit": "b0d07d45fe9514f80213f4020e5a61241458be626841cde717cb38a7", "quantity": "12" }
+                ],
+                "tx_hash": "1a0570af966fb355a7160e4f82d5a80b8681b7955f5d44bec0dce628516157f0",
+                "output_index": 0,
+                "data_hash": null,
+                "collateral": false,
+                "reference": false
+            }],
+            "outputs": [{
+                "address": "addr_test1qzx9hu8j4ah3auytk0mwcupd69hpc52t0cw39a65ndrah86djs784u92a3m5w475w3w35tyd6v3qumkze80j8a6h5tuqq5xe8y",
+                "amount": [{ "unit": "lovelace", "quantity": 41831683 }],
+                "output_index": 0,
+                "data_hash": null,
+                "inline_datum": null,
+                "reference_script_hash": null
+            }]
+        });
+        let mock = MockBlockfrost::start(Router::new().route(
+            "/txs/:hash/utxos",
+            get(move || async move { Json(payload) }),
+        ))
+        .await;
+
+        let Json(utxos) = get_transaction_utxos(
+            State(chain_state(&mock)),
+            ApiPath("1e043f100dce12d107f679685acd2fc0610e10f72a92d412794c9773d11d8477".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let input = &utxos.inputs[0];
+        assert_eq!(input.address, "addr_test1vrx8ryq6h7ka4hrfqsd5ctyxgghfw2pxg45y8yk8m6fwrpcfp7ju3");
+        assert_eq!((input.amount[0].unit.as_str(), input.amount[0].quantity.as_str()), ("lovelace", "42000000"));
+        assert_eq!(input.amount.len(), 2);
+        assert!(!input.collateral);
+        // Numeric quantities are read as the same strings
+        let output = &utxos.outputs[0];
+        assert!(output.address.starts_with("addr_test1qzx9hu8j"));
+        assert_eq!((output.amount[0].unit.as_str(), output.amount[0].quantity.as_str()), ("lovelace", "41831683"));
+    }
+
+    #[tokio::test]
+    async fn malformed_hash_is_rejected_before_blockfrost() {
+        let mock = MockBlockfrost::start(Router::new()).await;
+
+        let (status, _) = get_transaction_utxos(State(chain_state(&mock)), ApiPath("abc".to_string()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(mock.requests().is_empty());
+    }
+
+    const BLOCK_HASH: &str = "4ea1ba291e8eef538635a53e59fddba7810d1679631cc3aed7c8e6c4091a516a";
+
+    // /blocks/{hash_or_number} answers the same block for its hash and its height, and as the tip
+    fn block_routes() -> Router {
+        Router::new().route(
+            "/blocks/:id",
+            get(|Path(id): Path<String>| async move {
+                if ![BLOCK_HASH, "15243593", "latest"].contains(&id.as_str()) {
+                    return Err(StatusCode::NOT_FOUND);
+                }
+                Ok(Json(json!({
+                    "time": 1641338934, "height": 15243593, "hash": BLOCK_HASH, "slot": 412162133,
+                    "epoch": 425, "epoch_slot": 12, "slot_leader": "pool1pu5jlj4q9w9jlxeu370a3c9myx47md5j5m2str0naunn2qnikdy",
+                    "size": 3, "tx_count": 1, "output": "128314491794", "fees": 592661,
+                    "block_vrf": "vrf_vk1wf2k6lhujezqcfe00l6zetxpnmh9n6mwhpmhm0dvfh3fxgmdnrfqkms8ty",
+                    "previous_block": "43ebccb3ac72c7cebd0d9b755a4b08412c9f5dcb81b8a0ad1e3c197d29d47b05",
+                    "next_block": "8367f026cf4b03e116ff8ee5daf149b55ba5a6ec6dec04803b8dc317721d15fa",
+                    "confirmations": 4698
+                })))
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn block_is_found_by_hash_and_by_height() {
+        let mock = MockBlockfrost::start(block_routes()).await;
+
+        for id in [BLOCK_HASH, "15243593"] {
+            let Json(block) = get_block(State(chain_state(&mock)), ApiPath(id.to_string())).await.unwrap();
+
+            assert_eq!((block.hash.as_str(), block.height, block.slot), (BLOCK_HASH, Some(15243593), Some(412162133)));
+            // Blockfrost sends fees as a number here and output as a string; both come out as strings
+            assert_eq!((block.output.as_deref(), block.fees.as_deref()), (Some("128314491794"), Some("592661")));
+        }
+        assert_eq!(
+            mock.requests(),
+            [format!("/api/v0/blocks/{}", BLOCK_HASH), "/api/v0/blocks/15243593".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn tip_is_the_latest_block() {
+        let mock = MockBlockfrost::start(block_routes()).await;
+
+        let Json(tip) = get_tip(State(chain_state(&mock))).await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&tip).unwrap(),
+            json!({ "hash": BLOCK_HASH, "height": 15243593, "slot": 412162133, "epoch": 425, "time": 1641338934 })
+        );
+        assert_eq!(mock.requests(), ["/api/v0/blocks/latest"]);
+    }
+
+    #[tokio::test]
+    async fn block_id_must_be_a_hash_or_a_height() {
+        let mock = MockBlockfrost::start(block_routes()).await;
+
+        for id in ["latest", "-1", "12ab"] {
+            let (status, _) = get_block(State(chain_state(&mock)), ApiPath(id.to_string())).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", id);
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn latest_epoch_comes_with_its_parameters() {
+        let routes = Router::new()
+            .route(
+                "/epochs/latest",
+                get(|| async {
+                    Json(json!({
+                        "epoch": 225, "start_time": 1603403091, "end_time": 1603835086,
+                        "first_block_time": 1603403092, "last_block_time": 1603835084,
+                        "block_count": 21298, "tx_count": 17856, "output": "7849943934049314",
+                        "fees": 4203312194_u64, "active_stake": null
+                    }))
+                }),
+            )
+            .route(
+                "/epochs/latest/parameters",
+                get(|| async {
+                    Json(json!({
+                        "epoch": 225, "min_fee_a": 44, "min_fee_b": 155381, "max_block_size": 65536,
+                        "max_tx_size": 16384, "max_block_header_size": 1100, "key_deposit": "2000000",
+                        "pool_deposit": "500000000", "e_max": 18, "n_opt": 150, "min_pool_cost": "340000000",
+                        "protocol_major_ver": 2, "protocol_minor_ver": 0, "coins_per_utxo_size": "4310",
+                        "collateral_percent": 150, "max_collateral_inputs": 3
+                    }))
+                }),
+            );
+        let mock = MockBlockfrost::start(routes).await;
+
+        let Json(overview) = get_latest_epoch(State(chain_state(&mock))).await.unwrap();
+
+        assert_eq!((overview.epoch.epoch, overview.epoch.block_count), (225, 21298));
+        assert_eq!((overview.epoch.fees.as_str(), overview.epoch.active_stake.as_deref()), ("4203312194", None));
+        let parameters = &overview.parameters;
+        assert_eq!((parameters.min_fee_a, parameters.min_fee_b), (44, 155381));
+        assert_eq!(parameters.coins_per_utxo_size.as_deref(), Some("4310"));
+        assert_eq!(parameters.max_val_size, None);
+        // Served flat, with the parameters nested
+        let body = serde_json::to_value(&overview).unwrap();
+        assert_eq!((body["epoch"].clone(), body["parameters"]["key_deposit"].clone()), (json!(225), json!("2000000")));
+    }
+
+    #[tokio::test]
+    async fn epoch_parameters_failure_fails_the_request() {
+        let routes = Router::new()
+            .route("/epochs/latest", get(|| async { Json(json!({ "epoch": 225 })) }))
+            .route("/epochs/latest/parameters", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }));
+        let mock = MockBlockfrost::start(routes).await;
+
+        let (status, _) = get_latest_epoch(State(chain_state(&mock))).await.unwrap_err();
+
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,88 @@
+// Client IP for logging (and future per-client limits), honoring proxy headers only when trusted
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+
+/// The resolved client address, inserted into request extensions by `client_ip`
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+// With TRUST_PROXY the proxy's X-Forwarded-For / X-Real-IP wins over the socket peer.
+// Without it anyone could set those headers, so only the peer address is used
+pub async fn client_ip(
+    State(trust_proxy): State<bool>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(ip) = resolve(request.headers(), peer, trust_proxy) {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+fn resolve(headers: &HeaderMap, peer: Option<IpAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    if trust_proxy {
+        // The left-most X-Forwarded-For entry is the original client; proxies append after it
+        let forwarded = header(headers, "x-forwarded-for")
+            .and_then(|value| value.split(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        let real_ip = || header(headers, "x-real-ip").and_then(|ip| ip.trim().parse().ok());
+        if let Some(ip) = forwarded.or_else(real_ip) {
+            return Some(ip);
+        }
+    }
+    peer
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::call;
+    use axum::{middleware, routing::get, Extension, Json, Router};
+
+    // What a handler behind the middleware sees as the client address
+    async fn seen_ip(trust_proxy: bool, headers: &[(&str, &str)]) -> serde_json::Value {
+        let router = Router::new()
+            .route("/", get(|Extension(ClientIp(ip)): Extension<ClientIp>| async move { Json(ip.to_string()) }))
+            .layer(middleware::from_fn_with_state(trust_proxy, client_ip));
+        let mut request = Request::get("/");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        // The reverse proxy is the socket peer
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+
+        call(&router, request).await.1
+    }
+
+    #[tokio::test]
+    async fn proxy_headers_are_honored_only_when_trusted() {
+        let forwarded = [("x-forwarded-for", "203.0.113.7, 10.0.0.2")];
+        let real_ip = [("x-real-ip", "198.51.100.4")];
+
+        assert_eq!(seen_ip(true, &forwarded).await, "203.0.113.7");
+        assert_eq!(seen_ip(true, &real_ip).await, "198.51.100.4");
+        assert_eq!(seen_ip(true, &[("x-forwarded-for", "not-an-ip")]).await, "127.0.0.1");
+        assert_eq!(seen_ip(true, &[]).await, "127.0.0.1");
+
+        // Untrusted, a client could claim any address
+        assert_eq!(seen_ip(false, &forwarded).await, "127.0.0.1");
+        assert_eq!(seen_ip(false, &real_ip).await, "127.0.0.1");
+    }
+}
@@ -0,0 +1,82 @@
+// Json/Query/Path extractors whose rejections use the same `{"error": ..., "code": ...}`
+// body as the handlers, instead of axum's plain-text messages
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    extract::{FromRequest, FromRequestParts},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(ApiRejection))]
+pub struct ApiJson<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(ApiRejection))]
+pub struct ApiQuery<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(ApiRejection))]
+pub struct ApiPath<T>(pub T);
+
+/// A request the extractors couldn't parse
+pub struct ApiRejection {
+    status: StatusCode,
+    code: &'static str,
+    error: String,
+}
+
+impl IntoResponse for ApiRejection {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.error, "code": self.code })),
+        )
+            .into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiRejection {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match &rejection {
+            JsonRejection::MissingJsonContentType(_) => "UNSUPPORTED_MEDIA_TYPE",
+            JsonRejection::JsonSyntaxError(_) => "INVALID_JSON",
+            JsonRejection::JsonDataError(_) => "INVALID_BODY",
+            _ => "BAD_REQUEST",
+        };
+        Self {
+            status: rejection.status(),
+            code,
+            error: rejection.body_text(),
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiRejection {
+    fn from(rejection: QueryRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            code: "INVALID_QUERY",
+            error: rejection.body_text(),
+        }
+    }
+}
+
+impl From<PathRejection> for ApiRejection {
+    fn from(rejection: PathRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            code: "INVALID_PATH",
+            error: rejection.body_text(),
+        }
+    }
+}
+
+// Router fallback, so unknown routes answer JSON too
+pub async fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "Not found", "code": "NOT_FOUND" })),
+    )
+}
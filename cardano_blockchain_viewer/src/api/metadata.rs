@@ -0,0 +1,132 @@
+// src/api/metadata.rs
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use super::chain::ChainState;
+use super::extract::{ApiPath, ApiQuery};
+use super::{blockfrost_error_response, require_blockfrost};
+use crate::blockfrost::LabelMetadatum;
+
+// Blockfrost's own page size limit
+const MAX_METADATA_COUNT: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct MetadataQuery {
+    pub page: Option<u32>,
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetadataSearchResponse {
+    pub label: String,
+    // Newest first
+    pub transactions: Vec<LabelMetadatum>,
+    pub page: u32,
+    pub count: u32,
+}
+
+// Recent transactions with metadata under a label, e.g. /api/metadata/674 for CIP-20 messages (JWT required)
+pub async fn get_metadata_by_label(
+    State(state): State<ChainState>,
+    ApiPath(label): ApiPath<String>,
+    ApiQuery(query): ApiQuery<MetadataQuery>,
+) -> Result<Json<MetadataSearchResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = require_blockfrost(&state.blockfrost)?;
+
+    // Metadata labels are unsigned integers
+    if label.parse::<u64>().is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Metadata label must be a non-negative integer" })),
+        ));
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(10).clamp(1, MAX_METADATA_COUNT);
+
+    let transactions = blockfrost
+        .get_txs_by_metadata_label(&label, page, count)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch metadata"))?;
+
+    Ok(Json(MetadataSearchResponse {
+        label,
+        transactions,
+        page,
+        count,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockBlockfrost;
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    async fn search(
+        mock: &MockBlockfrost,
+        label: &str,
+        page: Option<u32>,
+        count: Option<u32>,
+    ) -> Result<MetadataSearchResponse, StatusCode> {
+        let state = ChainState {
+            blockfrost: Some(Arc::new(mock.client())),
+        };
+        get_metadata_by_label(State(state), ApiPath(label.to_string()), ApiQuery(MetadataQuery { page, count }))
+            .await
+            .map(|Json(response)| response)
+            .map_err(|(status, _)| status)
+    }
+
+    #[tokio::test]
+    async fn cip20_messages_come_back_with_their_transactions() {
+        // Shaped like a preprod /metadata/txs/labels/674 response
+        let payload = json!([
+            {
+                "tx_hash": "0d9c3a7a3ea1c5b1e8d3e8b4f0a3f7c2b6b1e8c95e6f2f1e5d0c7a9b8e4d3c2b",
+                "json_metadata": { "msg": ["Invoice-No: 1234", "Thank you!"] }
+            },
+            {
+                "tx_hash": "7f2a8d0c4b1e9f3a6c5d2e8b7a0f1c4d9e3b6a2f5c8d1e4b7a0c3f6d9e2b5a8c",
+                "json_metadata": { "msg": ["gm"] }
+            }
+        ]);
+        let mock = MockBlockfrost::start(Router::new().route(
+            "/metadata/txs/labels/:label",
+            get(move || async move { Json(payload) }),
+        ))
+        .await;
+
+        let response = search(&mock, "674", Some(2), Some(500)).await.unwrap();
+
+        assert_eq!((response.label.as_str(), response.page, response.count), ("674", 2, 100));
+        let messages: Vec<_> = response
+            .transactions
+            .iter()
+            .map(|tx| (&tx.tx_hash[..8], tx.json_metadata["msg"].clone()))
+            .collect();
+        assert_eq!(
+            messages,
+            [("0d9c3a7a", json!(["Invoice-No: 1234", "Thank you!"])), ("7f2a8d0c", json!(["gm"]))]
+        );
+        assert_eq!(mock.requests(), ["/api/v0/metadata/txs/labels/674?page=2&count=100&order=desc"]);
+    }
+
+    #[tokio::test]
+    async fn unused_label_is_empty_and_a_non_numeric_one_a_400() {
+        // Blockfrost answers 404 for a label no transaction has used
+        let mock = MockBlockfrost::start(Router::new()).await;
+
+        let response = search(&mock, "1337", None, None).await.unwrap();
+        assert!(response.transactions.is_empty());
+        assert_eq!((response.page, response.count), (1, 10));
+
+        for label in ["cip20", "-1"] {
+            assert_eq!(search(&mock, label, None, None).await.unwrap_err(), StatusCode::BAD_REQUEST, "{}", label);
+        }
+        assert_eq!(mock.requests().len(), 1);
+    }
+}
@@ -1,30 +1,77 @@
 // src/api/mod.rs
 use axum::{
-    middleware,
+    body::Body,
+    extract::State,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Router,
+    Json, Router,
 };
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::Level;
 
+pub mod address;
+pub mod admin;
 pub mod auth;
+pub mod chain;
+pub mod client_ip;
+pub mod extract;
+pub mod metadata;
+pub mod networks;
+pub mod stats;
 pub mod user;
 
 use crate::auth::{auth_middleware, JwtManager};
-use crate::blockfrost::BlockfrostClient;
+use crate::blockfrost::{BlockfrostClient, BlockfrostError};
+use crate::models::OuraStatusHandle;
 use crate::websocket::{websocket_handler, WebSocketState};
 
+// /api/networks only changes with a redeploy, so browsers and proxies may reuse it for an hour
+const NETWORKS_CACHE_CONTROL: &str = "public, max-age=3600";
+
+// Wallet data, tokens and live counters must never be served from a cache
+fn no_store() -> SetResponseHeaderLayer<HeaderValue> {
+    SetResponseHeaderLayer::if_not_present(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+}
+
+// Auth bodies only carry an address, signature and key; anything bigger is rejected with 413
+const AUTH_BODY_LIMIT_BYTES: usize = 16 * 1024;
+
 pub fn create_router(
     jwt_manager: Arc<JwtManager>,
-    blockfrost: Arc<BlockfrostClient>,
+    blockfrost: Option<Arc<BlockfrostClient>>,
     ws_state: WebSocketState,
+    oura_status: OuraStatusHandle,
+    magic: &'static str,
+    max_inflight: usize,
+    trust_proxy: bool,
 ) -> Router {
+    // Addresses signing in must belong to the network the instance runs on
+    let network = crate::config::address_network_for(magic);
     let auth_state = auth::AuthState {
         jwt_manager: jwt_manager.clone(),
         challenges: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
+        network,
+        challenge_format: auth::ChallengeFormat::from_env(),
     };
 
+    let chain_state = chain::ChainState {
+        blockfrost: blockfrost.clone(),
+    };
     let user_state = user::UserState { blockfrost };
+    let metadata_state = chain_state.clone();
+
+    // One pool of MAX_INFLIGHT slots shared by every route group that calls Blockfrost (and the
+    // address converter next to them); /ws, /health and the in-memory stats aren't limited
+    let inflight = middleware::from_fn_with_state(Arc::new(Semaphore::new(max_inflight)), inflight_limit);
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -34,21 +81,446 @@ pub fn create_router(
     let public_routes = Router::new()
         .route("/api/auth/challenge", post(auth::create_challenge))
         .route("/api/auth/verify", post(auth::verify_signature))
-        .with_state(auth_state);
+        .with_state(auth_state)
+        .layer(RequestBodyLimitLayer::new(AUTH_BODY_LIMIT_BYTES))
+        .layer(no_store());
+
+    let address_routes = Router::new()
+        .route("/api/address/convert", get(address::convert_address))
+        .layer(inflight.clone());
+
+    let network_routes = Router::new()
+        .route("/api/networks", get(networks::get_networks))
+        .with_state(magic)
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(NETWORKS_CACHE_CONTROL),
+        ));
+
+    let stats_routes = Router::new()
+        .route("/api/stats", get(stats::get_stats))
+        .route("/api/events/recent", get(stats::get_recent_events))
+        .with_state(ws_state.app_state.clone())
+        .layer(no_store());
+
+    let metrics_routes = Router::new()
+        .route("/metrics", get(stats::get_metrics))
+        .with_state(ws_state.metrics.clone())
+        .layer(no_store());
+
+    let chain_routes = Router::new()
+        .route("/api/tx/:hash", get(chain::get_transaction))
+        .route("/api/tx/:hash/utxos", get(chain::get_transaction_utxos))
+        .route("/api/block/:id", get(chain::get_block))
+        .route("/api/epoch/latest", get(chain::get_latest_epoch))
+        .route("/api/tip", get(chain::get_tip))
+        .with_state(chain_state)
+        .layer(inflight.clone());
 
     let protected_routes = Router::new()
         .route("/api/user/transactions", get(user::get_transactions))
         .route("/api/user/summary", get(user::get_summary))
+        .route("/api/user/staking", get(user::get_staking))
+        .route("/api/user/assets", get(user::get_assets))
+        .route("/api/user/balance-history", get(user::get_balance_history))
+        .route("/api/user/account/transactions", get(user::get_account_transactions))
         .with_state(user_state)
+        .layer(middleware::from_fn_with_state(
+            jwt_manager.clone(),
+            auth_middleware,
+        ))
+        // Outermost, so shed requests don't even cost a JWT check
+        .layer(inflight.clone())
+        // Also covers the 401/503 answers from the layers above
+        .layer(no_store());
+
+    let metadata_routes = Router::new()
+        .route("/api/metadata/:label", get(metadata::get_metadata_by_label))
+        .with_state(metadata_state)
+        .layer(middleware::from_fn_with_state(
+            jwt_manager.clone(),
+            auth_middleware,
+        ))
+        .layer(inflight)
+        .layer(no_store());
+
+    let session_routes = Router::new()
+        .route("/api/auth/me", get(auth::get_session))
         .layer(middleware::from_fn_with_state(
             jwt_manager,
             auth_middleware,
-        ));
+        ))
+        .layer(no_store());
+
+    let reset_state = admin::ResetState {
+        app_state: ws_state.app_state.clone(),
+        ws_tx: ws_state.ws_tx.clone(),
+    };
+    // Without ADMIN_TOKEN the admin routes don't exist and fall through to the 404 handler
+    let admin_routes = match admin::AdminToken::from_env() {
+        Some(token) => admin::routes(token, reset_state, oura_status).layer(no_store()),
+        None => Router::new(),
+    };
 
     Router::new()
         .route("/ws", get(websocket_handler))
         .with_state(ws_state)
         .merge(public_routes)
+        .merge(address_routes)
+        .merge(network_routes)
+        .merge(stats_routes)
+        .merge(metrics_routes)
+        .merge(chain_routes)
         .merge(protected_routes)
+        .merge(session_routes)
+        .merge(metadata_routes)
+        .merge(admin_routes)
+        .fallback(extract::not_found)
         .layer(cors)
-}
\ No newline at end of file
+        // Layers run bottom-up: assign an x-request-id, log the request, echo the id back
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_request_span)
+                .on_response(
+                    DefaultOnResponse::new()
+                        .level(Level::INFO)
+                        .latency_unit(LatencyUnit::Millis),
+                ),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        // Before the trace span is made, so the span can carry the client IP
+        .layer(middleware::from_fn_with_state(trust_proxy, client_ip::client_ip))
+}
+
+// Span for each HTTP request. Only the path is recorded: the query can carry the
+// WebSocket `token`, and headers/bodies carry JWTs and wallet signatures
+fn make_request_span(request: &Request<Body>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-");
+    let client_ip = request
+        .extensions()
+        .get::<client_ip::ClientIp>()
+        .map(|client_ip::ClientIp(ip)| ip.to_string())
+        .unwrap_or_else(|| "-".to_string());
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id = %request_id,
+        client_ip = %client_ip,
+    )
+}
+
+// Answer 503 instead of queueing once every in-flight slot is taken
+async fn inflight_limit(
+    State(slots): State<Arc<Semaphore>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Ok(_permit) = slots.try_acquire() else {
+        tracing::warn!("Rejecting {}: MAX_INFLIGHT requests already in flight", request.uri().path());
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Server is busy, try again later" })),
+        )
+            .into_response();
+    };
+
+    next.run(request).await
+}
+
+// The Blockfrost client, or a 503 when the server runs without a Blockfrost key
+pub(crate) fn require_blockfrost(
+    blockfrost: &Option<Arc<BlockfrostClient>>,
+) -> Result<&BlockfrostClient, (StatusCode, Json<serde_json::Value>)> {
+    blockfrost.as_deref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "Blockfrost not configured: set BLOCKFROST_API_KEY to enable this endpoint"
+            })),
+        )
+    })
+}
+
+// Map a Blockfrost error to the HTTP status and JSON body returned to the client
+pub(crate) fn blockfrost_error_response(
+    error: BlockfrostError,
+    context: &str,
+) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Blockfrost error: {}", error);
+
+    let status = match &error {
+        BlockfrostError::NotFound => StatusCode::NOT_FOUND,
+        BlockfrostError::AddressConversion(_) => StatusCode::BAD_REQUEST,
+        BlockfrostError::Upstream { status: 400, .. } => StatusCode::BAD_REQUEST,
+        BlockfrostError::RateLimited => StatusCode::SERVICE_UNAVAILABLE,
+        // Our key or Blockfrost itself is at fault, not the client
+        BlockfrostError::Unauthorized(_)
+        | BlockfrostError::Network(_)
+        | BlockfrostError::Decode(_)
+        | BlockfrostError::Upstream { .. } => StatusCode::BAD_GATEWAY,
+    };
+
+    let mut body = serde_json::json!({ "error": format!("{}: {}", context, error) });
+    // Lets the frontend tell a server misconfiguration apart from a Blockfrost outage
+    if matches!(error, BlockfrostError::Unauthorized(_)) {
+        body["code"] = "BLOCKFROST_UNAUTHORIZED".into();
+    }
+
+    (status, Json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppState, Metrics};
+    use crate::test_support::{bech32, call, enterprise_address, MockBlockfrost};
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::{broadcast, Mutex, Notify};
+
+    // Blockfrost answers /blocks/:id with a 404, but only once `release` is notified
+    async fn stalled_blockfrost(release: Arc<Notify>) -> MockBlockfrost {
+        let routes = Router::new().route(
+            "/blocks/:id",
+            get(move || async move {
+                release.notified().await;
+                (StatusCode::NOT_FOUND, r#"{"status_code":404,"error":"Not Found"}"#)
+            }),
+        );
+        MockBlockfrost::start(routes).await
+    }
+
+    fn jwt_manager() -> Arc<JwtManager> {
+        Arc::new(JwtManager::new(
+            "test-secret".to_string(),
+            "issuer".to_string(),
+            "audience".to_string(),
+        ))
+    }
+
+    fn router(blockfrost: Option<Arc<BlockfrostClient>>, max_inflight: usize) -> Router {
+        let jwt_manager = jwt_manager();
+        let ws_state = WebSocketState {
+            app_state: Arc::new(Mutex::new(AppState::new(10))),
+            ws_tx: broadcast::channel(8).0,
+            jwt_manager: jwt_manager.clone(),
+            metrics: Arc::new(Metrics::new(8, 8)),
+            require_auth: false,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+            max_connections: 10,
+            idle_timeout: None,
+        };
+        create_router(
+            jwt_manager,
+            blockfrost,
+            ws_state,
+            OuraStatusHandle::default(),
+            "preprod",
+            max_inflight,
+            false,
+        )
+    }
+
+    fn get_request(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn blockfrost_routes_answer_503_once_every_slot_is_taken() {
+        let release = Arc::new(Notify::new());
+        let blockfrost = stalled_blockfrost(Arc::clone(&release)).await;
+        let router = router(Some(Arc::new(blockfrost.client())), 1);
+
+        // Holds the only slot until Blockfrost is released
+        let stalled = tokio::spawn({
+            let router = router.clone();
+            async move { call(&router, get_request("/api/block/1")).await }
+        });
+        while blockfrost.requests().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        for uri in [
+            "/api/block/2",
+            "/api/tip",
+            "/api/metadata/674",
+            "/api/user/transactions?address=addr_test1",
+            "/api/address/convert?address=addr_test1",
+        ] {
+            let (status, body) = call(&router, get_request(uri)).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}: {}", uri, body);
+            assert_eq!(body["error"], "Server is busy, try again later");
+        }
+        // Routes that never reach Blockfrost keep answering
+        for uri in ["/api/stats", "/api/networks"] {
+            assert_eq!(call(&router, get_request(uri)).await.0, StatusCode::OK, "{}", uri);
+        }
+
+        release.notify_one();
+        assert_eq!(stalled.await.unwrap().0, StatusCode::NOT_FOUND);
+        // The slot is free again
+        release.notify_one();
+        assert_eq!(call(&router, get_request("/api/block/2")).await.0, StatusCode::NOT_FOUND);
+    }
+
+    // GET with a valid session token, as the frontend sends it
+    fn authorized_get(uri: &str) -> Request<Body> {
+        let token = jwt_manager().generate_token("addr_test1vz".to_string(), None).unwrap();
+        Request::get(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn user_summary_is_503_without_a_blockfrost_key() {
+        let router = router(None, 8);
+        let address = bech32(&enterprise_address(0, 1));
+
+        let (status, body) = call(&router, authorized_get(&format!("/api/user/summary?address={}", address))).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", body);
+        assert!(body["error"].as_str().unwrap().contains("BLOCKFROST_API_KEY"), "{}", body);
+        // The rest of the server keeps working
+        assert_eq!(call(&router, get_request("/api/stats")).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejected_blockfrost_key_is_a_502_with_its_own_code() {
+        let routes = Router::new().route(
+            "/addresses/:address",
+            get(|| async {
+                (StatusCode::FORBIDDEN, r#"{"status_code":403,"error":"Forbidden","message":"Invalid project token."}"#)
+            }),
+        );
+        let blockfrost = MockBlockfrost::start(routes).await;
+        let router = router(Some(Arc::new(blockfrost.client())), 8);
+        let address = bech32(&enterprise_address(0, 1));
+
+        let (status, body) = call(&router, authorized_get(&format!("/api/user/summary?address={}", address))).await;
+
+        assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_GATEWAY, Some("BLOCKFROST_UNAUTHORIZED")), "{}", body);
+        assert!(body["error"].as_str().unwrap().starts_with("Failed to fetch account info"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn session_endpoint_returns_the_token_claims() {
+        let router = router(None, 8);
+        let jwt_manager = jwt_manager();
+        let token = jwt_manager
+            .generate_token("addr_test1vz".to_string(), Some("stake_test1uq".to_string()))
+            .unwrap();
+        let claims = jwt_manager.validate_token(&token).unwrap();
+        let request = Request::get("/api/auth/me")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+
+        let (status, body) = call(&router, request).await;
+
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert_eq!(body["wallet_address"], "addr_test1vz");
+        assert_eq!(body["stake_address"], "stake_test1uq");
+        assert_eq!((body["iat"].as_u64(), body["exp"].as_u64()), (Some(claims.iat as u64), Some(claims.exp as u64)));
+        // Freshly issued, so (nearly) the whole 24 hours remain
+        let expires_in_secs = body["expires_in_secs"].as_u64().unwrap();
+        assert!((24 * 3600 - 5..=24 * 3600).contains(&expires_in_secs), "{}", body);
+
+        assert_eq!(call(&router, get_request("/api/auth/me")).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn ten_kilobyte_address_is_a_fast_400_before_any_lookup() {
+        let blockfrost = MockBlockfrost::start(Router::new()).await;
+        let router = router(Some(Arc::new(blockfrost.client())), 8);
+        let address = format!("addr_test1{}", "q".repeat(10 * 1024));
+        let json_post = |uri: &str, body: serde_json::Value| {
+            Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        for request in [
+            json_post("/api/auth/challenge", serde_json::json!({ "address": address })),
+            json_post(
+                "/api/auth/verify",
+                serde_json::json!({ "address": address, "signature": "00", "key": "00" }),
+            ),
+            authorized_get(&format!("/api/user/transactions?address={}", address)),
+            authorized_get(&format!("/api/user/summary?address={}", address)),
+        ] {
+            let uri = request.uri().path().to_string();
+            let started = std::time::Instant::now();
+            let (status, body) = call(&router, request).await;
+
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", uri, body);
+            assert!(body["error"].as_str().unwrap().contains("the maximum is 256"), "{}: {}", uri, body);
+            assert!(started.elapsed() < std::time::Duration::from_millis(500), "{}", uri);
+        }
+        assert_eq!(blockfrost.requests(), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn user_summary_is_never_cached() {
+        use tower::Service;
+
+        // An address Blockfrost has never seen: a 404, which the summary reports as empty
+        let blockfrost = MockBlockfrost::start(Router::new()).await;
+        let router = router(Some(Arc::new(blockfrost.client())), 8);
+        let uri = format!("/api/user/summary?address={}", bech32(&enterprise_address(0, 1)));
+
+        let response = router.clone().call(authorized_get(&uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        // Nor is the 401 for a missing token
+        let response = router.clone().call(get_request(&uri)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+
+        let response = router.clone().call(get_request("/metrics")).await.unwrap();
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        let response = router.clone().call(get_request("/api/networks")).await.unwrap();
+        assert_eq!(response.headers()[header::CACHE_CONTROL], NETWORKS_CACHE_CONTROL);
+    }
+
+    #[tokio::test]
+    async fn oversized_auth_body_is_413() {
+        let router = router(None, 8);
+        let body = serde_json::json!({ "address": "a".repeat(AUTH_BODY_LIMIT_BYTES) }).to_string();
+
+        for uri in ["/api/auth/challenge", "/api/auth/verify"] {
+            let request = Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.clone()))
+                .unwrap();
+            assert_eq!(call(&router, request).await.0, StatusCode::PAYLOAD_TOO_LARGE, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn extractor_rejections_are_json() {
+        let router = router(None, 8);
+        let invalid_json = Request::post("/api/auth/verify")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"address": "addr_test1", "signature":"#))
+            .unwrap();
+
+        let (status, body) = call(&router, invalid_json).await;
+
+        // `call` reads a non-JSON body as Null, so these only match a JSON body
+        assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("INVALID_JSON")), "{}", body);
+        assert!(body["error"].is_string(), "{}", body);
+
+        let (status, body) = call(&router, authorized_get("/api/user/transactions")).await;
+        assert_eq!((status, body["code"].as_str()), (StatusCode::BAD_REQUEST, Some("INVALID_QUERY")), "{}", body);
+    }
+}
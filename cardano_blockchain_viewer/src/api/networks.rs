@@ -0,0 +1,68 @@
+// src/api/networks.rs
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use crate::config::CardanoConfig;
+
+#[derive(Debug, Serialize)]
+pub struct NetworkInfo {
+    pub network_name: &'static str,
+    pub relay: &'static str,
+    pub magic: &'static str,
+    // "mainnet" or "testnet", the address format wallets on this network use
+    pub address_network: &'static str,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NetworksResponse {
+    // Magic of the network this instance streams from
+    pub active: &'static str,
+    pub networks: Vec<NetworkInfo>,
+}
+
+// The CardanoConfig presets, flagging the one this instance runs on
+pub async fn get_networks(State(active_magic): State<&'static str>) -> Json<NetworksResponse> {
+    let networks = CardanoConfig::presets()
+        .into_iter()
+        .map(|config| NetworkInfo {
+            network_name: config.network_name,
+            relay: config.relay,
+            magic: config.magic,
+            address_network: config.address_network(),
+            active: config.magic == active_magic,
+        })
+        .collect();
+
+    Json(NetworksResponse {
+        active: active_magic,
+        networks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_the_configured_network_is_flagged_active() {
+        for configured in CardanoConfig::presets() {
+            let Json(response) = get_networks(State(configured.magic)).await;
+
+            assert_eq!(response.active, configured.magic);
+            let active: Vec<_> = response
+                .networks
+                .iter()
+                .filter(|network| network.active)
+                .map(|network| network.network_name)
+                .collect();
+            assert_eq!(active, [configured.network_name]);
+            assert_eq!(response.networks.len(), 3);
+        }
+
+        // Mainnet wallets use mainnet addresses, every test network the testnet format
+        let Json(response) = get_networks(State("mainnet")).await;
+        let formats: Vec<_> = response.networks.iter().map(|network| network.address_network).collect();
+        assert_eq!(formats, ["testnet", "testnet", "mainnet"]);
+    }
+}
@@ -0,0 +1,117 @@
+// src/api/stats.rs
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::extract::ApiQuery;
+use crate::models::{AppState, BlockchainEvent, BufferStats, Metrics, MetricsSnapshot};
+
+#[derive(Debug, Deserialize)]
+pub struct RecentEventsQuery {
+    // Only events with a slot greater than this; events without a slot are left out
+    pub since_slot: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecentEvents {
+    // Oldest first, so clients can append them to what they already show
+    pub events: Vec<BlockchainEvent>,
+    pub total: usize,
+}
+
+// Snapshot of the same stats the WebSocket sends as its "stats" message
+pub async fn get_stats(State(state): State<Arc<Mutex<AppState>>>) -> Json<BufferStats> {
+    let app_state = state.lock().await;
+    Json(app_state.get_stats())
+}
+
+// Buffered events, the same ones a new WebSocket client is replayed
+pub async fn get_recent_events(
+    State(state): State<Arc<Mutex<AppState>>>,
+    ApiQuery(query): ApiQuery<RecentEventsQuery>,
+) -> Json<RecentEvents> {
+    let app_state = state.lock().await;
+    let events: Vec<BlockchainEvent> = app_state
+        .buffer
+        .iter()
+        .filter(|event| match query.since_slot {
+            Some(since) => event.slot().is_some_and(|slot| slot > since),
+            None => true,
+        })
+        .cloned()
+        .collect();
+
+    Json(RecentEvents {
+        total: events.len(),
+        events,
+    })
+}
+
+// Channel capacity and drop counters, for right-sizing BROADCAST_CAPACITY
+pub async fn get_metrics(State(metrics): State<Arc<Metrics>>) -> Json<MetricsSnapshot> {
+    Json(metrics.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::event_processor::broadcast_stats;
+    use serde_json::Value;
+    use tokio::sync::broadcast;
+
+    fn block(slot: u64) -> BlockchainEvent {
+        BlockchainEvent::Block {
+            slot,
+            hash: format!("block-{}", slot),
+            number: slot,
+            epoch: 1,
+            era: "Babbage".to_string(),
+            tx_count: 0,
+            timestamp: 0,
+            details: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn stats_endpoint_matches_the_websocket_stats_message() {
+        let state = Arc::new(Mutex::new(AppState::new(10)));
+        state.lock().await.add_event(block(10), 10);
+        let (ws_tx, mut ws_rx) = broadcast::channel(4);
+        broadcast_stats(&mut *state.lock().await, &ws_tx).unwrap();
+
+        let Json(stats) = get_stats(State(state)).await;
+
+        let frame: Value = serde_json::from_str(ws_rx.try_recv().unwrap().json()).unwrap();
+        assert_eq!(serde_json::to_value(stats).unwrap(), frame["data"]);
+    }
+
+    #[tokio::test]
+    async fn since_slot_keeps_only_later_slotted_events_in_order() {
+        let state = Arc::new(Mutex::new(AppState::new(10)));
+        {
+            let mut app_state = state.lock().await;
+            let other = BlockchainEvent::Other {
+                event_type: "unknown_record".to_string(),
+                timestamp: 0,
+                details: serde_json::json!({}),
+            };
+            let rollback = BlockchainEvent::RollBack { block_hash: "block-12".to_string(), block_slot: 12, timestamp: 0 };
+            for event in [block(5), block(10), other, rollback, block(15)] {
+                app_state.add_event(event, 10);
+            }
+        }
+        let recent = |since_slot| {
+            let state = Arc::clone(&state);
+            async move {
+                let Json(recent) = get_recent_events(State(state), ApiQuery(RecentEventsQuery { since_slot })).await;
+                recent.events.iter().map(|event| (event.kind(), event.slot())).collect::<Vec<_>>()
+            }
+        };
+
+        // Strictly after the slot: the block at 10 is where the client left off
+        assert_eq!(recent(Some(10)).await, [("RollBack", Some(12)), ("Block", Some(15))]);
+        assert_eq!(recent(Some(15)).await, []);
+        assert_eq!(recent(None).await.len(), 5);
+    }
+}
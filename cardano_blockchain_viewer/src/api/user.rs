@@ -3,12 +3,48 @@ use axum::{extract::State, http::StatusCode, Extension, Json};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use super::extract::ApiQuery;
+use super::{blockfrost_error_response, require_blockfrost};
+use crate::address::validate_cardano_address;
 use crate::auth::Claims;
-use crate::blockfrost::BlockfrostClient;
+use crate::blockfrost::{AssetDetail, BlockfrostAmount, BlockfrostClient, BlockfrostError, TxUtxos};
+
+// Upper bound on Blockfrost pages fetched by one /api/user/transactions request
+const MAX_TRANSACTION_PAGES: u32 = 5;
+
+// Blockfrost's own page size limit; each listed transaction also costs a detail lookup
+const MAX_TRANSACTION_COUNT: u32 = 100;
+
+// With ?breakdown=true only the first transactions get a UTXO lookup for direction/net_lovelace
+const MAX_BREAKDOWN_LOOKUPS: usize = 20;
+
+// Upper bound on transactions embedded by /api/user/summary?include_recent=N
+const MAX_RECENT_TRANSACTIONS: u32 = 5;
+
+// Upper bound on addresses queried by /api/user/account/transactions, one Blockfrost call each
+const MAX_ACCOUNT_ADDRESSES: usize = 10;
+
+// /api/user/balance-history walks back over at most this many of the newest transactions, in
+// pages of BALANCE_HISTORY_PAGE_SIZE, with one (cached) UTXO lookup each
+const BALANCE_HISTORY_PAGE_SIZE: u32 = 100;
+const MAX_BALANCE_HISTORY_PAGES: u32 = 2;
+
+// Points returned by /api/user/balance-history when max_points isn't given
+const DEFAULT_BALANCE_HISTORY_POINTS: usize = 100;
+
+// Only the first assets get metadata looked up, bounding Blockfrost calls per request
+const MAX_ENRICHED_ASSETS: usize = 20;
 
 #[derive(Clone)]
 pub struct UserState {
-    pub blockfrost: Arc<BlockfrostClient>,
+    // None when BLOCKFROST_API_KEY isn't set; handlers then answer 503
+    pub blockfrost: Option<Arc<BlockfrostClient>>,
+}
+
+impl UserState {
+    fn blockfrost(&self) -> Result<&BlockfrostClient, (StatusCode, Json<serde_json::Value>)> {
+        require_blockfrost(&self.blockfrost)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,11 +52,41 @@ pub struct TransactionQuery {
     pub address: String,
     pub page: Option<u32>,
     pub count: Option<u32>,
+    // Consecutive pages to fetch and concatenate, capped at MAX_TRANSACTION_PAGES
+    pub pages: Option<u32>,
+    // Add direction/net_lovelace, one UTXO lookup per transaction (up to MAX_BREAKDOWN_LOOKUPS)
+    pub breakdown: Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SummaryQuery {
     pub address: String,
+    // Embed the first N transactions (capped at MAX_RECENT_TRANSACTIONS) in the summary
+    pub include_recent: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BalanceHistoryQuery {
+    pub address: String,
+    // Downsample the series to at most this many points (the newest point is always kept)
+    pub max_points: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountTransactionQuery {
+    // Transactions returned, and fetched from each address (at most MAX_TRANSACTION_COUNT)
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountTransactionResponse {
+    pub stake_address: String,
+    pub transactions: Vec<Transaction>,
+    pub total: usize,
+    // Addresses under the stake key, and how many were queried (capped at MAX_ACCOUNT_ADDRESSES,
+    // fewer if Blockfrost rate limited the lookups)
+    pub addresses: usize,
+    pub addresses_queried: usize,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +94,10 @@ pub struct TransactionResponse {
     pub transactions: Vec<Transaction>,
     pub total: usize,
     pub page: u32,
+    pub pages: u32,
+    // e.g. "network_mismatch: address=mainnet, server=preprod" so the frontend can show a banner
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -35,10 +105,50 @@ pub struct Transaction {
     pub tx_hash: String,
     pub block: String,
     pub block_height: u64,
+    // Unix seconds
     pub block_time: u64,
     pub slot: u64,
     pub index: u32,
     pub fees: String,
+    // Only filled in by /api/user/transactions?breakdown=true, which knows whose wallet it is
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub flow: Option<LovelaceFlow>,
+}
+
+/// How a transaction moved ADA for the queried address
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    In,
+    Out,
+    // Spent from and paid only back to the address, e.g. consolidating UTXOs
+    #[serde(rename = "self")]
+    SelfTransfer,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LovelaceFlow {
+    pub direction: Direction,
+    // Received minus spent, so fees show up in the net of outgoing transactions
+    pub net_lovelace: i64,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct BalancePoint {
+    pub block_time: u64,
+    // Lovelace held by the address right after this transaction
+    pub cumulative_lovelace: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceHistory {
+    pub address: String,
+    pub points: Vec<BalancePoint>,
+    pub transactions_walked: usize,
+    // The address has older transactions than were walked, so the series starts after its first one
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,19 +157,66 @@ pub struct WalletSummary {
     pub stake_address: Option<String>,
     pub balance: String,
     pub transaction_count: usize,
+    // Only present when include_recent was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_transactions: Option<Vec<Transaction>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 pub struct AccountInfo {
     pub balance: String,
     pub tx_count: usize,
 }
 
+#[derive(Debug, Serialize)]
+pub struct WalletAssets {
+    pub address: String,
+    pub assets: Vec<WalletAsset>,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletAsset {
+    pub unit: String,
+    // Amount held by this wallet
+    pub quantity: String,
+    // None when the asset is beyond MAX_ENRICHED_ASSETS or its lookup failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<AssetDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StakeInfo {
+    pub stake_address: String,
+    pub active: bool,
+    pub controlled_amount: String,
+    pub rewards_sum: String,
+    pub withdrawals_sum: String,
+    pub pool_id: Option<String>,
+}
+
+// Reject malformed addresses before spending a Blockfrost request on them
+fn validate_query_address(address: &str) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    validate_cardano_address(address).map(|_| ()).map_err(|e| {
+        tracing::warn!("Rejected wallet address in query parameter: {}", e);
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Invalid wallet address: {}", e) })),
+        )
+    })
+}
+
 pub async fn get_transactions(
     State(state): State<UserState>,
     Extension(_claims): Extension<Claims>, // JWT still required for authentication
-    axum::extract::Query(query): axum::extract::Query<TransactionQuery>,
+    ApiQuery(query): ApiQuery<TransactionQuery>,
 ) -> Result<Json<TransactionResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
     // Validate wallet address from query parameter
     if query.address.is_empty() {
         tracing::error!("Empty wallet address in query parameter");
@@ -68,9 +225,11 @@ pub async fn get_transactions(
             Json(serde_json::json!({ "error": "Missing wallet address parameter" })),
         ));
     }
+    validate_query_address(&query.address)?;
 
     let page = query.page.unwrap_or(1);
-    let count = query.count.unwrap_or(10);
+    let count = query.count.unwrap_or(10).clamp(1, MAX_TRANSACTION_COUNT);
+    let pages = query.pages.unwrap_or(1).clamp(1, MAX_TRANSACTION_PAGES);
 
     let address_preview = if query.address.len() >= 16 {
         &query.address[..16]
@@ -79,35 +238,210 @@ pub async fn get_transactions(
     };
 
     tracing::info!(
-        "Fetching transactions for address: {}... (page: {})",
+        "Fetching transactions for address: {}... (page: {}, pages: {})",
         address_preview,
-        page
+        page,
+        pages
     );
 
-    let transactions = state
-        .blockfrost
-        .get_address_transactions(&query.address, page, count)
+    let mut transactions = match blockfrost
+        .get_address_transactions(&query.address, page, count, pages)
         .await
-        .map_err(|e| {
-            tracing::error!("Blockfrost error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": format!("Failed to fetch transactions: {}", e) })),
-            )
-        })?;
+    {
+        Ok(transactions) => transactions,
+        // No data for this address is not an error for the client
+        Err(BlockfrostError::NotFound) => Vec::new(),
+        Err(e) => return Err(blockfrost_error_response(e, "Failed to fetch transactions")),
+    };
+
+    let mut warnings = blockfrost.network_warnings(&query.address);
+    if query.breakdown.unwrap_or(false) {
+        if transactions.len() > MAX_BREAKDOWN_LOOKUPS {
+            warnings.push(format!(
+                "breakdown_limited: first {} of {} transactions",
+                MAX_BREAKDOWN_LOOKUPS,
+                transactions.len()
+            ));
+        }
+        let looked_up = transactions.len().min(MAX_BREAKDOWN_LOOKUPS);
+        add_lovelace_flows(blockfrost, &query.address, &mut transactions[..looked_up]).await;
+    }
 
     Ok(Json(TransactionResponse {
         total: transactions.len(),
         page,
+        pages,
         transactions,
+        warnings,
+    }))
+}
+
+// Fill in direction/net_lovelace for `address`. Lookups run concurrently under the client's
+// semaphore and are cached per tx hash; a failed one only leaves that transaction without them
+async fn add_lovelace_flows(blockfrost: &BlockfrostClient, address: &str, transactions: &mut [Transaction]) {
+    // Blockfrost lists UTXO addresses in bech32, so hex wallet addresses are converted first
+    let wallet = crate::address::from_any(address)
+        .and_then(|parsed| crate::address::to_bech32(&parsed))
+        .unwrap_or_else(|_| address.to_string());
+
+    let utxos = futures_util::future::join_all(
+        transactions
+            .iter()
+            .map(|tx| blockfrost.get_transaction_utxos(&tx.tx_hash)),
+    )
+    .await;
+
+    for (tx, utxos) in transactions.iter_mut().zip(utxos) {
+        match utxos {
+            Ok(utxos) => tx.flow = Some(lovelace_flow(&utxos, &wallet)),
+            Err(e) => tracing::warn!("Failed to fetch UTXOs for {}: {}", tx.tx_hash, e),
+        }
+    }
+}
+
+// Starts from the current balance and walks back from the newest transaction, so the points
+// are exact even when older history is cut off
+pub async fn get_balance_history(
+    State(state): State<UserState>,
+    Extension(_claims): Extension<Claims>, // JWT still required for authentication
+    ApiQuery(query): ApiQuery<BalanceHistoryQuery>,
+) -> Result<Json<BalanceHistory>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    if query.address.is_empty() {
+        tracing::error!("Empty wallet address in query parameter");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Missing wallet address parameter" })),
+        ));
+    }
+    validate_query_address(&query.address)?;
+
+    let max_points = query
+        .max_points
+        .unwrap_or(DEFAULT_BALANCE_HISTORY_POINTS)
+        .clamp(1, (BALANCE_HISTORY_PAGE_SIZE * MAX_BALANCE_HISTORY_PAGES) as usize);
+
+    let mut history = balance_history(
+        blockfrost,
+        &query.address,
+        BALANCE_HISTORY_PAGE_SIZE,
+        MAX_BALANCE_HISTORY_PAGES,
+    )
+    .await
+    .map_err(|e| blockfrost_error_response(e, "Failed to fetch balance history"))?;
+    history.points = downsample(&history.points, max_points);
+    Ok(Json(history))
+}
+
+// Every point, oldest first, for the newest `page_size * max_pages` transactions
+async fn balance_history(
+    blockfrost: &BlockfrostClient,
+    address: &str,
+    page_size: u32,
+    max_pages: u32,
+) -> Result<BalanceHistory, BlockfrostError> {
+    let warnings = blockfrost.network_warnings(address);
+    let info = match blockfrost.get_address_info(address).await {
+        Ok(info) => info,
+        // Never seen on chain: no history yet is an empty series
+        Err(BlockfrostError::NotFound) => {
+            return Ok(BalanceHistory {
+                address: address.to_string(),
+                points: Vec::new(),
+                transactions_walked: 0,
+                truncated: false,
+                warnings,
+            });
+        }
+        Err(e) => return Err(e),
+    };
+
+    let transactions = match blockfrost
+        .get_recent_address_transactions(address, page_size, max_pages)
+        .await
+    {
+        Ok(transactions) => transactions,
+        Err(BlockfrostError::NotFound) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    // Same cached lookups as /api/user/transactions?breakdown=true, so the two share UTXO fetches
+    let utxos = futures_util::future::join_all(
+        transactions
+            .iter()
+            .map(|tx| blockfrost.get_transaction_utxos(&tx.tx_hash)),
+    )
+    .await;
+
+    // Every earlier point depends on each later net amount, so a missing one fails the series
+    let mut balance = lovelace_of(&info.amount);
+    let mut points = Vec::with_capacity(transactions.len());
+    for (tx, utxos) in transactions.iter().zip(utxos) {
+        points.push(BalancePoint {
+            block_time: tx.block_time,
+            cumulative_lovelace: balance,
+        });
+        balance = balance.saturating_sub(lovelace_flow(&utxos?, &info.address).net_lovelace);
+    }
+    points.reverse();
+
+    Ok(BalanceHistory {
+        address: address.to_string(),
+        transactions_walked: transactions.len(),
+        truncated: info.tx_count > transactions.len(),
+        points,
+        warnings,
+    })
+}
+
+// Evenly spaced points, each the last of its bucket, so the final balance is always included
+fn downsample(points: &[BalancePoint], max_points: usize) -> Vec<BalancePoint> {
+    if points.len() <= max_points {
+        return points.to_vec();
+    }
+    (1..=max_points)
+        .map(|bucket| points[bucket * points.len() / max_points - 1])
+        .collect()
+}
+
+pub async fn get_account_transactions(
+    State(state): State<UserState>,
+    Extension(claims): Extension<Claims>, // Stake address comes from the JWT
+    ApiQuery(query): ApiQuery<AccountTransactionQuery>,
+) -> Result<Json<AccountTransactionResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    let stake_address = claims.stake_address.ok_or_else(|| {
+        tracing::error!("No stake address in token claims");
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No stake address associated with this session" })),
+        )
+    })?;
+
+    let count = query.count.unwrap_or(10).clamp(1, MAX_TRANSACTION_COUNT);
+    let (transactions, addresses, addresses_queried) = blockfrost
+        .get_account_transactions(&stake_address, count, MAX_ACCOUNT_ADDRESSES)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch account transactions"))?;
+
+    Ok(Json(AccountTransactionResponse {
+        stake_address,
+        total: transactions.len(),
+        transactions,
+        addresses,
+        addresses_queried,
     }))
 }
 
 pub async fn get_summary(
     State(state): State<UserState>,
     Extension(claims): Extension<Claims>, // JWT still required for authentication and stake address
-    axum::extract::Query(query): axum::extract::Query<SummaryQuery>,
+    ApiQuery(query): ApiQuery<SummaryQuery>,
 ) -> Result<Json<WalletSummary>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
     // Validate wallet address from query parameter
     if query.address.is_empty() {
         tracing::error!("Empty wallet address in query parameter");
@@ -116,6 +450,7 @@ pub async fn get_summary(
             Json(serde_json::json!({ "error": "Missing wallet address parameter" })),
         ));
     }
+    validate_query_address(&query.address)?;
 
     let address_preview = if query.address.len() >= 16 {
         &query.address[..16]
@@ -128,22 +463,652 @@ pub async fn get_summary(
         address_preview
     );
 
-    let account_info = state
-        .blockfrost
-        .get_account_info(&query.address)
-        .await
-        .map_err(|e| {
-            tracing::error!("Blockfrost error: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": format!("Failed to fetch account info: {}", e) })),
-            )
-        })?;
+    let recent_count = query
+        .include_recent
+        .map(|n| n.min(MAX_RECENT_TRANSACTIONS))
+        .filter(|&n| n > 0);
+
+    let (account_info, recent) = tokio::join!(blockfrost.get_account_info(&query.address), async {
+        match recent_count {
+            Some(count) => Some(
+                blockfrost
+                    .get_address_transactions(&query.address, 1, count, 1)
+                    .await,
+            ),
+            None => None,
+        }
+    });
+
+    let account_info = match account_info {
+        Ok(info) => info,
+        // No on-chain data yet, so report an empty account
+        Err(BlockfrostError::NotFound) => AccountInfo {
+            balance: "0".to_string(),
+            tx_count: 0,
+        },
+        Err(e) => return Err(blockfrost_error_response(e, "Failed to fetch account info")),
+    };
+
+    let recent_transactions = match recent {
+        Some(Ok(transactions)) => Some(transactions),
+        Some(Err(BlockfrostError::NotFound)) => Some(Vec::new()),
+        Some(Err(e)) => {
+            return Err(blockfrost_error_response(e, "Failed to fetch recent transactions"));
+        }
+        None => None,
+    };
+
+    let warnings = blockfrost.network_warnings(&query.address);
 
     Ok(Json(WalletSummary {
         address: query.address,
         stake_address: claims.stake_address, // Still get stake address from JWT
         balance: account_info.balance,
         transaction_count: account_info.tx_count,
+        recent_transactions,
+        warnings,
     }))
-}
\ No newline at end of file
+}
+
+pub async fn get_staking(
+    State(state): State<UserState>,
+    Extension(claims): Extension<Claims>, // Stake address comes from the JWT
+) -> Result<Json<StakeInfo>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    let stake_address = claims.stake_address.ok_or_else(|| {
+        tracing::error!("No stake address in token claims");
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No stake address associated with this session" })),
+        )
+    })?;
+
+    let stake_info = blockfrost
+        .get_stake_account(&stake_address)
+        .await
+        .map_err(|e| blockfrost_error_response(e, "Failed to fetch staking info"))?;
+
+    Ok(Json(stake_info))
+}
+
+fn lovelace_of(amounts: &[BlockfrostAmount]) -> i64 {
+    amounts
+        .iter()
+        .filter(|a| a.unit == "lovelace")
+        .filter_map(|a| a.quantity.parse::<i64>().ok())
+        .fold(0, i64::saturating_add)
+}
+
+// Collateral inputs are only consumed when a script fails, so they don't count as spent
+fn lovelace_flow(utxos: &TxUtxos, wallet: &str) -> LovelaceFlow {
+    let spent = utxos
+        .inputs
+        .iter()
+        .filter(|input| !input.collateral && input.address == wallet)
+        .map(|input| lovelace_of(&input.amount))
+        .fold(0, i64::saturating_add);
+    let received = utxos
+        .outputs
+        .iter()
+        .filter(|output| output.address == wallet)
+        .map(|output| lovelace_of(&output.amount))
+        .fold(0, i64::saturating_add);
+    let net_lovelace = received.saturating_sub(spent);
+
+    let direction = if spent == 0 {
+        Direction::In
+    } else if utxos.outputs.iter().all(|output| output.address == wallet) {
+        Direction::SelfTransfer
+    } else if net_lovelace < 0 {
+        Direction::Out
+    } else {
+        Direction::In
+    };
+
+    LovelaceFlow { direction, net_lovelace }
+}
+
+pub async fn get_assets(
+    State(state): State<UserState>,
+    Extension(_claims): Extension<Claims>, // JWT still required for authentication
+    ApiQuery(query): ApiQuery<SummaryQuery>,
+) -> Result<Json<WalletAssets>, (StatusCode, Json<serde_json::Value>)> {
+    let blockfrost = state.blockfrost()?;
+
+    if query.address.is_empty() {
+        tracing::error!("Empty wallet address in query parameter");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Missing wallet address parameter" })),
+        ));
+    }
+    validate_query_address(&query.address)?;
+
+    let amounts = match blockfrost.get_address_info(&query.address).await {
+        Ok(info) => info.amount,
+        // No on-chain data yet, so the wallet holds nothing
+        Err(BlockfrostError::NotFound) => Vec::new(),
+        Err(e) => return Err(blockfrost_error_response(e, "Failed to fetch address assets")),
+    };
+
+    let held: Vec<_> = amounts.into_iter().filter(|a| a.unit != "lovelace").collect();
+
+    // Look up metadata concurrently; the client's semaphore bounds how many run at once
+    let details = futures_util::future::join_all(
+        held.iter()
+            .take(MAX_ENRICHED_ASSETS)
+            .map(|a| blockfrost.get_asset(&a.unit)),
+    )
+    .await;
+
+    let mut details = details.into_iter();
+    let assets: Vec<WalletAsset> = held
+        .into_iter()
+        .map(|amount| {
+            let detail = match details.next() {
+                Some(Ok(detail)) => Some(detail),
+                Some(Err(e)) => {
+                    tracing::warn!("Failed to fetch asset {}: {}", amount.unit, e);
+                    None
+                }
+                None => None,
+            };
+            WalletAsset {
+                unit: amount.unit,
+                quantity: amount.quantity,
+                detail,
+            }
+        })
+        .collect();
+
+    let warnings = blockfrost.network_warnings(&query.address);
+
+    Ok(Json(WalletAssets {
+        address: query.address,
+        total: assets.len(),
+        assets,
+        warnings,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{bech32, enterprise_address, MockBlockfrost};
+    use axum::extract::{Path, Query};
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    // A transaction on the mock chain, with the lovelace each address spent and received
+    #[derive(Clone)]
+    struct ChainTx {
+        hash: String,
+        block_time: u64,
+        inputs: Vec<(String, u64)>,
+        outputs: Vec<(String, u64)>,
+    }
+
+    fn lovelace(quantity: u64) -> serde_json::Value {
+        json!([{ "unit": "lovelace", "quantity": quantity.to_string() }])
+    }
+
+    // Serves address info, the address listing (paged, oldest first unless order=desc), tx
+    // details and UTXOs for `chain`, which holds every transaction of the queried address
+    fn chain_routes(chain: Vec<ChainTx>) -> Router {
+        let chain = Arc::new(chain);
+        let find = |chain: &[ChainTx], hash: &str| chain.iter().find(|tx| tx.hash == hash).cloned();
+
+        Router::new()
+            .route(
+                "/addresses/:address",
+                get({
+                    let chain = Arc::clone(&chain);
+                    move |Path(address): Path<String>| async move {
+                        // Blockfrost answers 404 for addresses it has never seen
+                        if chain.is_empty() {
+                            return Err(StatusCode::NOT_FOUND);
+                        }
+                        let sum = |entries: &[(String, u64)]| -> i64 {
+                            entries.iter().filter(|(a, _)| *a == address).map(|(_, q)| *q as i64).sum()
+                        };
+                        let balance: i64 = chain.iter().map(|tx| sum(&tx.outputs) - sum(&tx.inputs)).sum();
+                        Ok(Json(json!({
+                            "address": address,
+                            "amount": lovelace(balance as u64),
+                            "tx_count": chain.len(),
+                        })))
+                    }
+                }),
+            )
+            .route(
+                "/addresses/:address/transactions",
+                get({
+                    let chain = Arc::clone(&chain);
+                    move |Query(params): Query<HashMap<String, String>>| async move {
+                        let page: usize = params["page"].parse().unwrap();
+                        let count: usize = params["count"].parse().unwrap();
+                        let mut ordered: Vec<_> = chain.iter().enumerate().collect();
+                        if params.get("order").map(String::as_str) == Some("desc") {
+                            ordered.reverse();
+                        }
+                        let listed: Vec<_> = ordered
+                            .into_iter()
+                            .skip((page - 1) * count)
+                            .take(count)
+                            .map(|(height, tx)| {
+                                json!({ "tx_hash": tx.hash, "tx_index": 0, "block_height": height, "block_time": tx.block_time })
+                            })
+                            .collect();
+                        Json(json!(listed))
+                    }
+                }),
+            )
+            .route(
+                "/txs/:hash",
+                get({
+                    let chain = Arc::clone(&chain);
+                    move |Path(hash): Path<String>| async move {
+                        let tx = find(&chain, &hash).ok_or(StatusCode::NOT_FOUND)?;
+                        Ok::<_, StatusCode>(Json(json!({
+                            "hash": tx.hash, "block": format!("block-{}", tx.hash), "block_height": 1,
+                            "block_time": tx.block_time, "slot": tx.block_time, "index": 0, "fees": "170000"
+                        })))
+                    }
+                }),
+            )
+            .route(
+                "/txs/:hash/utxos",
+                get(move |Path(hash): Path<String>| async move {
+                    let tx = find(&chain, &hash).ok_or(StatusCode::NOT_FOUND)?;
+                    let inputs: Vec<_> = tx
+                        .inputs
+                        .iter()
+                        .map(|(address, amount)| {
+                            json!({ "address": address, "amount": lovelace(*amount), "tx_hash": "00", "output_index": 0 })
+                        })
+                        .collect();
+                    let outputs: Vec<_> = tx
+                        .outputs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, (address, amount))| {
+                            json!({ "address": address, "amount": lovelace(*amount), "output_index": index })
+                        })
+                        .collect();
+                    Ok::<_, StatusCode>(Json(json!({ "hash": tx.hash, "inputs": inputs, "outputs": outputs })))
+                }),
+            )
+    }
+
+    fn claims(wallet: &str) -> Claims {
+        Claims {
+            wallet_address: wallet.to_string(),
+            stake_address: None,
+            exp: usize::MAX,
+            iat: 0,
+            iss: "issuer".to_string(),
+            aud: "audience".to_string(),
+        }
+    }
+
+    fn user_state(mock: &MockBlockfrost) -> UserState {
+        UserState {
+            blockfrost: Some(Arc::new(mock.client())),
+        }
+    }
+
+    fn transaction_query(address: &str, count: u32, breakdown: Option<bool>) -> TransactionQuery {
+        TransactionQuery {
+            address: address.to_string(),
+            page: None,
+            count: Some(count),
+            pages: None,
+            breakdown,
+        }
+    }
+
+    fn utxo_lookups(mock: &MockBlockfrost) -> usize {
+        mock.requests().iter().filter(|path| path.ends_with("/utxos")).count()
+    }
+
+    // `count` transactions each paying 1 ADA to `wallet`, hashed by their position
+    fn deposits(wallet: &str, count: usize) -> Vec<ChainTx> {
+        (0..count)
+            .map(|i| ChainTx {
+                hash: format!("{:064x}", i),
+                block_time: 1_700_000_000 + i as u64,
+                inputs: vec![],
+                outputs: vec![(wallet.to_string(), 1_000_000)],
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn sending_ada_out_is_direction_out_with_a_negative_net() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let payee = bech32(&enterprise_address(0, 2));
+        // 10 ADA in, 5 ADA to the payee, the change back minus the 0.17 ADA fee
+        let chain = vec![ChainTx {
+            hash: "aa".repeat(32),
+            block_time: 1_700_000_000,
+            inputs: vec![(wallet.clone(), 10_000_000)],
+            outputs: vec![(payee, 5_000_000), (wallet.clone(), 4_830_000)],
+        }];
+        let mock = MockBlockfrost::start(chain_routes(chain)).await;
+
+        let Json(response) = get_transactions(
+            State(user_state(&mock)),
+            Extension(claims(&wallet)),
+            ApiQuery(transaction_query(&wallet, 10, Some(true))),
+        )
+        .await
+        .unwrap();
+
+        let tx = serde_json::to_value(&response.transactions[0]).unwrap();
+        assert_eq!(tx["direction"], "out");
+        assert_eq!(tx["net_lovelace"], -5_170_000);
+    }
+
+    #[tokio::test]
+    async fn breakdown_is_opt_in_and_capped() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(deposits(&wallet, MAX_BREAKDOWN_LOOKUPS + 5))).await;
+        let state = user_state(&mock);
+
+        let Json(plain) = get_transactions(
+            State(state.clone()),
+            Extension(claims(&wallet)),
+            ApiQuery(transaction_query(&wallet, 50, None)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(plain.total, MAX_BREAKDOWN_LOOKUPS + 5);
+        assert!(plain.transactions.iter().all(|tx| tx.flow.is_none()));
+        assert_eq!(utxo_lookups(&mock), 0);
+
+        let Json(detailed) = get_transactions(
+            State(state),
+            Extension(claims(&wallet)),
+            ApiQuery(transaction_query(&wallet, 50, Some(true))),
+        )
+        .await
+        .unwrap();
+        let with_flow = detailed.transactions.iter().filter(|tx| tx.flow.is_some()).count();
+        assert_eq!(with_flow, MAX_BREAKDOWN_LOOKUPS);
+        assert_eq!(utxo_lookups(&mock), MAX_BREAKDOWN_LOOKUPS);
+        assert!(detailed.warnings.iter().any(|w| w.starts_with("breakdown_limited")), "{:?}", detailed.warnings);
+    }
+
+    #[tokio::test]
+    async fn count_is_clamped_to_the_blockfrost_page_size() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(Vec::new())).await;
+
+        let Json(response) = get_transactions(
+            State(user_state(&mock)),
+            Extension(claims(&wallet)),
+            ApiQuery(transaction_query(&wallet, 100_000, None)),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.transactions.is_empty());
+        assert_eq!(
+            mock.requests(),
+            vec![format!("/api/v0/addresses/{}/transactions?page=1&count={}", wallet, MAX_TRANSACTION_COUNT)]
+        );
+    }
+
+    #[tokio::test]
+    async fn account_count_is_clamped_to_the_blockfrost_page_size() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let routes = chain_routes(Vec::new()).route(
+            "/accounts/:stake/addresses",
+            get({
+                let wallet = wallet.clone();
+                move || async move { Json(json!([{ "address": wallet }])) }
+            }),
+        );
+        let mock = MockBlockfrost::start(routes).await;
+        let mut session = claims(&wallet);
+        session.stake_address = Some("stake_test1account".to_string());
+
+        for (requested, sent) in [(100_000, MAX_TRANSACTION_COUNT), (0, 1)] {
+            let query = AccountTransactionQuery { count: Some(requested) };
+            let Json(response) = get_account_transactions(
+                State(user_state(&mock)),
+                Extension(session.clone()),
+                ApiQuery(query),
+            )
+            .await
+            .unwrap();
+
+            assert!(response.transactions.is_empty());
+            let listing = format!("/api/v0/addresses/{}/transactions?page=1&count={}&order=desc", wallet, sent);
+            assert_eq!(mock.requests().last(), Some(&listing), "count={}", requested);
+        }
+    }
+
+    #[tokio::test]
+    async fn consecutive_pages_are_concatenated_in_order() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(deposits(&wallet, 5))).await;
+        let mut query = transaction_query(&wallet, 2, None);
+        query.pages = Some(2);
+
+        let Json(response) = get_transactions(State(user_state(&mock)), Extension(claims(&wallet)), ApiQuery(query))
+            .await
+            .unwrap();
+
+        let hashes: Vec<_> = response.transactions.iter().map(|tx| tx.tx_hash.clone()).collect();
+        assert_eq!(hashes, (0..4).map(|i| format!("{:064x}", i)).collect::<Vec<_>>());
+        assert_eq!((response.total, response.pages), (4, 2));
+        let listings: Vec<_> = mock.requests().into_iter().filter(|path| path.contains("/transactions?")).collect();
+        assert_eq!(
+            listings,
+            [1, 2].map(|page| format!("/api/v0/addresses/{}/transactions?page={}&count=2", wallet, page))
+        );
+    }
+
+    #[tokio::test]
+    async fn summary_embeds_recent_transactions_on_request() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(deposits(&wallet, 5))).await;
+        let summary = |include_recent| {
+            get_summary(
+                State(user_state(&mock)),
+                Extension(claims(&wallet)),
+                ApiQuery(SummaryQuery { address: wallet.clone(), include_recent }),
+            )
+        };
+
+        let Json(response) = summary(Some(3)).await.unwrap();
+        let recent = response.recent_transactions.expect("recent transactions requested");
+        let hashes: Vec<_> = recent.iter().map(|tx| tx.tx_hash.clone()).collect();
+        assert_eq!(hashes, (0..3).map(|i| format!("{:064x}", i)).collect::<Vec<_>>());
+        assert_eq!((response.balance.as_str(), response.transaction_count), ("5000000", 5));
+
+        let Json(response) = summary(None).await.unwrap();
+        assert!(serde_json::to_value(&response).unwrap().get("recent_transactions").is_none());
+    }
+
+    #[tokio::test]
+    async fn account_info_is_cached_between_summaries() {
+        let address = enterprise_address(0, 1);
+        let wallet = bech32(&address);
+        let mock = MockBlockfrost::start(chain_routes(deposits(&wallet, 2))).await;
+        let state = user_state(&mock);
+
+        // The cache is keyed by bech32, so the wallet's hex form shares the entry
+        for query_address in [wallet.clone(), hex::encode(address.to_bytes())] {
+            let query = SummaryQuery { address: query_address, include_recent: None };
+            let Json(response) = get_summary(State(state.clone()), Extension(claims(&wallet)), ApiQuery(query))
+                .await
+                .unwrap();
+            assert_eq!(response.balance, "2000000");
+        }
+
+        assert_eq!(mock.requests(), [format!("/api/v0/addresses/{}", wallet)]);
+    }
+
+    #[tokio::test]
+    async fn mainnet_address_on_a_testnet_server_carries_a_warning() {
+        let mainnet_wallet = bech32(&enterprise_address(1, 1));
+        let mock = MockBlockfrost::start(chain_routes(Vec::new())).await;
+        let state = user_state(&mock);
+
+        let Json(response) = get_transactions(
+            State(state.clone()),
+            Extension(claims(&mainnet_wallet)),
+            ApiQuery(transaction_query(&mainnet_wallet, 10, None)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.warnings, ["network_mismatch: address=mainnet, server=preprod"]);
+
+        let testnet_wallet = bech32(&enterprise_address(0, 1));
+        let Json(response) = get_transactions(
+            State(state),
+            Extension(claims(&testnet_wallet)),
+            ApiQuery(transaction_query(&testnet_wallet, 10, None)),
+        )
+        .await
+        .unwrap();
+        assert!(serde_json::to_value(&response).unwrap().get("warnings").is_none());
+    }
+
+    #[tokio::test]
+    async fn wallet_assets_come_with_their_metadata() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let policy = "b0d07d45fe9514f80213f4020e5a61241458be626841cde717cb38a7";
+        let (token, nft) = (format!("{}6e7574636f696e", policy), format!("{}4e4654303031", policy));
+        let routes = Router::new()
+            .route(
+                "/addresses/:address",
+                get({
+                    let (token, nft) = (token.clone(), nft.clone());
+                    move |Path(address): Path<String>| async move {
+                        Json(json!({
+                            "address": address,
+                            "amount": [
+                                { "unit": "lovelace", "quantity": "42000000" },
+                                { "unit": token, "quantity": "12" },
+                                { "unit": nft, "quantity": "1" }
+                            ],
+                            "tx_count": 3
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/assets/:unit",
+                get(move |Path(unit): Path<String>| async move {
+                    let name = &unit[policy.len()..];
+                    Json(json!({
+                        "asset": unit, "policy_id": policy, "asset_name": name,
+                        "fingerprint": format!("asset1{}", name), "quantity": 1000,
+                        "metadata": { "name": format!("name-{}", name), "decimals": 0 },
+                        "onchain_metadata": null
+                    }))
+                }),
+            );
+        let mock = MockBlockfrost::start(routes).await;
+
+        let Json(response) = get_assets(
+            State(user_state(&mock)),
+            Extension(claims(&wallet)),
+            ApiQuery(SummaryQuery { address: wallet.clone(), include_recent: None }),
+        )
+        .await
+        .unwrap();
+
+        // Lovelace isn't an asset; each held unit keeps its own quantity next to the metadata
+        assert_eq!(response.total, 2);
+        let held: Vec<_> = response
+            .assets
+            .iter()
+            .map(|asset| {
+                let detail = asset.detail.as_ref().expect("metadata attached");
+                (asset.unit.as_str(), asset.quantity.as_str(), detail.metadata.as_ref().unwrap()["name"].clone())
+            })
+            .collect();
+        assert_eq!(
+            held,
+            [
+                (token.as_str(), "12", json!("name-6e7574636f696e")),
+                (nft.as_str(), "1", json!("name-4e4654303031"))
+            ]
+        );
+        assert_eq!(response.assets[0].detail.as_ref().unwrap().quantity, "1000");
+    }
+
+    // +10 ADA, 3 ADA sent away (0.17 ADA fee), +2 ADA: 8.83 ADA today
+    fn history_chain(wallet: &str) -> Vec<ChainTx> {
+        let other = bech32(&enterprise_address(0, 9));
+        vec![
+            ChainTx {
+                hash: "01".repeat(32),
+                block_time: 1_700_000_000,
+                inputs: vec![(other.clone(), 20_000_000)],
+                outputs: vec![(wallet.to_string(), 10_000_000), (other.clone(), 9_830_000)],
+            },
+            ChainTx {
+                hash: "02".repeat(32),
+                block_time: 1_700_000_100,
+                inputs: vec![(wallet.to_string(), 10_000_000)],
+                outputs: vec![(other.clone(), 3_000_000), (wallet.to_string(), 6_830_000)],
+            },
+            ChainTx {
+                hash: "03".repeat(32),
+                block_time: 1_700_000_200,
+                inputs: vec![(other.clone(), 5_000_000)],
+                outputs: vec![(wallet.to_string(), 2_000_000), (other, 2_830_000)],
+            },
+        ]
+    }
+
+    fn series(history: &BalanceHistory) -> Vec<(u64, i64)> {
+        history.points.iter().map(|p| (p.block_time, p.cumulative_lovelace)).collect()
+    }
+
+    #[tokio::test]
+    async fn balance_history_walks_back_from_the_current_balance() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(history_chain(&wallet))).await;
+
+        let history = balance_history(&mock.client(), &wallet, 100, 2).await.unwrap();
+
+        assert_eq!(
+            series(&history),
+            vec![(1_700_000_000, 10_000_000), (1_700_000_100, 6_830_000), (1_700_000_200, 8_830_000)]
+        );
+        assert_eq!((history.transactions_walked, history.truncated), (3, false));
+        assert!(mock.requests().iter().any(|path| path.ends_with("&order=desc")), "{:?}", mock.requests());
+    }
+
+    #[tokio::test]
+    async fn truncated_history_keeps_the_newest_points_exact() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(history_chain(&wallet))).await;
+
+        // Room for the two newest transactions only
+        let history = balance_history(&mock.client(), &wallet, 2, 1).await.unwrap();
+
+        assert_eq!(series(&history), vec![(1_700_000_100, 6_830_000), (1_700_000_200, 8_830_000)]);
+        assert_eq!((history.transactions_walked, history.truncated), (2, true));
+    }
+
+    #[tokio::test]
+    async fn address_without_history_is_an_empty_series() {
+        let wallet = bech32(&enterprise_address(0, 1));
+        let mock = MockBlockfrost::start(chain_routes(Vec::new())).await;
+
+        let history = balance_history(&mock.client(), &wallet, 100, 2).await.unwrap();
+
+        assert!(history.points.is_empty());
+        assert!(!history.truncated);
+    }
+}
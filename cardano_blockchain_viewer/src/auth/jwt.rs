@@ -1,6 +1,12 @@
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
+use jsonwebtoken::{encode, decode, Algorithm, Header, Validation, EncodingKey, DecodingKey};
 use serde::{Deserialize, Serialize};
 
+// The only algorithm tokens are signed with and accepted in, so encoding and validation can't drift apart
+const JWT_ALGORITHM: Algorithm = Algorithm::HS256;
+
+// Allowed clock skew in seconds when checking `exp`
+const JWT_LEEWAY_SECS: u64 = 30;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub wallet_address: String,
@@ -8,17 +14,22 @@ pub struct Claims {
     pub exp: usize,
     // issued at
     pub iat: usize,
+    // issuer and audience, so tokens from this service aren't accepted by others sharing the secret
+    pub iss: String,
+    pub aud: String,
 }
 
 pub struct JwtManager {
     secret: String,
+    issuer: String,
+    audience: String,
 }
 
 impl JwtManager {
 
     // It creates an instance for the JwtManager everytime it is called
-    pub fn new(secret: String) -> Self {
-        Self { secret }
+    pub fn new(secret: String, issuer: String, audience: String) -> Self {
+        Self { secret, issuer, audience }
     }
 
     // A function inside a impl which can be also known as class in rust, which takes an instance, wallet address and stake address
@@ -38,12 +49,14 @@ impl JwtManager {
             stake_address,
             exp: expiration,
             iat: now.timestamp() as usize,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
         };
 
         // Create a JWT string by combining a header, payload, and a secret key.
         encode(
-            // sets the default header (algorithm = HS256)
-            &Header::default(),
+            // header pinned to the same algorithm validate_token expects
+            &Header::new(JWT_ALGORITHM),
             // the payload
             &claims,
             // The secret key used to sign the token
@@ -54,12 +67,72 @@ impl JwtManager {
 
     // For validating the token
     pub fn validate_token(&self, token: &str) -> Result<Claims, String> {
+        // Reject tokens whose header names any other algorithm
+        let mut validation = Validation::new(JWT_ALGORITHM);
+        validation.validate_exp = true;
+        validation.leeway = JWT_LEEWAY_SECS;
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
         decode::<Claims>(
             token,
             &DecodingKey::from_secret(self.secret.as_bytes()),
-            &Validation::default()
+            &validation
         )
         .map(|data| data.claims)
         .map_err(|e| format!("Invalid token: {}", e))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(secret: &str) -> JwtManager {
+        JwtManager::new(secret.to_string(), "cardano-viewer".to_string(), "cardano-viewer-web".to_string())
+    }
+
+    // Claims generate_token would issue right now
+    fn fresh_claims() -> Claims {
+        let now = chrono::Utc::now().timestamp() as usize;
+        Claims {
+            wallet_address: "addr_test1vz".to_string(),
+            stake_address: None,
+            exp: now + 3600,
+            iat: now,
+            iss: "cardano-viewer".to_string(),
+            aud: "cardano-viewer-web".to_string(),
+        }
+    }
+
+    #[test]
+    fn token_signed_with_another_algorithm_or_secret_is_rejected() {
+        let jwt = manager("secret");
+        let own = jwt.generate_token("addr_test1vz".to_string(), None).unwrap();
+        assert_eq!(jwt.validate_token(&own).unwrap().wallet_address, "addr_test1vz");
+
+        let hs512 = encode(&Header::new(Algorithm::HS512), &fresh_claims(), &EncodingKey::from_secret(b"secret"))
+            .unwrap();
+        assert!(jwt.validate_token(&hs512).is_err());
+
+        let other_secret = manager("other-secret").generate_token("addr_test1vz".to_string(), None).unwrap();
+        assert!(jwt.validate_token(&other_secret).is_err());
+    }
+
+    #[test]
+    fn token_for_another_audience_or_issuer_is_rejected() {
+        let jwt = manager("secret");
+        let sign = |claims: &Claims| {
+            encode(&Header::new(JWT_ALGORITHM), claims, &EncodingKey::from_secret(b"secret")).unwrap()
+        };
+        assert!(jwt.validate_token(&sign(&fresh_claims())).is_ok());
+
+        let other_audience = Claims { aud: "another-service".to_string(), ..fresh_claims() };
+        let error = jwt.validate_token(&sign(&other_audience)).unwrap_err();
+        assert!(error.contains("InvalidAudience"), "{}", error);
+
+        let other_issuer = Claims { iss: "another-service".to_string(), ..fresh_claims() };
+        let error = jwt.validate_token(&sign(&other_issuer)).unwrap_err();
+        assert!(error.contains("InvalidIssuer"), "{}", error);
+    }
+}
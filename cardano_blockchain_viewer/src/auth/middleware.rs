@@ -9,6 +9,7 @@ use serde_json::json;
 use std::sync::Arc;
 
 use super::jwt::JwtManager;
+use crate::api::auth::codes;
 
 pub async fn auth_middleware(
     // Arc is a smart pointer that allows multiple threads to share ownership of the same value safely
@@ -34,7 +35,7 @@ pub async fn auth_middleware(
             .ok_or_else(|| {
                 (
                     StatusCode::UNAUTHORIZED,
-                    Json(json!({ "error": "Missing authorization token"})),
+                    Json(json!({ "error": "Missing authorization token", "code": codes::MISSING_TOKEN })),
                 )
             })?;
 
@@ -43,7 +44,7 @@ pub async fn auth_middleware(
         let claims = jwt_manager.validate_token(token).map_err(|e| {
                 (
                     StatusCode::UNAUTHORIZED,
-                    Json(json!({ "error": format!("Invalid token: {}", e) })),
+                    Json(json!({ "error": format!("Invalid token: {}", e), "code": codes::INVALID_TOKEN })),
                 )
         })?;
 
@@ -0,0 +1,59 @@
+use std::fmt;
+
+/// Errors returned by the Blockfrost client
+#[derive(Debug)]
+pub enum BlockfrostError {
+    /// Blockfrost has no data for the requested resource (404)
+    NotFound,
+    /// The API key was rejected (401/403)
+    Unauthorized(String),
+    /// Blockfrost is throttling us (429) or has banned the key (418)
+    RateLimited,
+    /// The request never got a response (DNS, TLS, timeout, ...)
+    Network(String),
+    /// The response body could not be parsed
+    Decode(String),
+    /// The given address could not be converted to bech32
+    AddressConversion(String),
+    /// Any other non-success response from Blockfrost
+    Upstream { status: u16, message: String },
+}
+
+impl BlockfrostError {
+    // Map a non-success HTTP status from Blockfrost to an error variant
+    pub fn from_status(status: reqwest::StatusCode, body: &str) -> Self {
+        match status.as_u16() {
+            404 => Self::NotFound,
+            401 | 403 => Self::Unauthorized(body.to_string()),
+            418 | 429 => Self::RateLimited,
+            code => Self::Upstream {
+                status: code,
+                message: body.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for BlockfrostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Resource not found on Blockfrost"),
+            Self::Unauthorized(msg) => write!(f, "Blockfrost rejected the API key: {}", msg),
+            Self::RateLimited => write!(f, "Blockfrost rate limit exceeded"),
+            Self::Network(msg) => write!(f, "Request failed: {}", msg),
+            Self::Decode(msg) => write!(f, "Failed to parse response: {}", msg),
+            Self::AddressConversion(msg) => write!(f, "Address conversion failed: {}", msg),
+            Self::Upstream { status, message } => {
+                write!(f, "Blockfrost error: {} - {}", status, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BlockfrostError {}
+
+impl From<reqwest::Error> for BlockfrostError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Network(e.to_string())
+    }
+}
@@ -0,0 +1,84 @@
+// Deserializers for fields Blockfrost sends as strings but compatible backends/proxies send as numbers
+use serde::{Deserialize, Deserializer, de::Error};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Unsigned(u64),
+    Signed(i64),
+}
+
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            Self::String(s) => s,
+            Self::Unsigned(n) => n.to_string(),
+            Self::Signed(n) => n.to_string(),
+        }
+    }
+}
+
+/// Lovelace and asset quantities: `"1000"` or `1000`, always kept as a string
+pub fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(StringOrNumber::deserialize(deserializer)?.into_string())
+}
+
+/// Like `string_or_number`, also accepting null
+pub fn option_string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<StringOrNumber>::deserialize(deserializer)?.map(StringOrNumber::into_string))
+}
+
+/// `number_or_string`, then converted to unix seconds if it was sent in milliseconds
+pub fn unix_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    number_or_string(deserializer).map(crate::models::normalize_unix_time)
+}
+
+/// Timestamps and heights: `1700000000` or `"1700000000"`
+pub fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::Unsigned(n) => Ok(n),
+        StringOrNumber::String(s) => s
+            .parse()
+            .map_err(|_| D::Error::custom(format!("expected an unsigned integer, got \"{}\"", s))),
+        StringOrNumber::Signed(n) => Err(D::Error::custom(format!("expected an unsigned integer, got {}", n))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blockfrost::{BlockfrostAmount, BlockfrostTxDetails};
+    use serde_json::json;
+
+    #[test]
+    fn quantity_is_read_from_a_string_or_a_number() {
+        for quantity in [json!("42000000"), json!(42000000)] {
+            let amount: BlockfrostAmount =
+                serde_json::from_value(json!({ "unit": "lovelace", "quantity": quantity })).unwrap();
+            assert_eq!(amount.quantity, "42000000");
+        }
+        // Negative quantities (burns) keep their sign
+        let burn: BlockfrostAmount = serde_json::from_value(json!({ "unit": "ff", "quantity": -12 })).unwrap();
+        assert_eq!(burn.quantity, "-12");
+    }
+
+    #[test]
+    fn fees_and_block_time_are_read_from_strings_or_numbers() {
+        let details = |fees: serde_json::Value, block_time: serde_json::Value| {
+            serde_json::from_value::<BlockfrostTxDetails>(json!({
+                "hash": "aa", "block": "bb", "block_height": 10, "block_time": block_time,
+                "slot": 500, "index": 0, "fees": fees
+            }))
+        };
+
+        for (fees, block_time) in [(json!("170000"), json!(1700000000)), (json!(170000), json!("1700000000"))] {
+            let tx = details(fees, block_time).unwrap();
+            assert_eq!((tx.fees.as_str(), tx.block_time), ("170000", 1700000000));
+        }
+        assert!(details(json!("170000"), json!("yesterday")).is_err());
+        assert!(details(json!("170000"), json!(-1)).is_err());
+        assert!(details(json!(null), json!(1700000000)).is_err());
+    }
+}
@@ -1,75 +1,54 @@
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 
-/// Convert hex address to bech32 format for Blockfrost API
-/// Blockfrost requires bech32 addresses (addr1...), not hex
-fn hex_to_bech32_address(hex_address: &str) -> Result<String, String> {
-    use cardano_serialization_lib::address::Address;
-    
-    tracing::debug!("Converting hex address to bech32: {} ({} chars)", &hex_address[..hex_address.len().min(32)], hex_address.len());
-    
-    // Try to decode hex address
-    let address_bytes = hex::decode(hex_address)
-        .map_err(|e| {
-            tracing::error!("Failed to decode hex address: {}", e);
-            format!("Invalid hex address: {}", e)
-        })?;
-    
-    tracing::debug!("Decoded {} bytes from hex", address_bytes.len());
-    
-    // Create Address from bytes
-    let address = Address::from_bytes(address_bytes)
-        .map_err(|e| {
-            tracing::error!("Failed to create Address from bytes: {}", e);
-            format!("Invalid address bytes: {}", e)
-        })?;
-    
-    // Convert to bech32
-    let bech32 = address.to_bech32(None)
-        .map_err(|e| {
-            tracing::error!("Failed to convert to bech32: {}", e);
-            format!("Failed to convert to bech32: {}", e)
-        })?;
-    
-    tracing::debug!("Converted to bech32: {} ({} chars)", &bech32[..bech32.len().min(32)], bech32.len());
-    
-    Ok(bech32)
-}
+mod cache;
+mod error;
+mod lenient;
 
-/// Detect network from address format
-/// Returns "mainnet", "testnet", or "unknown"
-fn detect_network_from_address(address: &str) -> &'static str {
-    if address.starts_with("addr1") {
-        "mainnet"
-    } else if address.starts_with("addr_test") {
-        "testnet"
-    } else {
-        "unknown"
-    }
+use cache::TtlCache;
+pub use error::BlockfrostError;
+
+use crate::config::env_parse;
+use crate::models::Metrics;
+
+// Defaults for the transaction detail cache (BLOCKFROST_TX_CACHE_TTL_SECS / BLOCKFROST_TX_CACHE_MAX_ENTRIES)
+const DEFAULT_TX_CACHE_TTL_SECS: u64 = 600;
+const DEFAULT_TX_CACHE_MAX_ENTRIES: usize = 1000;
+
+// Defaults for the per-address account info cache (BLOCKFROST_ACCOUNT_CACHE_TTL_SECS /
+// BLOCKFROST_ACCOUNT_CACHE_MAX_ENTRIES). Balances only change when a tx lands, so a short TTL is enough
+const DEFAULT_ACCOUNT_CACHE_TTL_SECS: u64 = 30;
+const DEFAULT_ACCOUNT_CACHE_MAX_ENTRIES: usize = 1000;
+
+// Defaults for HTTP connection reuse (BLOCKFROST_POOL_MAX_IDLE / BLOCKFROST_POOL_IDLE_TIMEOUT_SECS).
+// A handful of kept-alive connections is plenty for Blockfrost's per-key rate limit
+const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 8;
+const DEFAULT_POOL_IDLE_TIMEOUT_SECS: u64 = 90;
+
+// Default cap on simultaneous Blockfrost requests from get_json (BLOCKFROST_MAX_CONCURRENT)
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Check whether a response body is an HTML page rather than JSON
+fn is_html(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    trimmed.starts_with("<!DOCTYPE") || trimmed.starts_with("<html")
 }
 
-/// Normalize address format - convert hex to bech32 if needed
-/// Returns bech32 address if input is hex, otherwise returns as-is
-fn normalize_address_for_blockfrost(address: &str) -> Result<String, String> {
+/// Convert an address to the bech32 form Blockfrost requires and detect its network
+/// Hex addresses (as returned by CIP-30 wallets) are decoded; bech32 round-trips unchanged
+fn normalize_address_for_blockfrost(address: &str) -> Result<(String, &'static str), String> {
     tracing::debug!("Normalizing address: {} ({} chars)", &address[..address.len().min(32)], address.len());
-    
-    // Check if it's already bech32 (starts with addr)
-    if address.starts_with("addr") {
-        tracing::debug!("Address is already bech32 format");
-        let network = detect_network_from_address(address);
-        tracing::info!("Detected address network: {} (address: {}...)", network, &address[..address.len().min(20)]);
-        return Ok(address.to_string());
-    }
-    
-    // Check if it looks like hex (even length, hex characters)
-    if address.len() % 2 == 0 && address.chars().all(|c| c.is_ascii_hexdigit()) {
-        tracing::debug!("Address appears to be hex format, converting...");
-        return hex_to_bech32_address(address);
-    }
-    
-    // If it doesn't match either format, try hex conversion anyway
-    tracing::warn!("Address format unclear, attempting hex conversion: {}", &address[..address.len().min(32)]);
-    hex_to_bech32_address(address)
+
+    let parsed = crate::address::from_any(address).map_err(|e| e.to_string())?;
+    let bech32 = crate::address::to_bech32(&parsed).map_err(|e| e.to_string())?;
+    let network = crate::address::network_of(&parsed);
+    tracing::debug!("Detected address network: {} (address: {}...)", network, &bech32[..bech32.len().min(20)]);
+
+    Ok((bech32, network))
 }
 
 #[derive(Clone)]
@@ -77,6 +56,17 @@ pub struct BlockfrostClient {
     client: Client,
     api_key: String,
     base_url: String,
+    network: &'static str,
+    // Confirmed transactions never change, so details are cached to spare the rate limit
+    tx_cache: TtlCache<BlockfrostTxDetails>,
+    // Inputs and outputs of confirmed transactions, same TTL and size as tx_cache
+    utxo_cache: TtlCache<TxUtxos>,
+    // Balance and tx count keyed by bech32 address, refreshed once the TTL runs out
+    account_cache: TtlCache<crate::api::user::AccountInfo>,
+    // Shared by every get_json call so fan-out lookups (e.g. asset enrichment) stay under the rate limit
+    request_slots: Arc<Semaphore>,
+    // Per-endpoint latency totals for /metrics; None leaves latency in the logs only
+    metrics: Option<Arc<Metrics>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,17 +74,20 @@ pub struct BlockfrostTransaction {
     pub tx_hash: String,
     pub tx_index: Option<u32>,
     pub block_height: u64,
+    #[serde(deserialize_with = "lenient::unix_time")]
     pub block_time: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockfrostTxDetails {
     pub hash: String,
     pub block: String,
     pub block_height: u64,
+    #[serde(deserialize_with = "lenient::unix_time")]
     pub block_time: u64,
     pub slot: Option<u64>,
     pub index: Option<u32>,
+    #[serde(deserialize_with = "lenient::string_or_number")]
     pub fees: String,
 }
 
@@ -112,10 +105,150 @@ pub struct BlockfrostAddressInfo {
     pub r#type: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockfrostAmount {
     pub unit: String,
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub quantity: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxMetadatum {
+    pub label: String,
+    pub json_metadata: serde_json::Value,
+}
+
+// One transaction's metadata under a label, from /metadata/txs/labels/{label}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelMetadatum {
+    pub tx_hash: String,
+    pub json_metadata: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxUtxos {
+    pub hash: String,
+    pub inputs: Vec<TxUtxoInput>,
+    pub outputs: Vec<TxUtxoOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxUtxoInput {
+    pub address: String,
+    pub amount: Vec<BlockfrostAmount>,
+    // The output being spent: source transaction and its output index
+    pub tx_hash: String,
+    pub output_index: u32,
+    #[serde(default)]
+    pub collateral: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxUtxoOutput {
+    pub address: String,
+    pub amount: Vec<BlockfrostAmount>,
+    pub output_index: u32,
+}
+
+// Native asset detail from /assets/{unit}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetDetail {
+    pub asset: String,
+    pub policy_id: String,
+    #[serde(default)]
+    pub asset_name: Option<String>,
+    pub fingerprint: String,
+    // Total minted supply, not the amount held by any one wallet
+    #[serde(deserialize_with = "lenient::string_or_number")]
     pub quantity: String,
+    // Off-chain registry metadata (name, ticker, decimals, ...)
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    // CIP-25/CIP-68 metadata from the minting transaction
+    #[serde(default)]
+    pub onchain_metadata: Option<serde_json::Value>,
+}
+
+// Block detail from /blocks/{hash_or_number}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockInfo {
+    pub hash: String,
+    // height, slot and epoch are null for the genesis block
+    pub height: Option<u64>,
+    pub slot: Option<u64>,
+    pub epoch: Option<u64>,
+    pub time: i64,
+    pub tx_count: u64,
+    pub size: u64,
+    // Lovelace totals as strings, null for empty blocks
+    #[serde(default, deserialize_with = "lenient::option_string_or_number")]
+    pub output: Option<String>,
+    #[serde(default, deserialize_with = "lenient::option_string_or_number")]
+    pub fees: Option<String>,
+    pub previous_block: Option<String>,
+    pub next_block: Option<String>,
+    pub confirmations: u64,
+}
+
+// Epoch summary from /epochs/latest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochInfo {
+    pub epoch: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub block_count: u64,
+    pub tx_count: u64,
+    // Lovelace totals as strings
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub output: String,
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub fees: String,
+    // Null until the epoch's stake snapshot is taken
+    pub active_stake: Option<String>,
+}
+
+// Protocol parameters in effect for an epoch, from /epochs/{number}/parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochParams {
+    pub epoch: u64,
+    pub min_fee_a: u64,
+    pub min_fee_b: u64,
+    pub max_block_size: u64,
+    pub max_tx_size: u64,
+    pub key_deposit: String,
+    pub pool_deposit: String,
+    pub min_pool_cost: String,
+    pub protocol_major_ver: u32,
+    pub protocol_minor_ver: u32,
+    // Only set from the Alonzo/Babbage eras onwards
+    #[serde(default)]
+    pub coins_per_utxo_size: Option<String>,
+    #[serde(default)]
+    pub max_val_size: Option<String>,
+    #[serde(default)]
+    pub collateral_percent: Option<u32>,
+    #[serde(default)]
+    pub max_collateral_inputs: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockfrostAccount {
+    pub stake_address: String,
+    pub active: bool,
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub controlled_amount: String,
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub rewards_sum: String,
+    #[serde(deserialize_with = "lenient::string_or_number")]
+    pub withdrawals_sum: String,
+    #[serde(default)]
+    pub pool_id: Option<String>,
+}
+
+// One entry of /accounts/{stake_address}/addresses
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockfrostAccountAddress {
+    pub address: String,
 }
 
 // #[derive(Debug, Serialize, Deserialize)]
@@ -126,12 +259,28 @@ pub struct BlockfrostAmount {
 // }
 
 impl BlockfrostClient {
+    /// Client for the public Blockfrost endpoint of `network`.
+    /// BLOCKFROST_BASE_URL overrides the URL (e.g. a self-hosted instance or a mock server)
     pub fn new(api_key: String, network: &str) -> Self {
-        let raw_base = match network {
-            "mainnet" => "https://cardano-mainnet.blockfrost.io/api/v0",
-            "preprod" => "https://cardano-preprod.blockfrost.io/api/v0",
-            "preview" => "https://cardano-preview.blockfrost.io/api/v0",
-            _ => "https://cardano-preprod.blockfrost.io/api/v0",
+        let base_url = std::env::var("BLOCKFROST_BASE_URL").unwrap_or_else(|_| {
+            match network {
+                "mainnet" => "https://cardano-mainnet.blockfrost.io/api/v0",
+                "preprod" => "https://cardano-preprod.blockfrost.io/api/v0",
+                "preview" => "https://cardano-preview.blockfrost.io/api/v0",
+                _ => "https://cardano-preprod.blockfrost.io/api/v0",
+            }
+            .to_string()
+        });
+
+        Self::with_base_url(api_key, network, &base_url)
+    }
+
+    /// Client for an explicit base URL; `network` is still used for address network checks
+    pub fn with_base_url(api_key: String, network: &str, raw_base: &str) -> Self {
+        let network = match network {
+            "mainnet" => "mainnet",
+            "preview" => "preview",
+            _ => "preprod",
         };
 
         // Defensive: ensure /api/v0 is present even if an env override strips it
@@ -148,63 +297,278 @@ impl BlockfrostClient {
         };
         tracing::info!("Blockfrost base URL: {}", base_url);
 
+        let cache_ttl = env_parse("BLOCKFROST_TX_CACHE_TTL_SECS", DEFAULT_TX_CACHE_TTL_SECS);
+        let cache_max_entries =
+            env_parse("BLOCKFROST_TX_CACHE_MAX_ENTRIES", DEFAULT_TX_CACHE_MAX_ENTRIES);
+        let account_cache_ttl =
+            env_parse("BLOCKFROST_ACCOUNT_CACHE_TTL_SECS", DEFAULT_ACCOUNT_CACHE_TTL_SECS);
+        let account_cache_max_entries = env_parse(
+            "BLOCKFROST_ACCOUNT_CACHE_MAX_ENTRIES",
+            DEFAULT_ACCOUNT_CACHE_MAX_ENTRIES,
+        );
+
+        let pool_max_idle = env_parse("BLOCKFROST_POOL_MAX_IDLE", DEFAULT_POOL_MAX_IDLE_PER_HOST);
+        let pool_idle_timeout =
+            env_parse("BLOCKFROST_POOL_IDLE_TIMEOUT_SECS", DEFAULT_POOL_IDLE_TIMEOUT_SECS);
+        let client = Client::builder()
+            .pool_max_idle_per_host(pool_max_idle)
+            .pool_idle_timeout(Duration::from_secs(pool_idle_timeout))
+            .build()
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to build Blockfrost HTTP client ({}), using defaults", e);
+                Client::new()
+            });
+        tracing::debug!(
+            "Blockfrost HTTP pool: max {} idle per host, {}s idle timeout",
+            pool_max_idle,
+            pool_idle_timeout
+        );
+
+        let max_concurrent =
+            env_parse("BLOCKFROST_MAX_CONCURRENT", DEFAULT_MAX_CONCURRENT_REQUESTS).max(1);
+
         Self {
-            client: Client::new(),
+            client,
             api_key,
             base_url: base_url.to_string(),
+            network,
+            tx_cache: TtlCache::new(Duration::from_secs(cache_ttl), cache_max_entries),
+            utxo_cache: TtlCache::new(Duration::from_secs(cache_ttl), cache_max_entries),
+            account_cache: TtlCache::new(
+                Duration::from_secs(account_cache_ttl),
+                account_cache_max_entries,
+            ),
+            request_slots: Arc::new(Semaphore::new(max_concurrent)),
+            metrics: None,
         }
     }
 
-    pub async fn get_address_transactions(
-        &self,
-        address: &str,
-        page: u32,
-        count: u32,
-    ) -> Result<Vec<crate::api::user::Transaction>, String> {
-        // Convert hex address to bech32 if needed (Blockfrost requires bech32)
-        let bech32_address = normalize_address_for_blockfrost(address)
-            .map_err(|e| format!("Address conversion failed: {}", e))?;
-        
-        // Detect network from address and warn if mismatch
-        let address_network = detect_network_from_address(&bech32_address);
-        let configured_network = if self.base_url.contains("mainnet") {
-            "mainnet"
-        } else if self.base_url.contains("preprod") {
-            "preprod"
-        } else if self.base_url.contains("preview") {
-            "preview"
-        } else {
-            "unknown"
+    // Aggregate request latency per endpoint into the shared metrics
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    // Send a request, logging how long Blockfrost took to answer
+    async fn send(&self, path: &str, request: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let endpoint = endpoint_label(path);
+        let started = Instant::now();
+        let result = request.send().await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let status = match &result {
+            Ok(response) => response.status().as_u16(),
+            // 0 marks requests that never got a response
+            Err(_) => 0,
         };
-        
-        if address_network == "mainnet" && configured_network != "mainnet" {
-            tracing::warn!(
-                "⚠️  Network mismatch detected! Address is mainnet (addr1...) but Blockfrost is configured for {}",
-                configured_network
-            );
-            tracing::warn!("   This will likely result in no data being returned. Consider using a {} address or configuring Blockfrost for mainnet.", configured_network);
-        } else if address_network == "testnet" && configured_network == "mainnet" {
-            tracing::warn!(
-                "⚠️  Network mismatch detected! Address is testnet (addr_test...) but Blockfrost is configured for mainnet"
+        tracing::info!(endpoint = %endpoint, status, elapsed_ms, "Blockfrost request completed");
+        if status == 401 || status == 403 {
+            tracing::error!(
+                "🔑 Blockfrost rejected the project_id ({}): BLOCKFROST_API_KEY may be wrong or not for the {} network",
+                status,
+                self.network
             );
         }
-        
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_blockfrost_latency(&endpoint, elapsed_ms);
+        }
+        result
+    }
+
+    /// Network this client was configured for
+    fn configured_network(&self) -> &'static str {
+        self.network
+    }
+
+    /// Machine-readable note when an address network can't be served by this client
+    /// e.g. "network_mismatch: address=mainnet, server=preprod"
+    fn network_mismatch(&self, address_network: &str) -> Option<String> {
+        let configured_network = self.configured_network();
+        let mismatched = (address_network == "mainnet" && configured_network != "mainnet")
+            || (address_network == "testnet" && configured_network == "mainnet");
+
+        mismatched.then(|| {
+            format!(
+                "network_mismatch: address={}, server={}",
+                address_network, configured_network
+            )
+        })
+    }
+
+    /// Warnings to return to the client alongside data for this address
+    pub fn network_warnings(&self, address: &str) -> Vec<String> {
+        normalize_address_for_blockfrost(address)
+            .ok()
+            .and_then(|(_, network)| self.network_mismatch(network))
+            .into_iter()
+            .collect()
+    }
+
+    /// Convert an address to bech32 (Blockfrost requires bech32) and detect its network,
+    /// warning when it doesn't match the configured network
+    fn prepare_address(&self, address: &str) -> Result<(String, &'static str), BlockfrostError> {
+        let (bech32_address, address_network) = normalize_address_for_blockfrost(address)
+            .map_err(BlockfrostError::AddressConversion)?;
+
+        if let Some(mismatch) = self.network_mismatch(address_network) {
+            tracing::warn!("⚠️  Network mismatch detected! ({})", mismatch);
+            tracing::warn!("   This will likely result in no data being returned. Consider using a {} address or configuring Blockfrost for the address network.", self.configured_network());
+        }
+
         tracing::info!(
             "Blockfrost: Converting address {} -> {}",
             &address[..address.len().min(16)],
             &bech32_address[..bech32_address.len().min(20)]
         );
-        
+
+        Ok((bech32_address, address_network))
+    }
+
+    /// Fetch `pages` consecutive pages starting at `page` and concatenate them in order,
+    /// stopping after a short page (the last one) or when rate limited after the first page
+    pub async fn get_address_transactions(
+        &self,
+        address: &str,
+        page: u32,
+        count: u32,
+        pages: u32,
+    ) -> Result<Vec<crate::api::user::Transaction>, BlockfrostError> {
+        self.address_transactions(address, page, count, pages, false).await
+    }
+
+    /// Like get_address_transactions from the first page, but newest transactions first
+    pub async fn get_recent_address_transactions(
+        &self,
+        address: &str,
+        count: u32,
+        pages: u32,
+    ) -> Result<Vec<crate::api::user::Transaction>, BlockfrostError> {
+        self.address_transactions(address, 1, count, pages, true).await
+    }
+
+    async fn address_transactions(
+        &self,
+        address: &str,
+        page: u32,
+        count: u32,
+        pages: u32,
+        newest_first: bool,
+    ) -> Result<Vec<crate::api::user::Transaction>, BlockfrostError> {
+        let mut transactions = Vec::new();
+        for current_page in page..page.saturating_add(pages.max(1)) {
+            if current_page > page {
+                // Same pause used between detail lookups, to stay under the rate limit
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+
+            let page_transactions = match self
+                .get_address_transactions_page(address, current_page, count, newest_first)
+                .await
+            {
+                Ok(page_transactions) => page_transactions,
+                Err(BlockfrostError::RateLimited) if current_page > page => {
+                    tracing::warn!(
+                        "Blockfrost: Rate limited at page {}, returning {} transactions from earlier pages",
+                        current_page,
+                        transactions.len()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let is_last_page = page_transactions.len() < count as usize;
+            transactions.extend(page_transactions);
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(transactions)
+    }
+
+    // Payment addresses that have been used with this stake key
+    pub async fn get_account_addresses(&self, stake_address: &str) -> Result<Vec<String>, BlockfrostError> {
+        match self
+            .get_json::<Vec<BlockfrostAccountAddress>>(&format!("accounts/{}/addresses", stake_address))
+            .await
+        {
+            Ok(addresses) => Ok(addresses.into_iter().map(|a| a.address).collect()),
+            // Blockfrost returns 404 when the stake key has never been used
+            Err(BlockfrostError::NotFound) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Newest `count` transactions across the addresses under a stake key (at most `max_addresses`),
+    /// deduplicated. Also returns how many addresses the account has and how many were actually
+    /// queried
+    pub async fn get_account_transactions(
+        &self,
+        stake_address: &str,
+        count: u32,
+        max_addresses: usize,
+    ) -> Result<(Vec<crate::api::user::Transaction>, usize, usize), BlockfrostError> {
+        let addresses = self.get_account_addresses(stake_address).await?;
+        if addresses.len() > max_addresses {
+            tracing::warn!(
+                "Blockfrost: Account has {} addresses, querying only the first {}",
+                addresses.len(),
+                max_addresses
+            );
+        }
+
+        let mut lists = Vec::new();
+        let mut queried = 0;
+        for (idx, address) in addresses.iter().take(max_addresses).enumerate() {
+            if idx > 0 {
+                // Same pause used between pages, to stay under the rate limit
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            }
+
+            // Newest page of each, so the truncated union is the account's newest overall
+            match self.get_recent_address_transactions(address, count, 1).await {
+                Ok(transactions) => lists.push(transactions),
+                Err(BlockfrostError::NotFound) => {}
+                Err(BlockfrostError::RateLimited) if idx > 0 => {
+                    tracing::warn!(
+                        "Blockfrost: Rate limited after {} of {} addresses, returning partial history",
+                        idx,
+                        addresses.len()
+                    );
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+            queried += 1;
+        }
+
+        let mut transactions = merge_transactions(lists);
+        transactions.truncate(count as usize);
+        Ok((transactions, addresses.len(), queried))
+    }
+
+    // Blockfrost lists oldest first unless asked for order=desc
+    async fn get_address_transactions_page(
+        &self,
+        address: &str,
+        page: u32,
+        count: u32,
+        newest_first: bool,
+    ) -> Result<Vec<crate::api::user::Transaction>, BlockfrostError> {
+        let (bech32_address, _network) = self.prepare_address(address)?;
+
         // Build URL with proper encoding - Blockfrost requires URL-encoded addresses
         // Use reqwest's URL builder to ensure proper encoding
         let base = reqwest::Url::parse(&self.base_url)
-            .map_err(|e| format!("Invalid base URL: {}", e))?;
+            .map_err(|e| BlockfrostError::Network(format!("Invalid base URL: {}", e)))?;
         
         // Use percent_encoding for URL encoding (standard library approach)
         // reqwest::Url::join() should handle encoding, but we'll be explicit
         let path_segment = format!("addresses/{}/transactions", bech32_address);
         let url = base.join(&path_segment)
-            .map_err(|e| format!("Failed to build URL: {}", e))?;
+            .map_err(|e| BlockfrostError::AddressConversion(format!("Failed to build URL: {}", e)))?;
         let url_str = url.as_str();
         
         tracing::info!("Blockfrost: Fetching transactions");
@@ -213,17 +577,21 @@ impl BlockfrostClient {
         tracing::info!("  URL: {}", url_str);
         tracing::info!("  Page: {}, Count: {}", page, count);
 
-        let response = self
+        let mut request = self
             .client
             .get(url_str)
             .header("project_id", &self.api_key)
             .header("accept", "application/json")
-            .query(&[("page", page.to_string()), ("count", count.to_string())])
-            .send()
+            .query(&[("page", page.to_string()), ("count", count.to_string())]);
+        if newest_first {
+            request = request.query(&[("order", "desc")]);
+        }
+        let response = self
+            .send(&path_segment, request)
             .await
             .map_err(|e| {
                 tracing::error!("Blockfrost request error: {}", e);
-                format!("Request failed: {}", e)
+                BlockfrostError::from(e)
             })?;
 
         let status = response.status();
@@ -236,40 +604,43 @@ impl BlockfrostClient {
             }
 
             tracing::error!("Blockfrost API error: {} - {}", status, text);
+            let error = BlockfrostError::from_status(status, &text);
             // Check if response is HTML (error page)
-            if text.trim_start().starts_with("<!DOCTYPE") || text.trim_start().starts_with("<html") {
-                return Err(format!(
-                    "Blockfrost returned HTML instead of JSON (status: {}). This usually means:\n\
-                    1. Invalid API key or API key not configured for this network\n\
-                    2. Network mismatch between address and Blockfrost configuration\n\
-                    3. Malformed request URL\n\
-                    Address: {}...\n\
-                    URL: {}",
-                    status,
-                    &bech32_address[..bech32_address.len().min(20)],
-                    url_str
-                ));
+            if matches!(error, BlockfrostError::Upstream { .. }) && is_html(&text) {
+                return Err(BlockfrostError::Upstream {
+                    status: status.as_u16(),
+                    message: format!(
+                        "Blockfrost returned HTML instead of JSON. This usually means:\n\
+                        1. Invalid API key or API key not configured for this network\n\
+                        2. Network mismatch between address and Blockfrost configuration\n\
+                        3. Malformed request URL\n\
+                        Address: {}...\n\
+                        URL: {}",
+                        &bech32_address[..bech32_address.len().min(20)],
+                        url_str
+                    ),
+                });
             }
-            return Err(format!("Blockfrost error: {} - {}", status, text));
+            return Err(error);
         }
 
         // Check if response is HTML (shouldn't happen with 200 status, but just in case)
-        if text.trim_start().starts_with("<!DOCTYPE") || text.trim_start().starts_with("<html") {
+        if is_html(&text) {
             tracing::error!("Blockfrost returned HTML instead of JSON even with success status");
-            return Err(format!(
+            return Err(BlockfrostError::Decode(format!(
                 "Blockfrost returned HTML instead of JSON. This suggests a configuration issue.\n\
                 Address: {}...\n\
                 URL: {}",
                 &bech32_address[..bech32_address.len().min(20)],
                 url_str
-            ));
+            )));
         }
 
         let preview = if text.len() > 1000 { format!("{}... ({} bytes)", &text[..1000], text.len()) } else { text.clone() };
         let txs: Vec<BlockfrostTransaction> = serde_json::from_str(&text)
             .map_err(|e| {
                 tracing::error!("Blockfrost JSON parse error: {}. Body: {}", e, preview);
-                format!("Failed to parse response: {}. Body: {}", e, preview)
+                BlockfrostError::Decode(format!("{}. Body: {}", e, preview))
             })?;
 
         // If no transactions, return empty list
@@ -301,6 +672,7 @@ impl BlockfrostClient {
                         slot: details.slot.unwrap_or_default(),
                         index: details.index.unwrap_or_else(|| tx.tx_index.unwrap_or_default()),
                         fees: details.fees,
+                        flow: None,
                     });
                 }
                 Err(e) => {
@@ -320,6 +692,7 @@ impl BlockfrostClient {
                         slot: 0, // Not available in list response
                         index: tx.tx_index.unwrap_or_default(),
                         fees: "0".to_string(), // Not available in list response
+                        flow: None,
                     });
                 }
             }
@@ -329,76 +702,175 @@ impl BlockfrostClient {
         Ok(transactions)
     }
 
-    async fn get_transaction_details(&self, tx_hash: &str) -> Result<BlockfrostTxDetails, String> {
-        let url = format!("{}/txs/{}", self.base_url, tx_hash);
+    async fn get_transaction_details(&self, tx_hash: &str) -> Result<BlockfrostTxDetails, BlockfrostError> {
+        if let Some(details) = self.tx_cache.get(tx_hash) {
+            tracing::debug!("Transaction cache hit: {}", tx_hash);
+            return Ok(details);
+        }
 
-        let response = self
+        let details: BlockfrostTxDetails = self.get_json(&format!("txs/{}", tx_hash)).await?;
+        self.tx_cache.insert(tx_hash.to_string(), details.clone());
+        Ok(details)
+    }
+
+    pub async fn get_transaction(&self, tx_hash: &str) -> Result<crate::api::user::Transaction, BlockfrostError> {
+        let details = self.get_transaction_details(tx_hash).await?;
+
+        Ok(crate::api::user::Transaction {
+            tx_hash: details.hash,
+            block: details.block,
+            block_height: details.block_height,
+            block_time: details.block_time,
+            slot: details.slot.unwrap_or_default(),
+            index: details.index.unwrap_or_default(),
+            fees: details.fees,
+            flow: None,
+        })
+    }
+
+    pub async fn get_transaction_utxos(&self, tx_hash: &str) -> Result<TxUtxos, BlockfrostError> {
+        if let Some(utxos) = self.utxo_cache.get(tx_hash) {
+            tracing::debug!("UTXO cache hit: {}", tx_hash);
+            return Ok(utxos);
+        }
+
+        let utxos: TxUtxos = self.get_json(&format!("txs/{}/utxos", tx_hash)).await?;
+        self.utxo_cache.insert(tx_hash.to_string(), utxos.clone());
+        Ok(utxos)
+    }
+
+    // `id` is a block hash or height
+    pub async fn get_block(&self, id: &str) -> Result<BlockInfo, BlockfrostError> {
+        self.get_json(&format!("blocks/{}", id)).await
+    }
+
+    pub async fn get_latest_block(&self) -> Result<BlockInfo, BlockfrostError> {
+        self.get_json("blocks/latest").await
+    }
+
+    pub async fn get_latest_epoch(&self) -> Result<EpochInfo, BlockfrostError> {
+        self.get_json("epochs/latest").await
+    }
+
+    pub async fn get_epoch_params(&self) -> Result<EpochParams, BlockfrostError> {
+        self.get_json("epochs/latest/parameters").await
+    }
+
+    pub async fn get_transaction_metadata(&self, tx_hash: &str) -> Result<Vec<TxMetadatum>, BlockfrostError> {
+        match self.get_json(&format!("txs/{}/metadata", tx_hash)).await {
+            Ok(metadata) => Ok(metadata),
+            // Special-case: most transactions carry no metadata
+            Err(BlockfrostError::NotFound) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Cheapest authenticated request, so a rejected key shows up as Unauthorized
+    pub async fn ping(&self) -> Result<(), BlockfrostError> {
+        self.get_json::<serde::de::IgnoredAny>("blocks/latest").await.map(|_| ())
+    }
+
+    /// Newest transactions carrying metadata under `label` (e.g. 674 for CIP-20 messages)
+    pub async fn get_txs_by_metadata_label(
+        &self,
+        label: &str,
+        page: u32,
+        count: u32,
+    ) -> Result<Vec<LabelMetadatum>, BlockfrostError> {
+        let path = format!(
+            "metadata/txs/labels/{}?page={}&count={}&order=desc",
+            label, page, count
+        );
+        match self.get_json(&path).await {
+            Ok(metadata) => Ok(metadata),
+            // No transaction has used this label yet
+            Err(BlockfrostError::NotFound) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// GET a path relative to the base URL and parse the JSON response
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, BlockfrostError> {
+        let url = reqwest::Url::parse(&self.base_url)
+            .and_then(|base| base.join(path))
+            .map_err(|e| BlockfrostError::Network(format!("Failed to build URL: {}", e)))?;
+
+        // The semaphore is never closed, so acquiring only waits
+        let _slot = self
+            .request_slots
+            .acquire()
+            .await
+            .map_err(|e| BlockfrostError::Network(e.to_string()))?;
+
+        let request = self
             .client
-            .get(&url)
+            .get(url.as_str())
             .header("project_id", &self.api_key)
-            .header("accept", "application/json")
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .header("accept", "application/json");
+        let response = self.send(path, request).await?;
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
         if !status.is_success() {
-            tracing::error!("Blockfrost API error: {} - {}", status, text);
-            return Err(format!("Blockfrost error: {} - {}", status, text));
+            // 404 is how Blockfrost says "no data", callers decide what that means
+            if status.as_u16() == 404 {
+                tracing::debug!("Blockfrost: No data (404) for {}", path);
+            } else {
+                tracing::error!("Blockfrost API error: {} - {}", status, text);
+            }
+            return Err(BlockfrostError::from_status(status, &text));
+        }
+
+        if is_html(&text) {
+            tracing::error!("Blockfrost returned HTML instead of JSON even with success status");
+            return Err(BlockfrostError::Decode(format!(
+                "Blockfrost returned HTML instead of JSON. This suggests a configuration issue.\n\
+                URL: {}",
+                url
+            )));
         }
 
         let preview = if text.len() > 1000 { format!("{}... ({} bytes)", &text[..1000], text.len()) } else { text.clone() };
         serde_json::from_str(&text)
             .map_err(|e| {
                 tracing::error!("Blockfrost JSON parse error: {}. Body: {}", e, preview);
-                format!("Failed to parse response: {}. Body: {}", e, preview)
+                BlockfrostError::Decode(format!("{}. Body: {}", e, preview))
             })
     }
 
-    pub async fn get_account_info(&self, address: &str) -> Result<crate::api::user::AccountInfo, String> {
-        // Convert hex address to bech32 if needed (Blockfrost requires bech32)
-        let bech32_address = normalize_address_for_blockfrost(address)
-            .map_err(|e| format!("Address conversion failed: {}", e))?;
-        
-        // Detect network from address and warn if mismatch
-        let address_network = detect_network_from_address(&bech32_address);
-        let configured_network = if self.base_url.contains("mainnet") {
-            "mainnet"
-        } else if self.base_url.contains("preprod") {
-            "preprod"
-        } else if self.base_url.contains("preview") {
-            "preview"
-        } else {
-            "unknown"
-        };
-        
-        if address_network == "mainnet" && configured_network != "mainnet" {
-            tracing::warn!(
-                "⚠️  Network mismatch detected! Address is mainnet (addr1...) but Blockfrost is configured for {}",
-                configured_network
-            );
-            tracing::warn!("   This will likely result in no data being returned. Consider using a {} address or configuring Blockfrost for mainnet.", configured_network);
-        } else if address_network == "testnet" && configured_network == "mainnet" {
-            tracing::warn!(
-                "⚠️  Network mismatch detected! Address is testnet (addr_test...) but Blockfrost is configured for mainnet"
-            );
+    /// Raw address info, including every unit (lovelace and native assets) it holds
+    pub async fn get_address_info(&self, address: &str) -> Result<BlockfrostAddressInfo, BlockfrostError> {
+        let (bech32_address, _network) = self.prepare_address(address)?;
+        self.get_json(&format!("addresses/{}", bech32_address)).await
+    }
+
+    // `unit` is the policy id followed by the hex asset name
+    pub async fn get_asset(&self, unit: &str) -> Result<AssetDetail, BlockfrostError> {
+        self.get_json(&format!("assets/{}", unit)).await
+    }
+
+    pub async fn get_account_info(&self, address: &str) -> Result<crate::api::user::AccountInfo, BlockfrostError> {
+        let (bech32_address, _network) = self.prepare_address(address)?;
+
+        if let Some(info) = self.account_cache.get(&bech32_address) {
+            tracing::debug!("Account info cache hit: {}", &bech32_address[..bech32_address.len().min(20)]);
+            return Ok(info);
         }
-        
-        tracing::info!(
-            "Blockfrost: Converting address {} -> {}",
-            &address[..address.len().min(16)],
-            &bech32_address[..bech32_address.len().min(20)]
-        );
-        
+
+        let info = self.fetch_account_info(address, &bech32_address).await?;
+        self.account_cache.insert(bech32_address, info.clone());
+        Ok(info)
+    }
+
+    async fn fetch_account_info(&self, address: &str, bech32_address: &str) -> Result<crate::api::user::AccountInfo, BlockfrostError> {
         // Build URL for address info with proper URL encoding
         let base = reqwest::Url::parse(&self.base_url)
-            .map_err(|e| format!("Invalid base URL: {}", e))?;
+            .map_err(|e| BlockfrostError::Network(format!("Invalid base URL: {}", e)))?;
         
         // Use reqwest::Url::join() which handles URL encoding automatically
         let path_segment = format!("addresses/{}", bech32_address);
         let url = base.join(&path_segment)
-            .map_err(|e| format!("Failed to build URL: {}", e))?;
+            .map_err(|e| BlockfrostError::AddressConversion(format!("Failed to build URL: {}", e)))?;
         let url_str = url.as_str();
 
         tracing::info!("Blockfrost: Fetching account info");
@@ -406,14 +878,12 @@ impl BlockfrostClient {
         tracing::info!("  Bech32 address: {} ({} chars)", &bech32_address[..bech32_address.len().min(32)], bech32_address.len());
         tracing::info!("  URL: {}", url_str);
 
-        let response = self
+        let request = self
             .client
             .get(url_str)
             .header("project_id", &self.api_key)
-            .header("accept", "application/json")
-            .send()
-            .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .header("accept", "application/json");
+        let response = self.send(&path_segment, request).await?;
 
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
@@ -431,50 +901,56 @@ impl BlockfrostClient {
             }
 
             tracing::error!("Blockfrost API error: {} - {}", status, text);
+            let error = BlockfrostError::from_status(status, &text);
             // Check if response is HTML (error page)
-            if text.trim_start().starts_with("<!DOCTYPE") || text.trim_start().starts_with("<html") {
-                return Err(format!(
-                    "Blockfrost returned HTML instead of JSON (status: {}). This usually means:\n\
-                    1. Invalid API key or API key not configured for this network\n\
-                    2. Network mismatch between address and Blockfrost configuration\n\
-                    3. Malformed request URL\n\
-                    Address: {}...\n\
-                    URL: {}",
-                    status,
-                    &bech32_address[..bech32_address.len().min(20)],
-                    url_str
-                ));
+            if matches!(error, BlockfrostError::Upstream { .. }) && is_html(&text) {
+                return Err(BlockfrostError::Upstream {
+                    status: status.as_u16(),
+                    message: format!(
+                        "Blockfrost returned HTML instead of JSON. This usually means:\n\
+                        1. Invalid API key or API key not configured for this network\n\
+                        2. Network mismatch between address and Blockfrost configuration\n\
+                        3. Malformed request URL\n\
+                        Address: {}...\n\
+                        URL: {}",
+                        &bech32_address[..bech32_address.len().min(20)],
+                        url_str
+                    ),
+                });
             }
             // Provide more helpful error messages
             if status == 400 {
-                return Err(format!(
-                    "Invalid request (400). Check address format.\n\
-                    Address: {}...\n\
-                    Error: {}",
-                    &bech32_address[..bech32_address.len().min(20)],
-                    text
-                ));
+                return Err(BlockfrostError::Upstream {
+                    status: 400,
+                    message: format!(
+                        "Invalid request. Check address format.\n\
+                        Address: {}...\n\
+                        Error: {}",
+                        &bech32_address[..bech32_address.len().min(20)],
+                        text
+                    ),
+                });
             }
-            return Err(format!("Blockfrost error: {} - {}", status, text));
+            return Err(error);
         }
 
         // Check if response is HTML (shouldn't happen with 200 status, but just in case)
-        if text.trim_start().starts_with("<!DOCTYPE") || text.trim_start().starts_with("<html") {
+        if is_html(&text) {
             tracing::error!("Blockfrost returned HTML instead of JSON even with success status");
-            return Err(format!(
+            return Err(BlockfrostError::Decode(format!(
                 "Blockfrost returned HTML instead of JSON. This suggests a configuration issue.\n\
                 Address: {}...\n\
                 URL: {}",
                 &bech32_address[..bech32_address.len().min(20)],
                 url_str
-            ));
+            )));
         }
 
         let preview = if text.len() > 1000 { format!("{}... ({} bytes)", &text[..1000], text.len()) } else { text.clone() };
         let info: BlockfrostAddressInfo = serde_json::from_str(&text)
             .map_err(|e| {
                 tracing::error!("Failed to parse Blockfrost response: {}. Body: {}", e, preview);
-                format!("Failed to parse response: {}. Body: {}", e, preview)
+                BlockfrostError::Decode(format!("{}. Body: {}", e, preview))
             })?;
 
         // Extract ADA balance (unit = "lovelace")
@@ -489,4 +965,333 @@ impl BlockfrostClient {
             tx_count: info.tx_count,
         })
     }
-}
\ No newline at end of file
+
+    pub async fn get_stake_account(&self, stake_address: &str) -> Result<crate::api::user::StakeInfo, BlockfrostError> {
+        tracing::info!(
+            "Blockfrost: Fetching stake account {}",
+            &stake_address[..stake_address.len().min(20)]
+        );
+        let stake_network = crate::address::from_any(stake_address)
+            .map(|parsed| crate::address::network_of(&parsed))
+            .unwrap_or("unknown");
+        if let Some(mismatch) = self.network_mismatch(stake_network) {
+            tracing::warn!("⚠️  Network mismatch detected for stake address! ({})", mismatch);
+        }
+
+        match self
+            .get_json::<BlockfrostAccount>(&format!("accounts/{}", stake_address))
+            .await
+        {
+            Ok(account) => Ok(crate::api::user::StakeInfo {
+                stake_address: account.stake_address,
+                active: account.active,
+                controlled_amount: account.controlled_amount,
+                rewards_sum: account.rewards_sum,
+                withdrawals_sum: account.withdrawals_sum,
+                pool_id: account.pool_id,
+            }),
+            // Special-case: Blockfrost returns 404 when the stake key has never been registered
+            Err(BlockfrostError::NotFound) => {
+                tracing::info!("Blockfrost: No stake history (404); returning inactive account");
+                Ok(crate::api::user::StakeInfo {
+                    stake_address: stake_address.to_string(),
+                    active: false,
+                    controlled_amount: "0".to_string(),
+                    rewards_sum: "0".to_string(),
+                    withdrawals_sum: "0".to_string(),
+                    pool_id: None,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// Union of per-address transaction lists: a tx touching several addresses appears once, newest first
+fn merge_transactions(
+    lists: Vec<Vec<crate::api::user::Transaction>>,
+) -> Vec<crate::api::user::Transaction> {
+    let mut seen = std::collections::HashSet::new();
+    let mut merged: Vec<_> = lists
+        .into_iter()
+        .flatten()
+        .filter(|tx| seen.insert(tx.tx_hash.clone()))
+        .collect();
+    merged.sort_by(|a, b| {
+        b.block_time
+            .cmp(&a.block_time)
+            .then(b.block_height.cmp(&a.block_height))
+            .then(b.index.cmp(&a.index))
+    });
+    merged
+}
+
+// Path with addresses, hashes and numbers replaced by "{}", so latency groups per endpoint
+// ("txs/{}/utxos") rather than per resource
+fn endpoint_label(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or(path);
+    path.trim_matches('/')
+        .split('/')
+        .map(|segment| {
+            if segment.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+                segment
+            } else {
+                "{}"
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{bech32, enterprise_address, MockBlockfrost};
+    use axum::extract::Path;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::Html;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use serde_json::json;
+
+    const ERROR_PAGE: &str = "<!DOCTYPE html><html><body>502 Bad Gateway</body></html>";
+
+    fn wallet() -> String {
+        bech32(&enterprise_address(0, 7))
+    }
+
+    fn client_for(network: &str) -> BlockfrostClient {
+        BlockfrostClient::with_base_url("key".to_string(), network, "http://127.0.0.1:9")
+    }
+
+    #[test]
+    fn prepare_address_converts_hex_to_bech32() {
+        let address = enterprise_address(0, 7);
+        let hex_address = hex::encode(address.to_bytes());
+
+        let prepared = client_for("preprod").prepare_address(&hex_address).unwrap();
+
+        assert_eq!(prepared, (bech32(&address), "testnet"));
+        assert_eq!(client_for("preprod").prepare_address(&bech32(&address)).unwrap(), prepared);
+        assert!(matches!(
+            client_for("preprod").prepare_address("not-an-address"),
+            Err(BlockfrostError::AddressConversion(_))
+        ));
+    }
+
+    #[test]
+    fn network_mismatch_is_detected_both_ways() {
+        let mainnet_hex = hex::encode(enterprise_address(1, 7).to_bytes());
+        let testnet_hex = hex::encode(enterprise_address(0, 7).to_bytes());
+
+        assert_eq!(
+            client_for("preview").network_warnings(&mainnet_hex),
+            ["network_mismatch: address=mainnet, server=preview"]
+        );
+        assert_eq!(
+            client_for("mainnet").network_warnings(&testnet_hex),
+            ["network_mismatch: address=testnet, server=mainnet"]
+        );
+        assert!(client_for("preprod").network_warnings(&testnet_hex).is_empty());
+        assert!(client_for("mainnet").network_warnings(&mainnet_hex).is_empty());
+    }
+
+    #[tokio::test]
+    async fn base_url_gets_api_prefix_and_trailing_slash() {
+        let mock = MockBlockfrost::start(Router::new().route("/blocks/latest", get(|| async { "{}" }))).await;
+
+        // Neither /api/v0 nor a trailing slash: both are added before joining paths
+        let client = BlockfrostClient::with_base_url("key".to_string(), "preprod", &mock.origin);
+        client.ping().await.unwrap();
+
+        assert_eq!(mock.requests(), vec!["/api/v0/blocks/latest"]);
+    }
+
+    #[tokio::test]
+    async fn address_transactions_url_uses_bech32_paging_and_project_id() {
+        let routes = Router::new().route(
+            "/addresses/:address/transactions",
+            get(|headers: HeaderMap| async move {
+                if headers.get("project_id").and_then(|v| v.to_str().ok()) != Some("test-project-id") {
+                    return (StatusCode::FORBIDDEN, "{}".to_string());
+                }
+                (StatusCode::OK, "[]".to_string())
+            }),
+        );
+        let mock = MockBlockfrost::start(routes).await;
+
+        // CIP-30 wallets hand out hex; Blockfrost only understands bech32
+        let hex_address = hex::encode(enterprise_address(0, 7).to_bytes());
+        let transactions = mock.client().get_address_transactions(&hex_address, 2, 5, 1).await.unwrap();
+
+        assert!(transactions.is_empty());
+        assert_eq!(
+            mock.requests(),
+            vec![format!("/api/v0/addresses/{}/transactions?page=2&count=5", wallet())]
+        );
+    }
+
+    #[tokio::test]
+    async fn address_transactions_404_is_an_empty_list() {
+        let routes = Router::new().route(
+            "/addresses/:address/transactions",
+            get(|| async { (StatusCode::NOT_FOUND, r#"{"status_code":404,"error":"Not Found"}"#) }),
+        );
+        let mock = MockBlockfrost::start(routes).await;
+
+        let transactions = mock.client().get_address_transactions(&wallet(), 1, 10, 1).await.unwrap();
+
+        assert!(transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn html_error_page_is_reported_as_such() {
+        let routes = Router::new().route(
+            "/addresses/:address/transactions",
+            get(|| async { (StatusCode::BAD_GATEWAY, Html(ERROR_PAGE)) }),
+        );
+        let mock = MockBlockfrost::start(routes).await;
+
+        match mock.client().get_address_transactions(&wallet(), 1, 10, 1).await {
+            Err(BlockfrostError::Upstream { status, message }) => {
+                assert_eq!(status, 502);
+                assert!(message.contains("returned HTML instead of JSON"), "{}", message);
+            }
+            other => panic!("expected an Upstream error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn html_with_success_status_is_a_decode_error() {
+        let mock = MockBlockfrost::start(
+            Router::new().route("/blocks/latest", get(|| async { Html(ERROR_PAGE) })),
+        )
+        .await;
+
+        match mock.client().get_latest_block().await {
+            Err(BlockfrostError::Decode(message)) => assert!(message.contains("HTML"), "{}", message),
+            other => panic!("expected a Decode error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_a_decode_error() {
+        let routes = Router::new()
+            .route("/addresses/:address/transactions", get(|| async { r#"[{"tx_hash": 5}]"# }))
+            .route("/blocks/latest", get(|| async { "not json" }));
+        let mock = MockBlockfrost::start(routes).await;
+        let client = mock.client();
+
+        assert!(matches!(
+            client.get_address_transactions(&wallet(), 1, 10, 1).await,
+            Err(BlockfrostError::Decode(_))
+        ));
+        assert!(matches!(client.get_latest_block().await, Err(BlockfrostError::Decode(_))));
+    }
+
+    #[tokio::test]
+    async fn transaction_list_is_filled_in_from_details() {
+        let routes = Router::new()
+            .route(
+                "/addresses/:address/transactions",
+                get(|| async {
+                    Json(json!([
+                        { "tx_hash": "aa", "tx_index": 1, "block_height": 10, "block_time": 1700000000 },
+                        { "tx_hash": "bb", "tx_index": 0, "block_height": 11, "block_time": "1700000020" }
+                    ]))
+                }),
+            )
+            .route(
+                "/txs/:hash",
+                get(|Path(hash): Path<String>| async move {
+                    if hash != "aa" {
+                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                    }
+                    Ok(Json(json!({
+                        "hash": "aa", "block": "blockhash", "block_height": 10,
+                        "block_time": 1700000000, "slot": 500, "index": 1, "fees": "170000"
+                    })))
+                }),
+            );
+        let mock = MockBlockfrost::start(routes).await;
+
+        let transactions = mock.client().get_address_transactions(&wallet(), 1, 10, 1).await.unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        let detailed = &transactions[0];
+        assert_eq!(
+            (detailed.tx_hash.as_str(), detailed.block.as_str(), detailed.slot, detailed.fees.as_str()),
+            ("aa", "blockhash", 500, "170000")
+        );
+        // Details failed for "bb", so the list entry is used with placeholders
+        let basic = &transactions[1];
+        assert_eq!(
+            (basic.tx_hash.as_str(), basic.block.as_str(), basic.block_time, basic.fees.as_str()),
+            ("bb", "block_11", 1700000020, "0")
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_details_are_fetched_once_per_hash() {
+        let routes = Router::new().route(
+            "/txs/:hash",
+            get(|Path(hash): Path<String>| async move {
+                Json(json!({
+                    "hash": hash, "block": "blockhash", "block_height": 10,
+                    "block_time": 1700000000, "slot": 500, "index": 0, "fees": "170000"
+                }))
+            }),
+        );
+        let mock = MockBlockfrost::start(routes).await;
+        let client = mock.client();
+
+        let first = client.get_transaction("aa").await.unwrap();
+        let second = client.get_transaction("aa").await.unwrap();
+        client.get_transaction("bb").await.unwrap();
+
+        assert_eq!((first.tx_hash.as_str(), second.tx_hash.as_str()), ("aa", "aa"));
+        assert_eq!(second.fees, "170000");
+        assert_eq!(mock.requests(), ["/api/v0/txs/aa", "/api/v0/txs/bb"]);
+    }
+
+    #[tokio::test]
+    async fn account_transactions_union_both_addresses_newest_first() {
+        let first = bech32(&enterprise_address(0, 1));
+        let second = bech32(&enterprise_address(0, 2));
+        let listing = |entries: &[(&str, u64)]| {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(hash, time)| json!({ "tx_hash": hash, "tx_index": 0, "block_height": time, "block_time": time }))
+                .collect();
+            json!(entries)
+        };
+        // "bb" moved funds between the two addresses, so both list it
+        let listings = [
+            (first, listing(&[("aa", 100), ("bb", 200)])),
+            (second, listing(&[("bb", 200), ("cc", 300)])),
+        ];
+        let addresses: Vec<_> = listings.iter().map(|(address, _)| json!({ "address": address })).collect();
+        let routes = Router::new()
+            .route("/accounts/:stake/addresses", get(move || async move { Json(json!(addresses)) }))
+            .route(
+                "/addresses/:address/transactions",
+                get(move |Path(address): Path<String>| async move {
+                    let (_, listing) = listings.iter().find(|(listed, _)| *listed == address).unwrap();
+                    Json(listing.clone())
+                }),
+            );
+        let mock = MockBlockfrost::start(routes).await;
+
+        let (transactions, total_addresses, queried) =
+            mock.client().get_account_transactions("stake_test1account", 10, 5).await.unwrap();
+
+        let merged: Vec<_> = transactions.iter().map(|tx| (tx.tx_hash.as_str(), tx.block_time)).collect();
+        assert_eq!(merged, [("cc", 300), ("bb", 200), ("aa", 100)]);
+        assert_eq!((total_addresses, queried), (2, 2));
+
+        // Each address can fill a page, but only the newest `count` overall come back
+        let (transactions, _, _) = mock.client().get_account_transactions("stake_test1account", 2, 5).await.unwrap();
+        let newest: Vec<_> = transactions.iter().map(|tx| tx.tx_hash.as_str()).collect();
+        assert_eq!(newest, ["cc", "bb"]);
+    }
+}
@@ -1,5 +1,7 @@
 // Configuration constants for the Cardano Blockchain Viewer
 
+use std::path::PathBuf;
+
 /// Number of events to keep in the circular buffer
 pub const BUFFER_SIZE: usize = 100;
 
@@ -12,11 +14,88 @@ pub const MAX_TX_COUNT: usize = 500;
 /// Server listening address (for both REST API and WebSocket)
 pub const SERVER_ADDR: &str = "0.0.0.0:8080";
 
+/// Read a boolean flag from the environment (`1`/`true`/`yes`/`on`), defaulting to false
+pub fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
+
+/// Read and parse a value from the environment, falling back to `default` when unset or invalid
+pub fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Where the Oura events are read from
+#[derive(Debug, Clone)]
+pub enum EventSource {
+    /// Spawn `oura dump` against the configured relay (default)
+    OuraSubprocess,
+    /// Read newline-delimited JSON events from stdin
+    Stdin,
+    /// Read newline-delimited JSON events from a file (for replaying captures)
+    File(PathBuf),
+}
+
+impl EventSource {
+    /// Select the source from `OURA_SOURCE` (`oura`, `stdin` or `file` with `OURA_FILE`)
+    pub fn from_env() -> Result<Self, String> {
+        let source = std::env::var("OURA_SOURCE").unwrap_or_else(|_| "oura".to_string());
+
+        match source.to_lowercase().as_str() {
+            "oura" | "subprocess" => Ok(Self::OuraSubprocess),
+            "stdin" => Ok(Self::Stdin),
+            "file" => std::env::var("OURA_FILE")
+                .map(|path| Self::File(PathBuf::from(path)))
+                .map_err(|_| "OURA_SOURCE=file requires OURA_FILE to be set".to_string()),
+            other => Err(format!(
+                "Unknown OURA_SOURCE '{}' (expected oura, stdin or file)",
+                other
+            )),
+        }
+    }
+}
+
+/// How `oura dump` connects to the node
+#[derive(Debug, Clone)]
+pub enum Bearer {
+    /// Node-to-node over TCP to the configured relay (default)
+    Tcp,
+    /// Node-to-client over a local node's Unix socket.
+    /// The network magic is still sent in the handshake and must match the node's network
+    Unix(PathBuf),
+}
+
+impl Bearer {
+    /// Select the bearer from `OURA_BEARER` (`tcp`, or `unix` with `OURA_SOCKET_PATH`)
+    pub fn from_env() -> Result<Self, String> {
+        let bearer = std::env::var("OURA_BEARER").unwrap_or_else(|_| "tcp".to_string());
+
+        match bearer.to_lowercase().as_str() {
+            "tcp" => Ok(Self::Tcp),
+            "unix" => std::env::var("OURA_SOCKET_PATH")
+                .ok()
+                .filter(|path| !path.trim().is_empty())
+                .map(|path| Self::Unix(PathBuf::from(path)))
+                .ok_or_else(|| "OURA_BEARER=unix requires OURA_SOCKET_PATH to be set".to_string()),
+            other => Err(format!(
+                "Unknown OURA_BEARER '{}' (expected tcp or unix)",
+                other
+            )),
+        }
+    }
+}
+
 /// Cardano network configuration
 pub struct CardanoConfig {
     pub relay: &'static str,
     pub magic: &'static str,
     pub network_name: &'static str,
+    pub source: EventSource,
+    pub bearer: Bearer,
 }
 
 
@@ -27,6 +106,8 @@ impl CardanoConfig {
             relay: "preprod-node.world.dev.cardano.org:30000",
             magic: "pre-prod",
             network_name: "PreProd Testnet",
+            source: EventSource::OuraSubprocess,
+            bearer: Bearer::Tcp,
         }
     }
 
@@ -37,6 +118,8 @@ impl CardanoConfig {
             relay: "preview-node.world.dev.cardano.org:3001",
             magic: "preview",
             network_name: "Preview Testnet",
+            source: EventSource::OuraSubprocess,
+            bearer: Bearer::Tcp,
         }
     }
 
@@ -46,8 +129,29 @@ impl CardanoConfig {
             relay: "relays-new.cardano-mainnet.iohk.io:3001",
             magic: "mainnet",
             network_name: "Mainnet",
+            source: EventSource::OuraSubprocess,
+            bearer: Bearer::Tcp,
         }
     }
+
+    /// Every supported network, as listed by /api/networks
+    pub fn presets() -> [Self; 3] {
+        [Self::preprod(), Self::preview(), Self::mainnet()]
+    }
+
+    /// Network addresses are expected to belong to: "mainnet", or "testnet" for every test network
+    pub fn address_network(&self) -> &'static str {
+        address_network_for(self.magic)
+    }
+}
+
+/// `CardanoConfig::address_network` for a bare network magic
+pub fn address_network_for(magic: &str) -> &'static str {
+    if magic == "mainnet" {
+        "mainnet"
+    } else {
+        "testnet"
+    }
 }
 
 impl Default for CardanoConfig {
@@ -1,29 +1,196 @@
-use axum::{extract::State, response::Json, routing::get, Router};
+use axum::{extract::State, http::StatusCode, response::Json, routing::get};
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
 
+mod address;
 mod api;
 mod auth;
 mod blockfrost;
 mod config;
 mod models;
 mod services;
+#[cfg(test)]
+mod test_support;
+mod tls;
 mod websocket;
 
-use config::{CardanoConfig, BUFFER_SIZE, SERVER_ADDR};
-use models::AppState;
-use services::{EventProcessor, OuraReader};
+use config::{env_flag, env_parse, Bearer, CardanoConfig, EventSource, BUFFER_SIZE, SERVER_ADDR};
+use models::{AppState, ClearStrategy, EvictionPolicy, Metrics};
+use services::{CursorStore, EventFilter, EventProcessor, OuraError, OuraReader};
 use websocket::WebSocketState;
 
 // Health check endpoint for deployment platforms
-async fn health_check() -> Json<Value> {
-    Json(json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+#[derive(Clone)]
+struct HealthState {
+    app_state: Arc<Mutex<AppState>>,
+    started_at: Instant,
+    network: &'static str,
+    started_streaming: Arc<AtomicBool>,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    timestamp: String,
+    network: &'static str,
+    uptime_secs: u64,
+    total_events: usize,
+    // True once Oura has delivered at least one event
+    oura_streaming: bool,
+}
+
+// Upper bound on the Blockfrost round trip made by /health/deep
+const DEEP_HEALTH_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone)]
+struct DeepHealthState {
+    health: HealthState,
+    blockfrost: Option<Arc<blockfrost::BlockfrostClient>>,
+}
+
+#[derive(Serialize)]
+struct DependencyHealth {
+    // "ok", "unauthorized", "unreachable" or "not_configured"
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeepHealthResponse {
+    #[serde(flatten)]
+    health: HealthResponse,
+    blockfrost: DependencyHealth,
+}
+
+// /health plus a live Blockfrost request; answers 503 "degraded" when a configured key is
+// rejected or Blockfrost can't be reached. Running without a key is not degraded
+async fn deep_health_check(
+    State(deep): State<DeepHealthState>,
+) -> (StatusCode, Json<DeepHealthResponse>) {
+    let Json(mut health) = health_check(State(deep.health)).await;
+
+    let blockfrost = match &deep.blockfrost {
+        None => DependencyHealth {
+            status: "not_configured",
+            latency_ms: None,
+            error: None,
+        },
+        Some(client) => {
+            let started = Instant::now();
+            let result = tokio::time::timeout(DEEP_HEALTH_TIMEOUT, client.ping()).await;
+            let latency_ms = Some(started.elapsed().as_millis() as u64);
+            let (status, error) = match result {
+                Ok(Ok(())) => ("ok", None),
+                Ok(Err(e @ blockfrost::BlockfrostError::Unauthorized(_))) => {
+                    ("unauthorized", Some(e.to_string()))
+                }
+                Ok(Err(e)) => ("unreachable", Some(e.to_string())),
+                Err(_) => (
+                    "unreachable",
+                    Some(format!("No response within {}s", DEEP_HEALTH_TIMEOUT.as_secs())),
+                ),
+            };
+            DependencyHealth { status, latency_ms, error }
+        }
+    };
+
+    let degraded = !matches!(blockfrost.status, "ok" | "not_configured");
+    if degraded {
+        health.status = "degraded";
+    }
+    let status = if degraded { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+    (status, Json(DeepHealthResponse { health, blockfrost }))
+}
+
+async fn health_check(State(health): State<HealthState>) -> Json<HealthResponse> {
+    let total_events = health.app_state.lock().await.total_events;
+
+    Json(HealthResponse {
+        status: "healthy",
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        network: health.network,
+        uptime_secs: health.started_at.elapsed().as_secs(),
+        total_events,
+        oura_streaming: health.started_streaming.load(Ordering::Relaxed),
+    })
+}
+
+// Well-known fallback secret, only acceptable outside production
+const DEFAULT_JWT_SECRET: &str = "change-this-secret-in-production-use-strong-key";
+const MIN_PRODUCTION_SECRET_LEN: usize = 32;
+
+// Read JWT_SECRET, refusing to start in production (APP_ENV/ENVIRONMENT=production)
+// with a missing, default or short secret
+fn load_jwt_secret() -> Result<String, String> {
+    let is_production = std::env::var("APP_ENV")
+        .or_else(|_| std::env::var("ENVIRONMENT"))
+        .is_ok_and(|env| env.trim().eq_ignore_ascii_case("production"));
+
+    let secret = match std::env::var("JWT_SECRET") {
+        Ok(secret) => secret,
+        Err(_) if is_production => {
+            return Err("❌ JWT_SECRET must be set when APP_ENV=production".to_string());
+        }
+        Err(_) => {
+            tracing::warn!(" ⚠️  JWT_SECRET not set, using default (CHANGE IN PRODUCTION!)");
+            DEFAULT_JWT_SECRET.to_string()
+        }
+    };
+
+    if is_production {
+        if secret == DEFAULT_JWT_SECRET {
+            return Err("❌ JWT_SECRET is the insecure default; set a unique secret for production".to_string());
+        }
+        if secret.len() < MIN_PRODUCTION_SECRET_LEN {
+            return Err(format!(
+                "❌ JWT_SECRET must be at least {} bytes in production (got {})",
+                MIN_PRODUCTION_SECRET_LEN,
+                secret.len()
+            ));
+        }
+    }
+
+    Ok(secret)
+}
+
+// Readiness probe: 503 until the Oura pipeline has processed at least one event
+async fn readiness_check(
+    State(started_streaming): State<Arc<AtomicBool>>,
+) -> (StatusCode, Json<Value>) {
+    if started_streaming.load(Ordering::Relaxed) {
+        (StatusCode::OK, Json(json!({ "status": "ready" })))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "starting", "reason": "No Oura events processed yet" })),
+        )
+    }
+}
+
+// Process Oura events until the reader drops its sender (end of a replayed file, or the
+// reader gave up). The mpsc receiver never lags; WebSocket clients that fall behind on the
+// broadcast side are skipped ahead by their own connection task, without stalling this loop
+async fn process_events(
+    mut oura_rx: mpsc::Receiver<models::OuraEvent>,
+    event_processor: EventProcessor,
+    ws_tx: broadcast::Sender<websocket::protocol::Frame>,
+) {
+    while let Some(oura_event) = oura_rx.recv().await {
+        if let Err(e) = event_processor.process_event(oura_event, &ws_tx).await {
+            error!("Event processing error: {}", e);
+        }
+    }
+    tracing::warn!("⚠️  Oura reader stopped, no further events will be processed");
 }
 
 #[tokio::main]
@@ -35,101 +202,387 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter("info")
-        .with_target(false)
-        .init();
+    // RUST_LOG (full filter syntax) or LOG_LEVEL picks the filter; LOG_FORMAT=json emits one JSON object per line
+    let log_filter = std::env::var("RUST_LOG")
+        .or_else(|_| std::env::var("LOG_LEVEL"))
+        .unwrap_or_else(|_| "info".to_string());
+    let env_filter = EnvFilter::try_new(&log_filter).unwrap_or_else(|e| {
+        eprintln!("Invalid log filter '{}' ({}), falling back to info", log_filter, e);
+        EnvFilter::new("info")
+    });
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_target(false);
+    if std::env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json")) {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
 
     // Get Cardano network configuration
-    let cardano_config = CardanoConfig::default(); // Uses PreProd by default
+    // Uses PreProd by default; OURA_SOURCE=stdin|file replays captured events without a live relay,
+    // OURA_BEARER=unix reads from a local node's socket instead of the public relay
+    let cardano_config = CardanoConfig {
+        source: EventSource::from_env()?,
+        bearer: Bearer::from_env()?,
+        ..CardanoConfig::default()
+    };
 
     info!("Starting Cardano Blockchain Viewer Backend");
     info!("Network: {}", cardano_config.network_name);
+    let started_at = Instant::now();
+    let network_name = cardano_config.network_name;
+    let network_magic = cardano_config.magic;
+    info!("Event source: {:?}", cardano_config.source);
 
     // Create shared application state
-    let state = Arc::new(Mutex::new(AppState::new(BUFFER_SIZE)));
+    let eviction = EvictionPolicy::from_env()?;
+    info!("Buffer eviction policy: {:?}", eviction);
+    let clear_strategy = ClearStrategy::from_env()?;
+    info!("Buffer clear strategy: {:?}", clear_strategy);
+    let state = Arc::new(Mutex::new(
+        AppState::new(BUFFER_SIZE)
+            .with_eviction(eviction)
+            .with_clear_strategy(clear_strategy),
+    ));
 
-    // Create broadcast channels with larger capacity to handle bursts
-    let (oura_tx, _) = broadcast::channel(1000); // Channel for Oura events
-    let (ws_tx, _) = broadcast::channel(1000); // Channel for WebSocket broadcasts
+    // The processor is Oura's only consumer, so a bounded mpsc channel (OURA_CHANNEL_CAPACITY,
+    // default 1000) makes the reader wait during bursts instead of dropping events.
+    // The WebSocket fan-out stays a broadcast channel sized by BROADCAST_CAPACITY (default 1000)
+    let oura_channel_capacity: usize = env_parse("OURA_CHANNEL_CAPACITY", 1000).max(1);
+    let broadcast_capacity: usize = env_parse("BROADCAST_CAPACITY", 1000).max(1);
+    let (oura_tx, oura_rx) = mpsc::channel(oura_channel_capacity); // Channel for Oura events
+    let (ws_tx, _) = broadcast::channel(broadcast_capacity); // Channel for WebSocket broadcasts
+    let metrics = Arc::new(Metrics::new(broadcast_capacity, oura_channel_capacity));
+    info!("📡 Oura channel capacity: {}, broadcast channel capacity: {}", oura_channel_capacity, broadcast_capacity);
 
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| {
-        tracing::warn!(" ⚠️  JWT_SECRET not set, using default (CHANGE IN PRODUCTION!)");
-        "change-this-secret-in-production-use-strong-key".to_string()
-    });
+    let jwt_secret = load_jwt_secret()?;
 
-    let blockfrost_key = std::env::var("BLOCKFROST_API_KEY").unwrap_or_else(|_| {
+    let blockfrost_key = std::env::var("BLOCKFROST_API_KEY").ok().or_else(|| {
         // Attempt to load from backend-specific .env if not yet loaded
         let _ = dotenvy::from_filename("cardano_blockchain_viewer/.env");
-        std::env::var("BLOCKFROST_API_KEY")
-            .expect("❌ BLOCKFROST_API_KEY environment variable must be set")
+        std::env::var("BLOCKFROST_API_KEY").ok()
     });
 
-    let jwt_manager = Arc::new(auth::JwtManager::new(jwt_secret));
-    let blockfrost_key_len = blockfrost_key.len();
-    let blockfrost = Arc::new(blockfrost::BlockfrostClient::new(blockfrost_key, "preprod"));
+    let jwt_issuer = std::env::var("JWT_ISSUER").unwrap_or_else(|_| "cardano-viewer".to_string());
+    let jwt_audience =
+        std::env::var("JWT_AUDIENCE").unwrap_or_else(|_| "cardano-viewer".to_string());
 
+    let jwt_manager = Arc::new(auth::JwtManager::new(jwt_secret, jwt_issuer, jwt_audience));
     info!("🔐 JWT Manager initialized");
-    info!("🌐 Blockfrost client initialized (preprod network)");
-    info!(
-        "🔑 BLOCKFROST_API_KEY loaded ({} chars)",
-        blockfrost_key_len
-    );
+
+    // Without a key the live feed and WebSocket still run; Blockfrost-backed endpoints answer 503
+    let blockfrost = match blockfrost_key {
+        Some(key) => {
+            info!("🔑 BLOCKFROST_API_KEY loaded ({} chars)", key.len());
+            let client = blockfrost::BlockfrostClient::new(key, "preprod").with_metrics(Arc::clone(&metrics));
+            info!("🌐 Blockfrost client initialized (preprod network)");
+            Some(Arc::new(client))
+        }
+        None => {
+            tracing::warn!("⚠️  BLOCKFROST_API_KEY not set, /api/user/*, /api/tx/* and /api/block/* are disabled");
+            None
+        }
+    };
 
     // Initialize services
-    let oura_reader = OuraReader::new(cardano_config);
-    let event_processor = EventProcessor::new(Arc::clone(&state));
+    // OURA_RESUME=true continues from the last processed block instead of oura's default start
+    let cursor = CursorStore::from_env();
+    let resume_point = cursor.as_ref().and_then(|cursor| cursor.load());
+    let oura_reader = OuraReader::new(cardano_config, Arc::clone(&metrics)).with_resume_point(resume_point);
+    let event_processor = EventProcessor::new(Arc::clone(&state))
+        .with_cursor(cursor)
+        .with_filter(EventFilter::from_env()?);
+    let started_streaming = event_processor.started_streaming();
+    let oura_status = oura_reader.status();
 
     // Spawn task to read from Oura
-    let oura_tx_clone = oura_tx.clone();
     tokio::spawn(async move {
-        if let Err(e) = oura_reader.start(oura_tx_clone).await {
-            error!("Oura reader error: {}", e);
+        match oura_reader.start(oura_tx).await {
+            Ok(()) => {}
+            // The reader already logged how to fix it; keep serving the REST API without a live feed
+            Err(OuraError::BinaryNotFound(_)) => {
+                error!("Live feed disabled: no Oura events will be streamed");
+            }
+            Err(e) => error!("Oura reader error: {}", e),
         }
     });
 
+    // Keep dashboards populated during quiet periods (STATS_KEEPALIVE_SECS=0 disables)
+    let keepalive_secs: u64 = env_parse("STATS_KEEPALIVE_SECS", 15);
+    if keepalive_secs > 0 {
+        event_processor.spawn_stats_keepalive(ws_tx.clone(), Duration::from_secs(keepalive_secs));
+    }
+
     // Spawn task to process events
-    let mut oura_rx = oura_tx.subscribe();
-    let ws_tx_clone = ws_tx.clone();
-    tokio::spawn(async move {
-        while let Ok(oura_event) = oura_rx.recv().await {
-            if let Err(e) = event_processor
-                .process_event(oura_event, &ws_tx_clone)
-                .await
-            {
-                error!("Event processing error: {}", e);
-            }
-        }
-    });
+    tokio::spawn(process_events(oura_rx, event_processor, ws_tx.clone()));
 
     // Create WebSocket state for Axum
     let ws_state = WebSocketState {
         app_state: Arc::clone(&state),
         ws_tx: ws_tx.clone(),
+        jwt_manager: Arc::clone(&jwt_manager),
+        metrics: Arc::clone(&metrics),
+        require_auth: env_flag("WS_REQUIRE_AUTH"),
+        active_connections: Arc::new(AtomicUsize::new(0)),
+        max_connections: env_parse("WS_MAX_CONNECTIONS", 1000),
+        // 0 (default) disables; stats keepalives count as activity, so set it above STATS_KEEPALIVE_SECS
+        idle_timeout: match env_parse("WS_IDLE_TIMEOUT_SECS", 0) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        },
     };
+    if ws_state.require_auth {
+        info!("🔒 WebSocket requires a JWT (WS_REQUIRE_AUTH is set)");
+    }
 
-    let api_router =
-        api::create_router(jwt_manager, blockfrost, ws_state).route("/health", get(health_check));
+    // Concurrent requests to the Blockfrost-backed routes; each can fan out to many Blockfrost
+    // calls, so extra ones get 503
+    let max_inflight: usize = env_parse("MAX_INFLIGHT", 64).max(1);
+    // Only behind a reverse proxy that sets X-Forwarded-For/X-Real-IP; otherwise clients could spoof them
+    let trust_proxy = env_flag("TRUST_PROXY");
+    if trust_proxy {
+        info!("🔁 Client IPs taken from X-Forwarded-For/X-Real-IP (TRUST_PROXY is set)");
+    }
+    let health_state = HealthState {
+        app_state: Arc::clone(&state),
+        started_at,
+        network: network_name,
+        started_streaming: Arc::clone(&started_streaming),
+    };
+    let deep_health_state = DeepHealthState {
+        health: health_state.clone(),
+        blockfrost: blockfrost.clone(),
+    };
+    let api_router = api::create_router(
+        jwt_manager,
+        blockfrost,
+        ws_state,
+        oura_status,
+        network_magic,
+        max_inflight,
+        trust_proxy,
+    )
+        .route("/health", get(health_check).with_state(health_state))
+        .route("/health/deep", get(deep_health_check).with_state(deep_health_state))
+        .route("/ready", get(readiness_check).with_state(started_streaming));
     let server_addr: SocketAddr = SERVER_ADDR.parse()?;
+    // HTTPS/wss when TLS_CERT_PATH and TLS_KEY_PATH are set
+    let tls_config = tls::config_from_env()?;
+    let (scheme, ws_scheme) = if tls_config.is_some() { ("https", "wss") } else { ("http", "ws") };
 
-    info!("🌍 Server starting on: http://{}", server_addr);
+    info!("🌍 Server starting on: {}://{}", scheme, server_addr);
     info!("   REST API Endpoints:");
-    info!("   - POST http://{}/api/auth/challenge", server_addr);
-    info!("   - POST http://{}/api/auth/verify", server_addr);
+    info!("   - GET  {}://{}/health", scheme, server_addr);
+    info!("   - GET  {}://{}/health/deep", scheme, server_addr);
+    info!("   - GET  {}://{}/ready", scheme, server_addr);
+    info!("   - POST {}://{}/api/auth/challenge", scheme, server_addr);
+    info!("   - POST {}://{}/api/auth/verify", scheme, server_addr);
+    info!("   - GET  {}://{}/api/auth/me (protected)", scheme, server_addr);
+    info!("   - GET  {}://{}/api/stats", scheme, server_addr);
+    info!("   - GET  {}://{}/api/networks", scheme, server_addr);
+    info!("   - GET  {}://{}/api/tx/:hash", scheme, server_addr);
+    info!("   - GET  {}://{}/api/tx/:hash/utxos", scheme, server_addr);
+    info!("   - GET  {}://{}/api/block/:id", scheme, server_addr);
+    info!(
+        "   - GET  {}://{}/api/metadata/:label (protected)",
+        scheme, server_addr
+    );
+    info!(
+        "   - GET  {}://{}/api/user/transactions (protected)",
+        scheme, server_addr
+    );
+    info!(
+        "   - GET  {}://{}/api/user/summary (protected)",
+        scheme, server_addr
+    );
+    info!(
+        "   - GET  {}://{}/api/user/staking (protected)",
+        scheme, server_addr
+    );
     info!(
-        "   - GET  http://{}/api/user/transactions (protected)",
-        server_addr
+        "   - GET  {}://{}/api/user/assets (protected)",
+        scheme, server_addr
     );
     info!(
-        "   - GET  http://{}/api/user/summary (protected)",
-        server_addr
+        "   - GET  {}://{}/api/user/balance-history (protected)",
+        scheme, server_addr
     );
     info!("   WebSocket Endpoint:");
-    info!("   - ws://{}/ws", server_addr);
-    info!("   Connect with: wscat -c ws://{}/ws", server_addr);
+    info!("   - {}://{}/ws", ws_scheme, server_addr);
+    info!("   Connect with: wscat -c {}://{}/ws", ws_scheme, server_addr);
 
     let listener = tokio::net::TcpListener::bind(server_addr).await?;
-    axum::serve(listener, api_router).await?;
+    match tls_config {
+        Some(config) => tls::serve(listener, api_router, config).await?,
+        None => {
+            axum::serve(
+                listener,
+                api_router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?
+        }
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health_state() -> HealthState {
+        HealthState {
+            app_state: Arc::new(Mutex::new(AppState::new(10))),
+            started_at: Instant::now() - Duration::from_secs(90),
+            network: "PreProd Testnet",
+            started_streaming: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    #[tokio::test]
+    async fn health_reports_uptime_network_and_streaming() {
+        let Json(health) = health_check(State(health_state())).await;
+        let health = serde_json::to_value(health).unwrap();
+
+        assert_eq!(health["status"], "healthy");
+        assert_eq!(health["network"], "PreProd Testnet");
+        assert!(health["uptime_secs"].as_u64().unwrap() >= 90, "{}", health);
+        assert_eq!(health["total_events"], 0);
+        assert_eq!(health["oura_streaming"], true);
+        assert!(chrono::DateTime::parse_from_rfc3339(health["timestamp"].as_str().unwrap()).is_ok());
+    }
+
+    async fn deep_health(blockfrost: Option<Arc<blockfrost::BlockfrostClient>>) -> (StatusCode, Value) {
+        let deep = DeepHealthState { health: health_state(), blockfrost };
+        let (status, Json(response)) = deep_health_check(State(deep)).await;
+        (status, serde_json::to_value(response).unwrap())
+    }
+
+    #[tokio::test]
+    async fn rejected_key_makes_deep_health_degraded() {
+        use axum::Router;
+        use test_support::MockBlockfrost;
+
+        let forbidden = Router::new().route(
+            "/blocks/latest",
+            get(|| async {
+                (StatusCode::FORBIDDEN, r#"{"status_code":403,"error":"Forbidden","message":"Invalid project token."}"#)
+            }),
+        );
+        let blockfrost = MockBlockfrost::start(forbidden).await;
+
+        let (status, health) = deep_health(Some(Arc::new(blockfrost.client()))).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", health);
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["blockfrost"]["status"], "unauthorized");
+        assert!(health["blockfrost"]["error"].is_string(), "{}", health);
+        assert_eq!(blockfrost.requests(), ["/api/v0/blocks/latest"]);
+
+        // Running without a key is not degraded, and the shallow fields are still there
+        let (status, health) = deep_health(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(health["status"], "healthy");
+        assert_eq!(health["blockfrost"]["status"], "not_configured");
+        assert_eq!(health["network"], "PreProd Testnet");
+    }
+
+    fn transaction_event(hash: &str) -> models::OuraEvent {
+        serde_json::from_value(json!({
+            "event": "apply",
+            "point": { "hash": "aa", "slot": 10 },
+            "record": {
+                "context": { "block_hash": "aa", "block_number": 5, "slot": 10, "timestamp": 1700000000, "tx_hash": hash },
+                "transaction": {
+                    "hash": hash, "fee": 170000, "size": 300, "input_count": 1, "output_count": 2,
+                    "total_output": 5000000, "mint_count": 0, "collateral_input_count": 0,
+                    "has_collateral_output": false
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    // Hashes of the transaction frames a receiver has been sent, reporting a lag as "lagged"
+    fn received(ws_rx: &mut broadcast::Receiver<websocket::protocol::Frame>) -> Vec<String> {
+        let mut received = Vec::new();
+        loop {
+            match ws_rx.try_recv() {
+                Ok(frame) => {
+                    let frame: Value = serde_json::from_str(frame.json()).unwrap();
+                    if frame["type"] == "Transaction" {
+                        received.push(frame["hash"].as_str().unwrap().to_string());
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => received.push("lagged".to_string()),
+                Err(_) => return received,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn lagged_receiver_keeps_getting_the_events_processed_after_it() {
+        let state = Arc::new(Mutex::new(AppState::new(100)));
+        let (ws_tx, mut lagging) = broadcast::channel(4);
+        let (oura_tx, oura_rx) = mpsc::channel(64);
+        let processor = tokio::spawn(process_events(oura_rx, EventProcessor::new(Arc::clone(&state)), ws_tx));
+
+        // A burst the receiver doesn't keep up with: only the newest frames are still there
+        for n in 0..20 {
+            oura_tx.send(transaction_event(&format!("burst-{}", n))).await.unwrap();
+        }
+        while state.lock().await.transactions_count < 20 {
+            tokio::task::yield_now().await;
+        }
+        let after_burst = received(&mut lagging);
+        assert_eq!(after_burst.first().map(String::as_str), Some("lagged"), "{:?}", after_burst);
+        assert_eq!(after_burst.last().map(String::as_str), Some("burst-19"), "{:?}", after_burst);
+
+        // The pipeline is still running and the receiver gets what follows in full
+        oura_tx.send(transaction_event("after-1")).await.unwrap();
+        oura_tx.send(transaction_event("after-2")).await.unwrap();
+        drop(oura_tx);
+        processor.await.unwrap();
+
+        assert_eq!(received(&mut lagging), ["after-1", "after-2"]);
+        assert_eq!(state.lock().await.transactions_count, 22);
+    }
+
+    #[tokio::test]
+    async fn health_answers_over_tls_with_a_self_signed_certificate() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+
+        // Self-signed P-256 leaf for localhost/127.0.0.1 valid until 2126, made with `openssl req -x509`
+        const CERT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/localhost.crt");
+        const KEY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/localhost.key");
+        let config = tls::load_config(CERT, KEY).unwrap();
+        let router = axum::Router::new().route("/health", get(health_check)).with_state(health_state());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(tls::serve(listener, router, config));
+
+        // The client only trusts the fixture certificate itself
+        let mut roots = RootCertStore::empty();
+        let pem = std::fs::read(CERT).unwrap();
+        for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let client = ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut stream = connector.connect(ServerName::try_from("localhost").unwrap(), tcp).await.unwrap();
+
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let health: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(health["status"], "healthy");
+    }
+}
@@ -26,6 +26,23 @@ pub struct Record {
     pub tx_output: Option<TxOutputRecord>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub roll_back: Option<RollBackRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mint: Option<MintRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub native_script: Option<NativeScriptRecord>,
+    // Oura emits each certificate kind under its own key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_registration: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_deregistration: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stake_delegation: Option<StakeDelegationRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_registration: Option<PoolRegistrationRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_retirement: Option<PoolRetirementRecord>,
     pub context: Context,
     pub fingerprint: Option<String>,
 }
@@ -38,6 +55,47 @@ pub struct RollBackRecord {
 }
 
 
+// One asset minted (positive quantity) or burned (negative) by a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintRecord {
+    pub policy: String,
+    pub asset: String,
+    pub quantity: i64,
+}
+
+// Transaction metadata under one label; the content (map_json, text_scalar, ...) isn't streamed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataRecord {
+    pub label: String,
+    #[serde(flatten)]
+    pub content: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NativeScriptRecord {
+    pub policy_id: String,
+    pub script: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakeDelegationRecord {
+    pub credential: serde_json::Value,
+    pub pool_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolRegistrationRecord {
+    pub operator: String,
+    #[serde(flatten)]
+    pub details: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolRetirementRecord {
+    pub pool: String,
+    pub epoch: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockRecord {
     pub hash: String,
@@ -109,7 +167,20 @@ pub struct Context {
 // Simplified Blockchain Events (Sent to frontend via WebSocket)
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize)]
+// Anything from here on is milliseconds: as seconds it would be past the year 5000
+const MILLIS_THRESHOLD: u64 = 100_000_000_000;
+
+/// Unix seconds from a timestamp in either seconds or milliseconds, told apart by magnitude.
+/// Every `timestamp`/`block_time` sent to clients goes through this
+pub fn normalize_unix_time(value: u64) -> u64 {
+    if value >= MILLIS_THRESHOLD {
+        value / 1000
+    } else {
+        value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum BlockchainEvent {
     Block{
@@ -117,6 +188,8 @@ pub enum BlockchainEvent {
         hash: String,
         number: u64,
         epoch: u64,
+        // Byron, Shelley, ..., Babbage, Conway
+        era: String,
         tx_count: u32,
         timestamp: u64,
         // Rest of the fields will be send by keeping inside the details so it will appear as a struct being passed
@@ -125,35 +198,132 @@ pub enum BlockchainEvent {
     },
     Transaction {
         hash: String,
+        slot: u64,
         fee: u64,
         inputs: u32,
         outputs: u32,
         total_output: u64,
+        // Number of assets minted or burned; is_mint lets dashboards filter token/NFT mints
+        mint_count: u32,
+        is_mint: bool,
         timestamp: u64,
         #[serde(flatten)]
         details: serde_json::Value,
     },
     TxInput {
         tx_hash: String,
+        // Slot of the block containing the spending transaction
+        slot: u64,
         input_tx_id: String,
         input_index: u32,
         timestamp: u64,
+        // Position of the input within its transaction, from the Oura context
+        #[serde(skip_serializing_if = "Option::is_none")]
+        input_idx: Option<u32>,
     },
     TxOutput {
         tx_hash: String,
+        slot: u64,
         address: String,
         amount: u64,
         timestamp: u64,
+        // Position of the transaction within its block and of the output within the transaction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tx_idx: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        output_idx: Option<u32>,
     },
     RollBack {
         block_hash: String,
         block_slot: u64,
         timestamp: u64,
     },
+    Mint {
+        tx_hash: String,
+        slot: u64,
+        policy: String,
+        asset: String,
+        // Negative for burns
+        quantity: i64,
+        timestamp: u64,
+    },
+    Metadata {
+        tx_hash: String,
+        slot: u64,
+        label: String,
+        timestamp: u64,
+    },
+    NativeScript {
+        tx_hash: String,
+        slot: u64,
+        policy_id: String,
+        timestamp: u64,
+    },
+    Certificate {
+        tx_hash: String,
+        slot: u64,
+        // Oura record name, e.g. "stake_delegation" or "pool_retirement"
+        kind: String,
+        // Pool the certificate refers to, when it has one
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pool: Option<String>,
+        timestamp: u64,
+    },
     Other {
         event_type: String,
         timestamp: u64,
         #[serde(flatten)]
         details: serde_json::Value,
     },
-}
\ No newline at end of file
+}
+
+impl BlockchainEvent {
+    // Variant name, matching the serialized `type` tag
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Block { .. } => "Block",
+            Self::Transaction { .. } => "Transaction",
+            Self::TxInput { .. } => "TxInput",
+            Self::TxOutput { .. } => "TxOutput",
+            Self::RollBack { .. } => "RollBack",
+            Self::Mint { .. } => "Mint",
+            Self::Metadata { .. } => "Metadata",
+            Self::NativeScript { .. } => "NativeScript",
+            Self::Certificate { .. } => "Certificate",
+            Self::Other { .. } => "Other",
+        }
+    }
+
+    // Chain slot the event belongs to (block_slot for rollbacks); None for Other
+    pub fn slot(&self) -> Option<u64> {
+        match self {
+            Self::Block { slot, .. }
+            | Self::Transaction { slot, .. }
+            | Self::TxInput { slot, .. }
+            | Self::TxOutput { slot, .. }
+            | Self::Mint { slot, .. }
+            | Self::Metadata { slot, .. }
+            | Self::NativeScript { slot, .. }
+            | Self::Certificate { slot, .. } => Some(*slot),
+            Self::RollBack { block_slot, .. } => Some(*block_slot),
+            Self::Other { .. } => None,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seconds_pass_through_and_milliseconds_are_scaled_down() {
+        // Oura's seconds and the same instant in milliseconds
+        assert_eq!(normalize_unix_time(1_700_000_000), 1_700_000_000);
+        assert_eq!(normalize_unix_time(1_700_000_000_123), 1_700_000_000);
+        assert_eq!(normalize_unix_time(0), 0);
+
+        // Either side of the cut-over
+        assert_eq!(normalize_unix_time(MILLIS_THRESHOLD - 1), MILLIS_THRESHOLD - 1);
+        assert_eq!(normalize_unix_time(MILLIS_THRESHOLD), MILLIS_THRESHOLD / 1000);
+        assert_eq!(normalize_unix_time(u64::MAX), u64::MAX / 1000);
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Counters for messages lost between pipeline stages, shared across tasks
+pub struct Metrics {
+    // Capacity of the WebSocket broadcast channel (BROADCAST_CAPACITY)
+    pub broadcast_capacity: usize,
+    // Capacity of the bounded Oura → processor channel (OURA_CHANNEL_CAPACITY)
+    pub oura_channel_capacity: usize,
+    // Oura events that couldn't be handed to the processor
+    pub oura_send_failures: AtomicU64,
+    // Times a WebSocket client fell behind the channel, and how many messages it missed in total
+    pub ws_lag_events: AtomicU64,
+    pub ws_lagged_messages: AtomicU64,
+    // Blockfrost request count and total milliseconds per endpoint, e.g. "addresses/{}/transactions"
+    blockfrost_latency: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+/// Point-in-time copy of the counters, served at /metrics
+#[derive(Debug, Serialize)]
+pub struct MetricsSnapshot {
+    pub broadcast_capacity: usize,
+    pub oura_channel_capacity: usize,
+    pub oura_send_failures: u64,
+    pub ws_lag_events: u64,
+    pub ws_lagged_messages: u64,
+    // Omitted until a Blockfrost request has been made
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub blockfrost_latency: BTreeMap<String, EndpointLatency>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EndpointLatency {
+    pub requests: u64,
+    pub avg_ms: u64,
+}
+
+impl Metrics {
+    pub fn new(broadcast_capacity: usize, oura_channel_capacity: usize) -> Self {
+        Self {
+            broadcast_capacity,
+            oura_channel_capacity,
+            oura_send_failures: AtomicU64::new(0),
+            ws_lag_events: AtomicU64::new(0),
+            ws_lagged_messages: AtomicU64::new(0),
+            blockfrost_latency: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_blockfrost_latency(&self, endpoint: &str, elapsed_ms: u64) {
+        let mut latency = self.blockfrost_latency.lock().unwrap();
+        let (requests, total_ms) = latency.entry(endpoint.to_string()).or_default();
+        *requests = requests.saturating_add(1);
+        *total_ms = total_ms.saturating_add(elapsed_ms);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            broadcast_capacity: self.broadcast_capacity,
+            oura_channel_capacity: self.oura_channel_capacity,
+            oura_send_failures: self.oura_send_failures.load(Ordering::Relaxed),
+            ws_lag_events: self.ws_lag_events.load(Ordering::Relaxed),
+            ws_lagged_messages: self.ws_lagged_messages.load(Ordering::Relaxed),
+            blockfrost_latency: self
+                .blockfrost_latency
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(endpoint, &(requests, total_ms))| {
+                    let latency = EndpointLatency {
+                        requests,
+                        avg_ms: total_ms / requests.max(1),
+                    };
+                    (endpoint.clone(), latency)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Lifecycle of the `oura dump` child process, served at /api/admin/oura
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OuraStatus {
+    // None while no child is running (or when reading from stdin/file)
+    pub pid: Option<u32>,
+    // Times the child was respawned after exiting
+    pub restarts: u64,
+    pub last_restart: Option<DateTime<Utc>>,
+    // True while the child process is running
+    pub connected: bool,
+}
+
+// Updated by the Oura reader, read by the admin endpoint
+pub type OuraStatusHandle = Arc<Mutex<OuraStatus>>;
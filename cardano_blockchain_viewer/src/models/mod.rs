@@ -1,7 +1,9 @@
 // Models module - exports all model types
 
 pub mod events;
+pub mod metrics;
 pub mod stats;
 
 pub use events::*;
+pub use metrics::*;
 pub use stats::*;
\ No newline at end of file
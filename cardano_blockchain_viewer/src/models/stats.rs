@@ -1,9 +1,11 @@
 use serde::Serialize;
 use std::collections::VecDeque;
+use std::time::Instant;
 
 use super::BlockchainEvent;
 
-/// Statistics about buffered blockchain events
+/// Statistics about buffered blockchain events.
+/// "Since the last clear" means since startup with BUFFER_CLEAR=cumulative
 #[derive(Debug, Clone, Serialize)]
 pub struct BufferStats {
     // usize is used for array and vector indexing, .len() and .capacity()
@@ -12,9 +14,74 @@ pub struct BufferStats {
     pub transactions_count: usize,
     pub inputs_count: usize,
     pub outputs_count: usize,
+    // Sum of Transaction total_output since the last clear
+    pub total_output_lovelace: u64,
+    // Sum of Transaction fee since the last clear
+    pub total_fees_lovelace: u64,
     pub buffer_size: usize,
     pub last_block_number: u64,
     pub last_slot: u64,
+    // Rollbacks since the last clear, and the slot the most recent one rolled back to
+    pub rollback_count: usize,
+    pub last_rollback_slot: Option<u64>,
+}
+
+/// What add_event drops once the buffer is full (BUFFER_EVICTION)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The oldest event, whatever its kind
+    #[default]
+    Fifo,
+    /// The oldest non-Block event, so TxInput/TxOutput bursts don't push every recent block out
+    PreferBlocks,
+}
+
+impl EvictionPolicy {
+    // `fifo` (default) or `prefer_blocks`
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("BUFFER_EVICTION").ok().as_deref().map(str::trim) {
+            None | Some("") => Ok(Self::Fifo),
+            Some(value) if value.eq_ignore_ascii_case("fifo") => Ok(Self::Fifo),
+            Some(value) if value.eq_ignore_ascii_case("prefer_blocks") => Ok(Self::PreferBlocks),
+            Some(value) => Err(format!(
+                "Unknown BUFFER_EVICTION '{}' (expected fifo or prefer_blocks)",
+                value
+            )),
+        }
+    }
+}
+
+/// What clear_buffer does to the counters once MAX_BLOCK_COUNT/MAX_TX_COUNT is reached (BUFFER_CLEAR).
+/// Either way clients get a `buffer_cleared` notice before the next stats message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClearStrategy {
+    /// Empty the buffer and reset the per-kind counters and lovelace sums to zero
+    #[default]
+    Reset,
+    /// Only empty the buffer; counters keep growing so dashboards see no drop
+    Cumulative,
+}
+
+impl ClearStrategy {
+    // `reset` (default) or `cumulative`
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("BUFFER_CLEAR").ok().as_deref().map(str::trim) {
+            None | Some("") => Ok(Self::Reset),
+            Some(value) if value.eq_ignore_ascii_case("reset") => Ok(Self::Reset),
+            Some(value) if value.eq_ignore_ascii_case("cumulative") => Ok(Self::Cumulative),
+            Some(value) => Err(format!(
+                "Unknown BUFFER_CLEAR '{}' (expected reset or cumulative)",
+                value
+            )),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reset => "reset",
+            Self::Cumulative => "cumulative",
+        }
+    }
 }
 
 /// Application state holding the event buffer and statistics
@@ -24,9 +91,21 @@ pub struct AppState {
     pub transactions_count: usize,
     pub inputs_count: usize,
     pub outputs_count: usize,
+    pub total_output_lovelace: u64,
+    pub total_fees_lovelace: u64,
     pub total_events: usize,
     pub last_block_number: u64,
     pub last_slot: u64,
+    pub rollback_count: usize,
+    pub last_rollback_slot: Option<u64>,
+    // When a stats message was last broadcast, so the keepalive doesn't double-send
+    pub last_stats_sent: Option<Instant>,
+    pub eviction: EvictionPolicy,
+    pub clear_strategy: ClearStrategy,
+    // blocks_count/transactions_count at the last Cumulative clear, so should_clear measures
+    // growth since then instead of firing on every event
+    pub blocks_at_clear: usize,
+    pub transactions_at_clear: usize,
 }
 
 impl AppState {
@@ -38,56 +117,196 @@ impl AppState {
             transactions_count: 0,
             inputs_count: 0,
             outputs_count: 0,
+            total_output_lovelace: 0,
+            total_fees_lovelace: 0,
             total_events: 0,
             last_block_number: 0,
             last_slot: 0,
+            rollback_count: 0,
+            last_rollback_slot: None,
+            last_stats_sent: None,
+            eviction: EvictionPolicy::default(),
+            clear_strategy: ClearStrategy::default(),
+            blocks_at_clear: 0,
+            transactions_at_clear: 0,
         }
     }
 
+    pub fn with_eviction(mut self, eviction: EvictionPolicy) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    pub fn with_clear_strategy(mut self, clear_strategy: ClearStrategy) -> Self {
+        self.clear_strategy = clear_strategy;
+        self
+    }
+
     // Add an event to the buffer and update statistics
     pub fn add_event(&mut self, event: BlockchainEvent, buffer_size: usize){
+        self.count_event(&event);
+
+        // Add to buffer (circular buffer)
+        if self.buffer.len() >= buffer_size {
+            self.evict();
+        }
+        self.buffer.push_back(event);
+    }
 
+    fn evict(&mut self) {
+        let oldest_non_block = match self.eviction {
+            EvictionPolicy::Fifo => None,
+            EvictionPolicy::PreferBlocks => self
+                .buffer
+                .iter()
+                .position(|event| !matches!(event, BlockchainEvent::Block { .. })),
+        };
+        // With only blocks buffered, PreferBlocks falls back to the oldest one
+        match oldest_non_block {
+            Some(index) => {
+                self.buffer.remove(index);
+            }
+            None => {
+                self.buffer.pop_front();
+            }
+        }
+    }
+
+    // Update statistics without buffering, for events filtered out of the stream
+    pub fn count_event(&mut self, event: &BlockchainEvent) {
         // Count event types
         // the match event check which type is it from the enum in the events.rs
         // If the BlockchainEvent is block it runs the written code
         // It checks which type is it and then increases the count
-        match &event {
+        match event {
             // use only number and slot and ignore other field
             BlockchainEvent::Block { number, slot, ..} => {
-                self.blocks_count += 1;
+                self.blocks_count = self.blocks_count.saturating_add(1);
                 self.last_block_number = *number;
                 self.last_slot = *slot;
             }
-            BlockchainEvent::Transaction { .. } => self.transactions_count += 1,
-            BlockchainEvent::TxInput { .. } => self.inputs_count += 1,
-            BlockchainEvent::TxOutput { .. } => self.outputs_count += 1,
+            BlockchainEvent::Transaction { total_output, fee, .. } => {
+                self.transactions_count = self.transactions_count.saturating_add(1);
+                self.total_output_lovelace = self.total_output_lovelace.saturating_add(*total_output);
+                self.total_fees_lovelace = self.total_fees_lovelace.saturating_add(*fee);
+            }
+            BlockchainEvent::TxInput { .. } => {
+                self.inputs_count = self.inputs_count.saturating_add(1);
+            }
+            BlockchainEvent::TxOutput { .. } => {
+                self.outputs_count = self.outputs_count.saturating_add(1);
+            }
+            BlockchainEvent::RollBack { block_slot, .. } => {
+                self.rollback_count = self.rollback_count.saturating_add(1);
+                self.last_rollback_slot = Some(*block_slot);
+            }
             // _ = catch-all pattern.
             // Matches any value that hasn’t been matched earlier.
             // {} = do nothing.
             _ => {}
         }
 
-        self.total_events += 1;
+        // Counters saturate instead of wrapping (release) or panicking (debug) in very long runs
+        self.total_events = self.total_events.saturating_add(1);
+    }
+
+    // Remove buffered events after the rollback slot and undo their counts.
+    // Counts only ever shrink with saturating_sub: events filtered out of the buffer (EVENT_TYPES)
+    // were counted but can't be trimmed, so the totals may exceed what the buffer holds
+    pub fn rollback_to(&mut self, slot: u64) {
+        let mut removed_blocks = 0;
+        let mut removed_txs = 0;
+        let mut removed_inputs = 0;
+        let mut removed_outputs = 0;
+        let mut removed_output: u64 = 0;
+        let mut removed_fees: u64 = 0;
 
-        // Add to buffer (circular buffer)
-        if self.buffer.len() >= buffer_size {
-            self.buffer.pop_front();
+        self.buffer.retain(|event| match event {
+            BlockchainEvent::Block { slot: event_slot, .. } if *event_slot > slot => {
+                removed_blocks += 1;
+                false
+            }
+            BlockchainEvent::Transaction {
+                slot: event_slot,
+                total_output,
+                fee,
+                ..
+            } if *event_slot > slot => {
+                removed_txs += 1;
+                removed_output = removed_output.saturating_add(*total_output);
+                removed_fees = removed_fees.saturating_add(*fee);
+                false
+            }
+            BlockchainEvent::TxInput { slot: event_slot, .. } if *event_slot > slot => {
+                removed_inputs += 1;
+                false
+            }
+            BlockchainEvent::TxOutput { slot: event_slot, .. } if *event_slot > slot => {
+                removed_outputs += 1;
+                false
+            }
+            BlockchainEvent::Mint { slot: event_slot, .. }
+            | BlockchainEvent::Metadata { slot: event_slot, .. }
+            | BlockchainEvent::NativeScript { slot: event_slot, .. }
+            | BlockchainEvent::Certificate { slot: event_slot, .. }
+                if *event_slot > slot =>
+            {
+                false
+            }
+            _ => true,
+        });
+
+        self.blocks_count = self.blocks_count.saturating_sub(removed_blocks);
+        self.transactions_count = self.transactions_count.saturating_sub(removed_txs);
+        self.inputs_count = self.inputs_count.saturating_sub(removed_inputs);
+        self.outputs_count = self.outputs_count.saturating_sub(removed_outputs);
+        self.total_output_lovelace = self.total_output_lovelace.saturating_sub(removed_output);
+        self.total_fees_lovelace = self.total_fees_lovelace.saturating_sub(removed_fees);
+
+        // The chain tip is now the newest block we still hold (or the rollback point itself)
+        let last_block = self.buffer.iter().rev().find_map(|event| match event {
+            BlockchainEvent::Block { number, slot, .. } => Some((*number, *slot)),
+            _ => None,
+        });
+        match last_block {
+            Some((number, block_slot)) => {
+                self.last_block_number = number;
+                self.last_slot = block_slot;
+            }
+            None => self.last_slot = self.last_slot.min(slot),
         }
-        self.buffer.push_back(event);
     }
 
-    // Clear the buffer and reset counters (but keep total_events)
+    // Clear the buffer and, with ClearStrategy::Reset, the counters
+    // (total_events, the tip and the last rollback slot are always kept)
     pub fn clear_buffer(&mut self){
         self.buffer.clear();
+        if self.clear_strategy == ClearStrategy::Cumulative {
+            self.blocks_at_clear = self.blocks_count;
+            self.transactions_at_clear = self.transactions_count;
+            return;
+        }
         self.blocks_count = 0;
         self.transactions_count = 0;
         self.inputs_count = 0;
         self.outputs_count = 0;
+        self.total_output_lovelace = 0;
+        self.total_fees_lovelace = 0;
+        self.rollback_count = 0;
+    }
+
+    // Forget everything, including total_events, the tip and rollback history (POST /api/admin/reset)
+    pub fn reset(&mut self) {
+        let capacity = self.buffer.capacity();
+        *self = Self::new(capacity)
+            .with_eviction(self.eviction)
+            .with_clear_strategy(self.clear_strategy);
     }
 
     /// Check if buffer should be cleared based on thresholds
     pub fn should_clear(&self, max_blocks: usize, max_txs: usize) -> bool {
-        self.blocks_count >= max_blocks || self.transactions_count >= max_txs
+        self.blocks_count.saturating_sub(self.blocks_at_clear) >= max_blocks
+            || self.transactions_count.saturating_sub(self.transactions_at_clear) >= max_txs
     }
 
     // Get current statistics
@@ -98,9 +317,204 @@ impl AppState {
             transactions_count: self.transactions_count,
             inputs_count: self.inputs_count,
             outputs_count: self.outputs_count,
+            total_output_lovelace: self.total_output_lovelace,
+            total_fees_lovelace: self.total_fees_lovelace,
             buffer_size: self.buffer.len(),
             last_block_number: self.last_block_number,
             last_slot: self.last_slot,
+            rollback_count: self.rollback_count,
+            last_rollback_slot: self.last_rollback_slot,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(slot: u64, fee: u64, total_output: u64) -> BlockchainEvent {
+        BlockchainEvent::Transaction {
+            hash: format!("tx-{}", slot),
+            slot,
+            fee,
+            inputs: 1,
+            outputs: 1,
+            total_output,
+            mint_count: 0,
+            is_mint: false,
+            timestamp: 0,
+            details: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn output_volume_sums_transactions_and_saturates() {
+        let mut state = AppState::new(10);
+        state.add_event(transaction(1, 0, 1_500_000), 10);
+        state.add_event(transaction(2, 0, 2_500_000), 10);
+        state.add_event(transaction(3, 0, 1), 10);
+        assert_eq!(state.get_stats().total_output_lovelace, 4_000_001);
+
+        state.add_event(transaction(4, 0, u64::MAX), 10);
+        state.add_event(transaction(5, 0, 7), 10);
+        assert_eq!(state.get_stats().total_output_lovelace, u64::MAX);
+        assert_eq!(state.transactions_count, 5);
+    }
+
+    #[test]
+    fn fees_are_summed_until_the_buffer_is_cleared() {
+        let mut state = AppState::new(10);
+        for (slot, fee) in [(1, 170_000), (2, 200_000), (3, 1)] {
+            state.add_event(transaction(slot, fee, 0), 10);
+        }
+        let stats = state.get_stats();
+        assert_eq!(stats.total_fees_lovelace, 370_001);
+        assert_eq!(serde_json::to_value(&stats).unwrap()["total_fees_lovelace"], 370_001);
+
+        state.clear_buffer();
+        assert_eq!(state.get_stats().total_fees_lovelace, 0);
+        state.add_event(transaction(4, 50, 0), 10);
+        assert_eq!(state.get_stats().total_fees_lovelace, 50);
+    }
+
+    fn block(slot: u64) -> BlockchainEvent {
+        BlockchainEvent::Block {
+            slot,
+            hash: format!("block-{}", slot),
+            number: slot,
+            epoch: 1,
+            era: "Babbage".to_string(),
+            tx_count: 1,
+            timestamp: 0,
+            details: serde_json::json!({}),
+        }
+    }
+
+    fn output(slot: u64) -> BlockchainEvent {
+        BlockchainEvent::TxOutput {
+            tx_hash: format!("tx-{}", slot),
+            slot,
+            address: "addr_test1vz".to_string(),
+            amount: 1_000_000,
+            timestamp: 0,
+            tx_idx: None,
+            output_idx: None,
+        }
+    }
+
+    #[test]
+    fn rollback_drops_events_past_the_slot_and_their_counts() {
+        let mut state = AppState::new(10);
+        for slot in [10, 20, 30] {
+            state.add_event(block(slot), 10);
+            state.add_event(transaction(slot, 100, 1_000), 10);
+            state.add_event(output(slot), 10);
+        }
+
+        state.rollback_to(20);
+
+        let kept: Vec<_> = state.buffer.iter().map(|event| event.slot().unwrap()).collect();
+        assert_eq!(kept, [10, 10, 10, 20, 20, 20]);
+        let stats = state.get_stats();
+        assert_eq!((stats.blocks_count, stats.transactions_count, stats.outputs_count), (2, 2, 2));
+        assert_eq!((stats.total_output_lovelace, stats.total_fees_lovelace), (2_000, 200));
+        assert_eq!((stats.last_block_number, stats.last_slot), (20, 20));
+    }
+
+    fn rollback(block_slot: u64) -> BlockchainEvent {
+        BlockchainEvent::RollBack {
+            block_hash: format!("block-{}", block_slot),
+            block_slot,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn rollback_events_update_the_rollback_counters() {
+        let mut state = AppState::new(10);
+        assert_eq!((state.get_stats().rollback_count, state.get_stats().last_rollback_slot), (0, None));
+
+        state.add_event(rollback(20), 10);
+        state.add_event(rollback(15), 10);
+
+        let stats = state.get_stats();
+        assert_eq!((stats.rollback_count, stats.last_rollback_slot), (2, Some(15)));
+        assert_eq!(stats.total_events, 2);
+
+        // A clear starts a new count but still reports where the last rollback went
+        state.clear_buffer();
+        let stats = state.get_stats();
+        assert_eq!((stats.rollback_count, stats.last_rollback_slot), (0, Some(15)));
+    }
+
+    #[test]
+    fn counters_at_their_limits_saturate_instead_of_panicking() {
+        let mut state = AppState::new(10);
+        state.blocks_count = usize::MAX;
+        state.transactions_count = usize::MAX;
+        state.outputs_count = usize::MAX;
+        state.rollback_count = usize::MAX;
+        state.total_events = usize::MAX - 1;
+        state.total_fees_lovelace = u64::MAX - 1;
+
+        for event in [block(10), transaction(10, 5, 1), output(10), rollback(5)] {
+            state.add_event(event, 10);
+        }
+
+        let stats = state.get_stats();
+        assert_eq!((stats.blocks_count, stats.transactions_count), (usize::MAX, usize::MAX));
+        assert_eq!((stats.outputs_count, stats.rollback_count), (usize::MAX, usize::MAX));
+        assert_eq!((stats.total_events, stats.total_fees_lovelace), (usize::MAX, u64::MAX));
+        assert!(state.should_clear(usize::MAX, usize::MAX));
+
+        // Trimming more than was counted stops at zero
+        state.blocks_count = 0;
+        state.transactions_count = 0;
+        state.total_fees_lovelace = 1;
+        state.rollback_to(0);
+        assert_eq!((state.blocks_count, state.transactions_count, state.total_fees_lovelace), (0, 0, 0));
+    }
+
+    fn input(slot: u64) -> BlockchainEvent {
+        BlockchainEvent::TxInput {
+            tx_hash: format!("tx-{}", slot),
+            slot,
+            input_tx_id: "ab".repeat(32),
+            input_index: 0,
+            timestamp: 0,
+            input_idx: None,
+        }
+    }
+
+    // Slots of the buffered events, tagged "b" for blocks and "i" for inputs
+    fn buffered(state: &AppState) -> Vec<(&'static str, u64)> {
+        state
+            .buffer
+            .iter()
+            .map(|event| match event {
+                BlockchainEvent::Block { slot, .. } => ("b", *slot),
+                event => ("i", event.slot().unwrap()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prefer_blocks_evicts_inputs_before_the_block() {
+        let mut fifo = AppState::new(3);
+        let mut prefer_blocks = AppState::new(3).with_eviction(EvictionPolicy::PreferBlocks);
+        for state in [&mut fifo, &mut prefer_blocks] {
+            state.add_event(block(1), 3);
+            for slot in 2..=6 {
+                state.add_event(input(slot), 3);
+            }
+        }
+
+        assert_eq!(buffered(&fifo), [("i", 4), ("i", 5), ("i", 6)]);
+        assert_eq!(buffered(&prefer_blocks), [("b", 1), ("i", 5), ("i", 6)]);
+
+        // With only blocks left, the oldest block goes
+        for slot in 7..=9 {
+            prefer_blocks.add_event(block(slot), 3);
+        }
+        assert_eq!(buffered(&prefer_blocks), [("b", 7), ("b", 8), ("b", 9)]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::config::env_flag;
+use crate::models::Point;
+
+/// Persists the last processed chain point so `oura dump` can resume from it after a restart
+pub struct CursorStore {
+    path: PathBuf,
+}
+
+impl CursorStore {
+    /// Enabled by `OURA_RESUME=true`; the point is kept in `OURA_CURSOR_FILE` (default `oura_cursor.json`)
+    pub fn from_env() -> Option<Self> {
+        if !env_flag("OURA_RESUME") {
+            return None;
+        }
+
+        let path = std::env::var("OURA_CURSOR_FILE")
+            .unwrap_or_else(|_| "oura_cursor.json".to_string());
+        info!("Oura resume enabled, cursor file: {}", path);
+        Some(Self {
+            path: PathBuf::from(path),
+        })
+    }
+
+    // Last saved point, or None on first start or when the file can't be read
+    pub fn load(&self) -> Option<Point> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(point) => Some(point),
+            Err(e) => {
+                warn!("Ignoring unreadable cursor file {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    // Write to a temp file and rename so a crash never leaves a half-written cursor
+    pub async fn save(&self, point: &Point) -> std::io::Result<()> {
+        let json = serde_json::to_string(point)?;
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, json).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saved_point_is_read_back() {
+        let dir = std::env::temp_dir().join(format!("oura-cursor-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let store = CursorStore { path: dir.join("cursor.json") };
+        assert!(store.load().is_none());
+
+        store.save(&Point { hash: "aa".to_string(), slot: 10 }).await.unwrap();
+        store.save(&Point { hash: "bb".to_string(), slot: 20 }).await.unwrap();
+        let loaded = store.load().unwrap();
+        assert_eq!((loaded.hash.as_str(), loaded.slot), ("bb", 20));
+        assert!(!dir.join("cursor.tmp").exists());
+
+        tokio::fs::write(&store.path, "{ not json").await.unwrap();
+        assert!(store.load().is_none());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
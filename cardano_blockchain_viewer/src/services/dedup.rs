@@ -0,0 +1,48 @@
+use std::collections::{HashSet, VecDeque};
+
+/// Fingerprints of the most recently processed Oura events, to drop the duplicates oura
+/// re-emits after reconnecting. Once full, the oldest fingerprint is forgotten first
+pub struct FingerprintCache {
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+    // 0 disables deduplication
+    capacity: usize,
+}
+
+impl FingerprintCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Remember the fingerprint; false when it was already seen. Events without one always pass
+    pub fn insert(&mut self, fingerprint: Option<&str>) -> bool {
+        let Some(fingerprint) = fingerprint else {
+            return true;
+        };
+        if self.capacity == 0 {
+            return true;
+        }
+        if self.seen.contains(fingerprint) {
+            return false;
+        }
+
+        if self.order.len() >= self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        self.seen.insert(fingerprint.to_string());
+        self.order.push_back(fingerprint.to_string());
+        true
+    }
+
+    // After a rollback the same events may legitimately be applied again
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::models::BlockchainEvent;
+
+// Every BlockchainEvent kind, as named by its `type` tag
+const EVENT_KINDS: [&str; 10] = [
+    "Block",
+    "Transaction",
+    "TxInput",
+    "TxOutput",
+    "RollBack",
+    "Mint",
+    "Metadata",
+    "NativeScript",
+    "Certificate",
+    "Other",
+];
+
+/// Which event kinds are buffered and broadcast; filtered-out events are still counted in stats
+#[derive(Default)]
+pub struct EventFilter {
+    // None lets every kind through
+    enabled: Option<HashSet<&'static str>>,
+}
+
+impl EventFilter {
+    /// Read a comma-separated allowlist from `EVENT_TYPES` (e.g. `Block,Transaction`); unset allows everything
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("EVENT_TYPES") {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Ok(Self { enabled: None }),
+        }
+    }
+
+    /// Allowlist from a comma-separated list of kinds, matched case-insensitively
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut enabled = HashSet::new();
+        for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let kind = EVENT_KINDS
+                .iter()
+                .find(|kind| kind.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown event type '{}' in EVENT_TYPES (expected {})",
+                        name,
+                        EVENT_KINDS.join(", ")
+                    )
+                })?;
+            enabled.insert(*kind);
+        }
+
+        info!("Event types streamed: {}", value);
+        Ok(Self {
+            enabled: Some(enabled),
+        })
+    }
+
+    pub fn allows(&self, event: &BlockchainEvent) -> bool {
+        self.enabled
+            .as_ref()
+            .is_none_or(|enabled| enabled.contains(event.kind()))
+    }
+}
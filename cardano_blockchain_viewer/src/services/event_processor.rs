@@ -1,39 +1,111 @@
 // Let's multiple part of your program share the same data safely
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 // It makes sure only one task can modify data at a time
 use tokio::sync::{Mutex, broadcast};
-use tracing::info;
+use tracing::{debug, info, warn};
 
-use crate::config::{BUFFER_SIZE, MAX_BLOCK_COUNT, MAX_TX_COUNT};
-use crate::models::{AppState, BlockchainEvent, OuraEvent};
+use crate::config::{env_parse, BUFFER_SIZE, MAX_BLOCK_COUNT, MAX_TX_COUNT};
+use super::{CursorStore, EventFilter, FingerprintCache};
+use crate::models::{normalize_unix_time, AppState, BlockchainEvent, OuraEvent, Record};
+use crate::websocket::protocol::{self, Frame};
 
 // Service for processing Oura events and managing application state
 pub struct EventProcessor {
     state: Arc<Mutex<AppState>>,
+    // Flipped on the first processed event; backs the /ready probe
+    started_streaming: Arc<AtomicBool>,
+    // Send stats every N other events (STATS_EVERY_N_EVENTS, default 5); 0 sends them only for blocks/txs
+    stats_every_n_events: usize,
+    // Where the last block/rollback point is saved when OURA_RESUME is on
+    cursor: Option<CursorStore>,
+    // Event kinds that are buffered and broadcast (EVENT_TYPES)
+    filter: EventFilter,
+    // Recently processed fingerprints (DEDUP_CACHE_SIZE, default 1000; 0 disables)
+    fingerprints: std::sync::Mutex<FingerprintCache>,
 }
 
 impl EventProcessor {
     // Create a new EventProcessor with shared state
     pub fn new(state: Arc<Mutex<AppState>>) -> Self {
-        Self { state }
+        Self {
+            state,
+            started_streaming: Arc::new(AtomicBool::new(false)),
+            stats_every_n_events: env_parse("STATS_EVERY_N_EVENTS", 5),
+            cursor: None,
+            filter: EventFilter::default(),
+            fingerprints: std::sync::Mutex::new(FingerprintCache::new(env_parse(
+                "DEDUP_CACHE_SIZE",
+                1000,
+            ))),
+        }
+    }
+
+    // Persist the chain point of every processed block and rollback
+    pub fn with_cursor(mut self, cursor: Option<CursorStore>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    // Only buffer and broadcast the event kinds the filter allows
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // Shared flag that becomes true once the first Oura event has been processed
+    pub fn started_streaming(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.started_streaming)
     }
 
     // Process an Oura event: convert it, update state and broadcast
     pub async fn process_event(
         &self,
         oura_event: OuraEvent,
-        ws_tx: &broadcast::Sender<String>,
+        ws_tx: &broadcast::Sender<Frame>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.started_streaming.swap(true, Ordering::Relaxed) {
+            info!("✅ First Oura event received, live feed is ready");
+        }
+
+        // Oura replays events on reconnect; rollbacks reset the cache since their blocks are re-applied
+        {
+            let mut fingerprints = self.fingerprints.lock().unwrap();
+            if oura_event.record.roll_back.is_some() {
+                fingerprints.clear();
+            } else if !fingerprints.insert(oura_event.record.fingerprint.as_deref()) {
+                debug!("Skipping duplicate Oura event {:?}", oura_event.record.fingerprint);
+                return Ok(());
+            }
+        }
+
+        let point = oura_event.point.clone();
+
         // Convert Oura event to simplified blockchain event
         let event = self.convert_oura_event(oura_event);
 
+        let streamed = self.filter.allows(&event);
+
         // Log Summary
-        self.log_event(&event);
+        if streamed {
+            self.log_event(&event);
+        }
 
         // Add to buffer and update state
         {
             let mut state = self.state.lock().await;
-            state.add_event(event.clone(), BUFFER_SIZE);
+
+            // Drop buffered events from the blocks that were rolled back before recording the rollback
+            if let BlockchainEvent::RollBack { block_slot, .. } = &event {
+                state.rollback_to(*block_slot);
+            }
+
+            if streamed {
+                state.add_event(event.clone(), BUFFER_SIZE);
+            } else {
+                state.count_event(&event);
+            }
 
             // Check if we should clear the buffer
             if state.should_clear(MAX_BLOCK_COUNT, MAX_TX_COUNT) {
@@ -41,13 +113,17 @@ impl EventProcessor {
                     "Clearing buffer: blocks={}, txs={}",
                     state.blocks_count, state.transactions_count
                 );
+                broadcast_buffer_cleared(&state, ws_tx)?;
                 state.clear_buffer();
             }
 
-            // Send stats immediately for Block and Transaction events, and every 5 events for others
+            // Send stats immediately for Block and Transaction events, and every N events for others
             let should_send_stats = match &event {
                 BlockchainEvent::Block { .. } | BlockchainEvent::Transaction { .. } => true,
-                _ => state.total_events % 5 == 0,
+                _ => {
+                    self.stats_every_n_events > 0
+                        && state.total_events.is_multiple_of(self.stats_every_n_events)
+                }
             };
 
             if should_send_stats {
@@ -61,76 +137,127 @@ impl EventProcessor {
                     stats.total_events
                 );
 
-                // Create the expected stats message format for the frontend
-                let stats_message = serde_json::json!({
-                    "type": "stats",
-                    "data": stats
-                });
-
-                let stats_json = serde_json::to_string(&stats_message)?;
-
-                // Check if there are any active receivers before sending
-                if ws_tx.receiver_count() > 0 {
-                    if let Err(e) = ws_tx.send(stats_json) {
-                        // Channel send failed, but we have receivers so this is unexpected
-                        if state.total_events % 50 == 0 {
-                            info!("Failed to send stats to active receivers: {}", e);
-                        }
-                    }
-                }
+                broadcast_stats(&mut state, ws_tx)?;
             }
         }
 
         // Broadcast to WebSocket clients
-        let event_json = serde_json::to_string(&event)?;
+        if streamed {
+            let event_json = protocol::encode(&event)?;
 
-        if let Err(e) = ws_tx.send(event_json) {
             // Channel is likely full or closed (no receivers)
             // This is normal when no WebSocket clients are connected
             // Silent failure to avoid log spam
+            let _ = ws_tx.send(event_json.into());
+        }
+
+        // Block and rollback points are valid intersections to resume from
+        if let Some(cursor) = &self.cursor
+            && matches!(event, BlockchainEvent::Block { .. } | BlockchainEvent::RollBack { .. })
+            && let Err(e) = cursor.save(&point).await
+        {
+            warn!("Failed to save Oura cursor: {}", e);
         }
 
         Ok(())
     }
 
+    // Re-send stats every `interval` while no events arrive, so freshly connected dashboards aren't left empty
+    pub fn spawn_stats_keepalive(&self, ws_tx: broadcast::Sender<Frame>, interval: Duration) {
+        let state = Arc::clone(&self.state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                if ws_tx.receiver_count() == 0 {
+                    continue;
+                }
+
+                let mut state = state.lock().await;
+                // Events are flowing and already carried stats recently
+                if state.last_stats_sent.is_some_and(|sent| sent.elapsed() < interval) {
+                    continue;
+                }
+                if let Err(e) = broadcast_stats(&mut state, &ws_tx) {
+                    warn!("Failed to send keepalive stats: {}", e);
+                }
+            }
+        });
+    }
+
     // Convert Oura event to simplified blockchain event
     fn convert_oura_event(&self, oura_event: OuraEvent) -> BlockchainEvent {
-        let timestamp = oura_event.record.context.timestamp.unwrap_or(0);
+        // Unix seconds on the wire, whatever unit this Oura build reports
+        let timestamp = oura_event
+            .record
+            .context
+            .timestamp
+            .map(normalize_unix_time)
+            .unwrap_or(0);
+        let slot = oura_event.record.context.slot.unwrap_or(oura_event.point.slot);
 
         // Check what type of record this is
         if let Some(block) = oura_event.record.block {
+            // Fields promoted to the top level are left out of details, so serializing the event
+            // directly (e.g. /api/events/recent) doesn't repeat keys
+            let mut details = serde_json::to_value(&block).unwrap_or(serde_json::Value::Null);
+            if let serde_json::Value::Object(map) = &mut details {
+                for key in ["slot", "hash", "number", "epoch", "era", "tx_count"] {
+                    map.remove(key);
+                }
+            }
             BlockchainEvent::Block {
                 slot: block.slot,
                 hash: block.hash.clone(),
                 number: block.number,
                 epoch: block.epoch,
+                era: block.era.clone(),
                 tx_count: block.tx_count,
                 timestamp,
-                details: serde_json::to_value(&block).unwrap_or(serde_json::Value::Null),
+                details,
             }
         } else if let Some(tx) = oura_event.record.transaction {
+            // Same as for blocks; input_count/output_count stay since clients read them alongside
+            // inputs/outputs
+            let mut details = serde_json::to_value(&tx).unwrap_or(serde_json::Value::Null);
+            if let serde_json::Value::Object(map) = &mut details {
+                for key in ["hash", "fee", "total_output", "mint_count"] {
+                    map.remove(key);
+                }
+            }
             BlockchainEvent::Transaction {
                 hash: tx.hash.clone(),
+                slot,
                 fee: tx.fee,
                 inputs: tx.input_count,
                 outputs: tx.output_count,
                 total_output: tx.total_output,
+                mint_count: tx.mint_count,
+                is_mint: tx.mint_count > 0,
                 timestamp,
-                details: serde_json::to_value(&tx).unwrap_or(serde_json::Value::Null),
+                details,
             }
         } else if let Some(input) = oura_event.record.tx_input {
             BlockchainEvent::TxInput {
                 tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
                 input_tx_id: input.tx_id,
                 input_index: input.index,
                 timestamp,
+                input_idx: oura_event.record.context.input_idx,
             }
         } else if let Some(output) = oura_event.record.tx_output {
             BlockchainEvent::TxOutput {
                 tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
                 address: output.address,
                 amount: output.amount,
                 timestamp,
+                tx_idx: oura_event.record.context.tx_idx,
+                output_idx: oura_event.record.context.output_idx,
             }
         } else if let Some(rollback) = oura_event.record.roll_back {
             BlockchainEvent::RollBack {
@@ -138,6 +265,37 @@ impl EventProcessor {
                 block_slot: rollback.block_slot,
                 timestamp,
             }
+        } else if let Some(mint) = oura_event.record.mint {
+            BlockchainEvent::Mint {
+                tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
+                policy: mint.policy,
+                asset: mint.asset,
+                quantity: mint.quantity,
+                timestamp,
+            }
+        } else if let Some(metadata) = oura_event.record.metadata {
+            BlockchainEvent::Metadata {
+                tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
+                label: metadata.label,
+                timestamp,
+            }
+        } else if let Some(script) = oura_event.record.native_script {
+            BlockchainEvent::NativeScript {
+                tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
+                policy_id: script.policy_id,
+                timestamp,
+            }
+        } else if let Some((kind, pool)) = certificate_summary(&oura_event.record) {
+            BlockchainEvent::Certificate {
+                tx_hash: oura_event.record.context.tx_hash.unwrap_or_default(),
+                slot,
+                kind: kind.to_string(),
+                pool,
+                timestamp,
+            }
         } else {
             let event_type = oura_event.event.clone();
             BlockchainEvent::Other {
@@ -166,7 +324,7 @@ impl EventProcessor {
 
             // If event type is transaction
             BlockchainEvent::Transaction { hash, fee, .. } => {
-                info!("💳 Transaction {} (fee: {} lovelace)", &hash[..16], fee);
+                info!("💳 Transaction {} (fee: {} lovelace)", truncate(hash, 16), fee);
             }
 
             // If event type is TxInput
@@ -175,14 +333,14 @@ impl EventProcessor {
                 input_index,
                 ..
             } => {
-                info!("📥 Input: {}:{}", &input_tx_id[..16], input_index);
+                info!("📥 Input: {}:{}", truncate(input_tx_id, 16), input_index);
             }
 
             // If event type is TxOutput
             BlockchainEvent::TxOutput {
                 address, amount, ..
             } => {
-                info!("📤 Output: {} lovelace to {}", amount, &address[..20]);
+                info!("📤 Output: {} lovelace to {}", amount, truncate(address, 20));
             }
 
             BlockchainEvent::RollBack {
@@ -192,7 +350,7 @@ impl EventProcessor {
             } => {
                 info!(
                     "🔄 Rollback to block {} at slot {}",
-                    &block_hash[..16],
+                    truncate(block_hash, 16),
                     block_slot
                 );
             }
@@ -201,9 +359,434 @@ impl EventProcessor {
             _ => {}
         }
     }
+}
+
+// Oura record name and referenced pool of a certificate record, if the record is one
+fn certificate_summary(record: &Record) -> Option<(&'static str, Option<String>)> {
+    if let Some(delegation) = &record.stake_delegation {
+        Some(("stake_delegation", Some(delegation.pool_hash.clone())))
+    } else if let Some(registration) = &record.pool_registration {
+        Some(("pool_registration", Some(registration.operator.clone())))
+    } else if let Some(retirement) = &record.pool_retirement {
+        Some(("pool_retirement", Some(retirement.pool.clone())))
+    } else if record.stake_registration.is_some() {
+        Some(("stake_registration", None))
+    } else if record.stake_deregistration.is_some() {
+        Some(("stake_deregistration", None))
+    } else {
+        None
+    }
+}
+
+// Sent before the buffer is emptied, with the counts it held, so clients can tell the stats
+// discontinuity (or, with cumulative clears, the emptied replay buffer) from a restart
+fn broadcast_buffer_cleared(
+    state: &AppState,
+    ws_tx: &broadcast::Sender<Frame>,
+) -> Result<(), serde_json::Error> {
+    let notice = serde_json::json!({
+        "type": "buffer_cleared",
+        "strategy": state.clear_strategy.as_str(),
+        "blocks_count": state.blocks_count,
+        "transactions_count": state.transactions_count,
+        "buffer_size": state.buffer.len(),
+    });
+    if ws_tx.receiver_count() > 0 {
+        let _ = ws_tx.send(protocol::encode(&notice)?.into());
+    }
+    Ok(())
+}
+
+// Broadcast the `"type":"stats"` message the frontend expects and record when it was sent
+pub(crate) fn broadcast_stats(
+    state: &mut AppState,
+    ws_tx: &broadcast::Sender<Frame>,
+) -> Result<(), serde_json::Error> {
+    let stats_message = serde_json::json!({
+        "type": "stats",
+        "data": state.get_stats()
+    });
+    let stats_json = protocol::encode(&stats_message)?;
+    state.last_stats_sent = Some(std::time::Instant::now());
+
+    // Check if there are any active receivers before sending
+    if ws_tx.receiver_count() > 0
+        && let Err(e) = ws_tx.send(stats_json.into())
+    {
+        // Channel send failed, but we have receivers so this is unexpected
+        if state.total_events.is_multiple_of(50) {
+            info!("Failed to send stats to active receivers: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+// First `max` characters of `s` for log previews; never panics on short or non-ASCII input
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ClearStrategy;
+    use serde_json::json;
+
+    fn processor() -> EventProcessor {
+        EventProcessor::new(Arc::new(Mutex::new(AppState::new(10))))
+    }
+
+    fn oura_event(record: serde_json::Value) -> OuraEvent {
+        let mut record = record;
+        record["context"] =
+            json!({ "block_hash": null, "block_number": 5, "slot": 10, "timestamp": 1_700_000_000 });
+        serde_json::from_value(json!({
+            "event": "apply",
+            "point": { "hash": "ab".repeat(32), "slot": 10 },
+            "record": record,
+        }))
+        .unwrap()
+    }
+
+    // Serializing the event directly, as /api/events/recent does, must not repeat a key
+    fn assert_keys_unique(event: &BlockchainEvent) {
+        let raw = serde_json::to_string(event).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        for key in value.as_object().unwrap().keys() {
+            assert_eq!(raw.matches(&format!("\"{}\":", key)).count(), 1, "{} repeated in {}", key, raw);
+        }
+    }
+
+    #[test]
+    fn transaction_details_leave_out_the_promoted_fields() {
+        let event = processor().convert_oura_event(oura_event(json!({
+            "transaction": {
+                "hash": "cd".repeat(32), "fee": 170_000, "size": 300, "input_count": 1, "output_count": 2,
+                "total_output": 5_000_000, "mint_count": 0, "collateral_input_count": 0,
+                "has_collateral_output": false
+            }
+        })));
+
+        let BlockchainEvent::Transaction { details, .. } = &event else {
+            panic!("expected a Transaction, got {:?}", event);
+        };
+        let expected = json!({
+            "size": 300,
+            "input_count": 1,
+            "output_count": 2,
+            "collateral_input_count": 0,
+            "has_collateral_output": false
+        });
+        assert_eq!(details, &expected);
+        assert_keys_unique(&event);
+    }
+
+    #[test]
+    fn minting_transaction_is_flagged() {
+        for (mint_count, is_mint) in [(3, true), (0, false)] {
+            let event = processor().convert_oura_event(oura_event(json!({
+                "transaction": {
+                    "hash": "cd".repeat(32), "fee": 170_000, "size": 300, "input_count": 1, "output_count": 2,
+                    "total_output": 5_000_000, "mint_count": mint_count, "collateral_input_count": 0,
+                    "has_collateral_output": false
+                }
+            })));
+
+            let wire = serde_json::to_value(&event).unwrap();
+            assert_eq!((wire["mint_count"].clone(), wire["is_mint"].clone()), (json!(mint_count), json!(is_mint)));
+        }
+    }
+
+    // Certificate and mint records in the shape `oura dump` prints them, with preprod-like values
+    const STAKE_DELEGATION: &str = r#"{"event":"apply","point":{"hash":"5f20df933584822601f9e3f8c024eb5eb252fe8cefb24d1317dc3d432e940ebb","slot":42781314},"record":{"context":{"block_hash":"5f20df933584822601f9e3f8c024eb5eb252fe8cefb24d1317dc3d432e940ebb","block_number":1542630,"slot":42781314,"timestamp":1697447714,"tx_idx":3,"tx_hash":"8f25a31e8bdbd9c4bd3729cbcfe99e00d5d28aadd5620de8e772f6fb1f313811","certificate_idx":1},"stake_delegation":{"credential":{"AddrKeyhash":"7b1a9d9f5c14ad27ee9bdc0f2a1b61f0a9e321d90e22e521b0337a1d"},"pool_hash":"c3e27f2ed33c8bb4af9f0fa1da0bd056a7bb58ab2cd6bf1d9a8cfbb1"},"fingerprint":"42781314.cert.8f25a31e8bdbd9c4.1"}}"#;
+    const MINT: &str = r#"{"event":"apply","point":{"hash":"5f20df933584822601f9e3f8c024eb5eb252fe8cefb24d1317dc3d432e940ebb","slot":42781314},"record":{"context":{"block_hash":"5f20df933584822601f9e3f8c024eb5eb252fe8cefb24d1317dc3d432e940ebb","block_number":1542630,"slot":42781314,"timestamp":1697447714,"tx_idx":3,"tx_hash":"8f25a31e8bdbd9c4bd3729cbcfe99e00d5d28aadd5620de8e772f6fb1f313811"},"mint":{"policy":"b0d07d45fe9514f80213f4020e5a61241458be626841cde717cb38a7","asset":"6e7574636f696e","quantity":-12},"fingerprint":"42781314.mint.8f25a31e8bdbd9c4.0"}}"#;
+
+    #[test]
+    fn certificate_record_becomes_a_lean_certificate_event() {
+        let oura_event: OuraEvent = serde_json::from_str(STAKE_DELEGATION).unwrap();
+        let delegation = oura_event.record.stake_delegation.as_ref().unwrap();
+        assert_eq!(delegation.pool_hash, "c3e27f2ed33c8bb4af9f0fa1da0bd056a7bb58ab2cd6bf1d9a8cfbb1");
+        assert_eq!(oura_event.record.context.certificate_idx, Some(1));
+
+        let event = processor().convert_oura_event(oura_event);
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "Certificate",
+                "tx_hash": "8f25a31e8bdbd9c4bd3729cbcfe99e00d5d28aadd5620de8e772f6fb1f313811",
+                "slot": 42781314,
+                "kind": "stake_delegation",
+                "pool": "c3e27f2ed33c8bb4af9f0fa1da0bd056a7bb58ab2cd6bf1d9a8cfbb1",
+                "timestamp": 1697447714
+            })
+        );
+    }
+
+    #[test]
+    fn mint_record_becomes_a_lean_mint_event() {
+        let oura_event: OuraEvent = serde_json::from_str(MINT).unwrap();
+        let mint = oura_event.record.mint.as_ref().unwrap();
+        assert_eq!((mint.asset.as_str(), mint.quantity), ("6e7574636f696e", -12));
+
+        let event = processor().convert_oura_event(oura_event);
+
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "Mint",
+                "tx_hash": "8f25a31e8bdbd9c4bd3729cbcfe99e00d5d28aadd5620de8e772f6fb1f313811",
+                "slot": 42781314,
+                "policy": "b0d07d45fe9514f80213f4020e5a61241458be626841cde717cb38a7",
+                "asset": "6e7574636f696e",
+                "quantity": -12,
+                "timestamp": 1697447714
+            })
+        );
+    }
+
+    #[test]
+    fn block_details_leave_out_the_promoted_fields() {
+        let event = processor().convert_oura_event(oura_event(json!({
+            "block": {
+                "hash": "ab".repeat(32), "number": 5, "slot": 10, "epoch": 1, "epoch_slot": 2,
+                "era": "Babbage", "body_size": 3, "issuer_vkey": "aa", "vrf_vkey": "bb", "tx_count": 0,
+                "previous_hash": "ef".repeat(32)
+            }
+        })));
+
+        assert!(matches!(event, BlockchainEvent::Block { .. }), "{:?}", event);
+        assert_keys_unique(&event);
+    }
+
+    #[tokio::test]
+    async fn keepalive_tick_broadcasts_stats_without_new_events() {
+        let processor = processor();
+        let (ws_tx, mut ws_rx) = broadcast::channel(4);
+
+        processor.spawn_stats_keepalive(ws_tx, Duration::from_millis(20));
+
+        let frame = tokio::time::timeout(Duration::from_secs(5), ws_rx.recv())
+            .await
+            .expect("no keepalive within 5s")
+            .unwrap();
+        let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+        assert_eq!(frame["type"], "stats");
+        assert_eq!(frame["data"]["total_events"], 0);
+    }
+
+    #[test]
+    fn tx_input_and_output_carry_the_context_slot() {
+        let processor = processor();
+        let mut input = oura_event(json!({ "tx_input": { "tx_id": "ab", "index": 0 } }));
+        input.record.context.slot = Some(4_242);
+        let mut output = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+        output.record.context.slot = Some(4_243);
+        // Without a context slot the event point's slot is used
+        let mut pointless = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+        pointless.record.context.slot = None;
+        pointless.point.slot = 4_244;
+
+        let slots: Vec<_> = [input, output, pointless]
+            .into_iter()
+            .map(|event| processor.convert_oura_event(event))
+            .map(|event| (event.kind(), event.slot()))
+            .collect();
+        assert_eq!(
+            slots,
+            [("TxInput", Some(4_242)), ("TxOutput", Some(4_243)), ("TxOutput", Some(4_244))]
+        );
+    }
+
+    #[tokio::test]
+    async fn context_indices_reach_the_broadcast_events() {
+        let processor = processor();
+        let (ws_tx, mut ws_rx) = broadcast::channel(64);
+        let mut output = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+        output.record.context.tx_idx = Some(2);
+        output.record.context.output_idx = Some(1);
+        let mut input = oura_event(json!({ "tx_input": { "tx_id": "ab", "index": 0 } }));
+        input.record.context.input_idx = Some(3);
+        let unindexed = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+
+        for oura_event in [output, input, unindexed] {
+            processor.process_event(oura_event, &ws_tx).await.unwrap();
+        }
+
+        let mut indices = Vec::new();
+        while let Ok(frame) = ws_rx.try_recv() {
+            let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+            if frame["type"] != "stats" {
+                let frame = frame.as_object().unwrap();
+                let present = ["tx_idx", "output_idx", "input_idx"].map(|key| frame.get(key).cloned());
+                indices.push((frame["type"].clone(), present));
+            }
+        }
+        // Absent indices are left out rather than sent as null
+        assert_eq!(
+            indices,
+            [
+                (json!("TxOutput"), [Some(json!(2)), Some(json!(1)), None]),
+                (json!("TxInput"), [None, None, Some(json!(3))]),
+                (json!("TxOutput"), [None, None, None]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn every_broadcast_message_carries_the_protocol_version() {
+        let processor = processor();
+        let (ws_tx, mut ws_rx) = broadcast::channel(64);
+        let records = [
+            json!({ "block": {
+                "hash": "ab".repeat(32), "number": 5, "slot": 10, "epoch": 1, "epoch_slot": 2, "era": "Babbage",
+                "body_size": 3, "issuer_vkey": "aa", "vrf_vkey": "bb", "tx_count": 0, "previous_hash": "ef".repeat(32)
+            }}),
+            json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }),
+            json!({ "mint": { "policy": "ff", "asset": "aa", "quantity": 1 } }),
+            json!({ "roll_back": { "block_hash": "ab".repeat(32), "block_slot": 9 } }),
+            json!({ "unknown_record": {} }),
+        ];
+
+        for record in records {
+            processor.process_event(oura_event(record), &ws_tx).await.unwrap();
+        }
+        broadcast_stats(&mut *processor.state.lock().await, &ws_tx).unwrap();
+
+        let mut types = Vec::new();
+        while let Ok(frame) = ws_rx.try_recv() {
+            let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+            assert_eq!(frame["v"], protocol::PROTOCOL_VERSION, "{}", frame);
+            types.push(frame["type"].as_str().unwrap().to_string());
+        }
+        for kind in ["stats", "Block", "TxOutput", "Mint", "RollBack", "Other"] {
+            assert!(types.iter().any(|t| t == kind), "no {} frame in {:?}", kind, types);
+        }
+    }
+
+    #[tokio::test]
+    async fn stats_follow_the_configured_cadence() {
+        let mut processor = processor();
+        processor.stats_every_n_events = 2;
+        let (ws_tx, mut ws_rx) = broadcast::channel(64);
+
+        for _ in 0..5 {
+            let output = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+            processor.process_event(output, &ws_tx).await.unwrap();
+        }
+
+        let mut frames = Vec::new();
+        while let Ok(frame) = ws_rx.try_recv() {
+            let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+            frames.push(match frame["type"].as_str().unwrap() {
+                "stats" => format!("stats@{}", frame["data"]["total_events"]),
+                other => other.to_string(),
+            });
+        }
+        assert_eq!(
+            frames,
+            ["TxOutput", "stats@2", "TxOutput", "TxOutput", "stats@4", "TxOutput", "TxOutput"]
+        );
+    }
+
+    #[tokio::test]
+    async fn clear_is_announced_before_the_stats_that_follow_it() {
+        for strategy in [ClearStrategy::Reset, ClearStrategy::Cumulative] {
+            let state = Arc::new(Mutex::new(AppState::new(BUFFER_SIZE).with_clear_strategy(strategy)));
+            let processor = EventProcessor::new(Arc::clone(&state));
+            let (ws_tx, mut ws_rx) = broadcast::channel(4 * MAX_BLOCK_COUNT);
+
+            for number in 0..MAX_BLOCK_COUNT as u64 {
+                let block = oura_event(json!({ "block": {
+                    "hash": format!("{:064x}", number), "number": number, "slot": 10, "epoch": 1, "epoch_slot": 2,
+                    "era": "Babbage", "body_size": 3, "issuer_vkey": "aa", "vrf_vkey": "bb", "tx_count": 0,
+                    "previous_hash": "ef".repeat(32)
+                }}));
+                processor.process_event(block, &ws_tx).await.unwrap();
+            }
+
+            let mut frames = Vec::new();
+            while let Ok(frame) = ws_rx.try_recv() {
+                frames.push(serde_json::from_str::<serde_json::Value>(frame.json()).unwrap());
+            }
+            let notice = frames
+                .iter()
+                .position(|frame| frame["type"] == "buffer_cleared")
+                .expect("no buffer_cleared notice");
+            assert_eq!(frames[notice]["strategy"], strategy.as_str());
+            assert_eq!(frames[notice]["blocks_count"], MAX_BLOCK_COUNT);
+            assert_eq!(frames[notice]["buffer_size"], MAX_BLOCK_COUNT);
+            let stats = &frames[notice + 1];
+            assert_eq!(stats["type"], "stats", "{:?}", strategy);
+            let blocks_after = if strategy == ClearStrategy::Reset { 0 } else { MAX_BLOCK_COUNT };
+            assert_eq!(stats["data"]["blocks_count"], blocks_after, "{:?}", strategy);
+            assert!(state.lock().await.buffer.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn filtered_out_kinds_are_counted_but_not_buffered_or_broadcast() {
+        let state = Arc::new(Mutex::new(AppState::new(10)));
+        let processor = EventProcessor::new(Arc::clone(&state)).with_filter(EventFilter::parse("Block").unwrap());
+        let (ws_tx, mut ws_rx) = broadcast::channel(64);
+
+        let output = oura_event(json!({ "tx_output": { "address": "addr_test1vz", "amount": 1 } }));
+        processor.process_event(output, &ws_tx).await.unwrap();
+
+        let state = state.lock().await;
+        assert!(state.buffer.is_empty());
+        assert_eq!((state.outputs_count, state.total_events), (1, 1));
+        while let Ok(frame) = ws_rx.try_recv() {
+            let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+            assert_eq!(frame["type"], "stats", "{}", frame);
+        }
+    }
+
+    #[tokio::test]
+    async fn fingerprinted_duplicate_is_counted_once() {
+        let state = Arc::new(Mutex::new(AppState::new(10)));
+        let processor = EventProcessor::new(Arc::clone(&state));
+        let (ws_tx, _ws_rx) = broadcast::channel(64);
+        let replayed: OuraEvent = serde_json::from_str(MINT).unwrap();
+        let mut unfingerprinted = replayed.clone();
+        unfingerprinted.record.fingerprint = None;
+
+        for oura_event in [replayed.clone(), replayed, unfingerprinted.clone(), unfingerprinted] {
+            processor.process_event(oura_event, &ws_tx).await.unwrap();
+        }
+
+        // Once for the fingerprinted pair, twice for the events oura didn't fingerprint
+        let state = state.lock().await;
+        assert_eq!((state.total_events, state.buffer.len()), (3, 3));
+    }
+
+    #[tokio::test]
+    async fn short_and_multibyte_hashes_are_logged_without_panicking() {
+        // Log arguments are only formatted when a subscriber listens. The test runtime is
+        // single-threaded, so a thread-local one sees every event
+        let subscriber = tracing_subscriber::fmt().with_test_writer().finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let processor = processor();
+        let (ws_tx, _ws_rx) = broadcast::channel(64);
+        let records = [
+            json!({ "transaction": {
+                "hash": "ab", "fee": 1, "size": 1, "input_count": 0, "output_count": 0, "total_output": 0,
+                "mint_count": 0, "collateral_input_count": 0, "has_collateral_output": false
+            }}),
+            json!({ "tx_input": { "tx_id": "", "index": 0 } }),
+            json!({ "tx_output": { "address": "addr_€€€€€€€€€€€€€€€€€€€", "amount": 1 } }),
+            json!({ "roll_back": { "block_hash": "aéééééééééééééééé", "block_slot": 1 } }),
+        ];
+
+        for record in records {
+            processor.process_event(oura_event(record), &ws_tx).await.unwrap();
+        }
 
-    // Get the current Application state (for WebSocket initial sync)
-    pub fn get_state(&self) -> Arc<Mutex<AppState>> {
-        Arc::clone(&self.state)
+        assert_eq!(truncate("ab", 16), "ab");
+        assert_eq!(truncate("ééé", 2), "éé");
     }
 }
@@ -2,6 +2,12 @@
 
 pub mod oura_reader;
 pub mod event_processor;
+pub mod cursor;
+pub mod event_filter;
+pub mod dedup;
 
-pub use oura_reader::OuraReader;
-pub use event_processor::EventProcessor;
\ No newline at end of file
+pub use oura_reader::{OuraError, OuraReader};
+pub use event_processor::EventProcessor;
+pub use cursor::CursorStore;
+pub use event_filter::EventFilter;
+pub use dedup::FingerprintCache;
@@ -1,47 +1,199 @@
+use std::fmt;
+use std::io::ErrorKind;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::broadcast;
+use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-use crate::config::CardanoConfig;
-use crate::models::OuraEvent;
+use crate::config::{Bearer, CardanoConfig, EventSource};
+use crate::config::env_parse;
+use crate::models::{Metrics, OuraEvent, OuraStatusHandle, Point};
+
+// Send failures are expected while nothing is subscribed, so warn about them at most this often
+const SEND_FAILURE_WARN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Errors that stop the Oura reader
+#[derive(Debug)]
+pub enum OuraError {
+    /// The `oura` binary could not be found at the configured path
+    BinaryNotFound(String),
+    /// Spawning the process or reading the event source failed
+    Io(std::io::Error),
+}
+
+impl fmt::Display for OuraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BinaryNotFound(bin) => write!(
+                f,
+                "`{}` binary not found on PATH — install oura, set OURA_BIN to its path or set OURA_SOURCE=file",
+                bin
+            ),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for OuraError {}
+
+impl From<std::io::Error> for OuraError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
 
 /// Service for managing the Oura subprocess and reading blockchain events
 pub struct OuraReader {
     config: CardanoConfig,
+    // Path or name of the oura binary (OURA_BIN, defaults to `oura` on PATH)
+    oura_bin: String,
+    // Appended to the `oura dump` arguments (OURA_EXTRA_ARGS, split on whitespace)
+    extra_args: Vec<String>,
+    // `--magic` value; None leaves it to oura's default (OURA_MAGIC=none)
+    magic: Option<String>,
+    // Chain point to start from instead of oura's default (OURA_RESUME)
+    resume_point: Option<Point>,
+    // Wait before respawning an exited child (OURA_RESTART_DELAY_SECS, default 5); zero
+    // disables restarts
+    restart_delay: Duration,
+    metrics: Arc<Metrics>,
+    status: OuraStatusHandle,
 }
 
 impl OuraReader {
     // Create a new OuraReader with the given Configuration
-    pub fn new(config: CardanoConfig) -> Self {
-        Self { config }
+    pub fn new(config: CardanoConfig, metrics: Arc<Metrics>) -> Self {
+        let oura_bin = std::env::var("OURA_BIN").unwrap_or_else(|_| "oura".to_string());
+        // The child is spawned directly, never through a shell, so these can only ever be oura
+        // arguments. Quotes aren't interpreted: an argument can't contain whitespace
+        let extra_args: Vec<String> = std::env::var("OURA_EXTRA_ARGS")
+            .map(|args| args.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        if extra_args.iter().any(|arg| arg.contains(['"', '\''])) {
+            warn!("OURA_EXTRA_ARGS contains quotes, which are passed to oura literally");
+        }
+        // A stale magic stalls the handshake without an error, so some relays are better off
+        // without one. OURA_MAGIC overrides the network's magic, or drops it with `none`
+        let magic = match std::env::var("OURA_MAGIC").ok().map(|m| m.trim().to_string()) {
+            Some(magic) if magic.eq_ignore_ascii_case("none") => None,
+            Some(magic) if !magic.is_empty() => Some(magic),
+            _ => Some(config.magic.to_string()),
+        };
+        match &magic {
+            Some(magic) => info!("Oura magic: {}", magic),
+            None => info!("Oura magic: none (oura uses its default)"),
+        }
+        Self {
+            config,
+            oura_bin,
+            extra_args,
+            magic,
+            resume_point: None,
+            restart_delay: Duration::from_secs(env_parse("OURA_RESTART_DELAY_SECS", 5)),
+            metrics,
+            status: OuraStatusHandle::default(),
+        }
+    }
+
+    // Shared view of the child process PID and restarts, for the admin endpoint
+    pub fn status(&self) -> OuraStatusHandle {
+        Arc::clone(&self.status)
     }
 
-    // Start reading evetnts from the Oura and send then throught the channel
+    // Resume `oura dump` from a previously processed point
+    pub fn with_resume_point(mut self, point: Option<Point>) -> Self {
+        if let Some(point) = &point {
+            info!("Resuming Oura from slot {} ({})", point.slot, point.hash);
+        }
+        self.resume_point = point;
+        self
+    }
+
+    // Start reading evetnts from the configured source and send then throught the channel
     pub async fn start(
         &self,
-        tx: broadcast::Sender<OuraEvent>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        tx: mpsc::Sender<OuraEvent>,
+    ) -> Result<(), OuraError> {
+        match &self.config.source {
+            EventSource::OuraSubprocess => self.start_subprocess(tx).await,
+            EventSource::Stdin => {
+                info!("Reading Oura events from stdin");
+                read_events(BufReader::new(tokio::io::stdin()), &tx, &self.metrics).await;
+                info!("Reached end of stdin");
+                Ok(())
+            }
+            EventSource::File(path) => {
+                info!("Reading Oura events from file: {}", path.display());
+                let file = tokio::fs::File::open(path).await?;
+                read_events(BufReader::new(file), &tx, &self.metrics).await;
+                info!("Reached end of file: {}", path.display());
+                Ok(())
+            }
+        }
+    }
+
+    // Spawn `oura dump` and read its stdout, respawning it restart_delay after it exits
+    async fn start_subprocess(
+        &self,
+        tx: mpsc::Sender<OuraEvent>,
+    ) -> Result<(), OuraError> {
+        let mut resume_point = self.resume_point.clone();
+
+        loop {
+            if let Some(last_point) = self.run_subprocess(&tx, resume_point.as_ref()).await? {
+                resume_point = Some(last_point);
+            }
+
+            if self.restart_delay.is_zero() {
+                return Ok(());
+            }
+            info!("Restarting Oura in {:?}...", self.restart_delay);
+            tokio::time::sleep(self.restart_delay).await;
+
+            let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+            status.restarts += 1;
+            status.last_restart = Some(chrono::Utc::now());
+        }
+    }
+
+    // Run one `oura dump` child until it exits; returns the last block/rollback point it emitted
+    async fn run_subprocess(
+        &self,
+        tx: &mpsc::Sender<OuraEvent>,
+        since: Option<&Point>,
+    ) -> Result<Option<Point>, OuraError> {
         info!("Starting Oura dump command...");
         info!("Network: {}", self.config.network_name);
-        info!("Connecting to: {}", self.config.relay);
+        match &self.config.bearer {
+            Bearer::Tcp => info!("Connecting to: {}", self.config.relay),
+            Bearer::Unix(path) => info!("Connecting to local node socket: {}", path.display()),
+        }
         info!("This may take a moment to connect to the Cardano Node...");
 
         // Spawn oura dump command with proper flags to only output JSON
-        let mut child = Command::new("oura")
-            .arg("dump")
-            .arg(self.config.relay)
-            .arg("--bearer")
-            .arg("tcp")
-            .arg("--magic")
-            .arg(self.config.magic)
+        let args = self.dump_args(since);
+        info!("Running: {} {}", self.oura_bin, args.join(" "));
+        let mut child = Command::new(&self.oura_bin)
+            .args(args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped()) // Capture stderr to log errors
             // spawn starts the process asynchronously
             // Returns a Child process handle (child) that can be used to read output or wait for the process to finish.
-            // -? propagates errors if starting the process fails.
-            .spawn()?;
+            .spawn()
+            .map_err(|e| {
+                if e.kind() == ErrorKind::NotFound {
+                    let err = OuraError::BinaryNotFound(self.oura_bin.clone());
+                    error!("❌ {}", err);
+                    err
+                } else {
+                    OuraError::Io(e)
+                }
+            })?;
+        self.set_running(child.id());
 
         // It takes the piped output to the terminal to the stdout and if it fails it panics with the message
         let stdout = child.stdout.take().expect("Failed to capture stdout");
@@ -56,39 +208,386 @@ impl OuraReader {
             }
         });
 
-        let reader = BufReader::new(stdout);
-        let mut lines = reader.lines();
+        let last_point = read_events(BufReader::new(stdout), tx, &self.metrics).await;
 
-        while let Ok(Some(line)) = lines.next_line().await {
-            // Skip empty lines and non-JSON lines
-            if line.trim().is_empty() || !line.trim().starts_with('{') {
-                continue;
-            }
+        // Wait for child process
+        let exit = child.wait().await;
+        self.set_running(None);
+        let status = exit?;
+        error!("Oura process exited with status: {}", status);
+
+        Ok(last_point)
+    }
+
+    fn set_running(&self, pid: Option<u32>) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.pid = pid;
+        status.connected = pid.is_some();
+    }
+
+    // Arguments for `oura dump`: the peer (relay address or socket path), bearer, network magic
+    // unless disabled, the intersection point when resuming, then OURA_EXTRA_ARGS in order
+    fn dump_args(&self, since: Option<&Point>) -> Vec<String> {
+        let (peer, bearer) = match &self.config.bearer {
+            Bearer::Tcp => (self.config.relay.to_string(), "tcp"),
+            Bearer::Unix(path) => (path.display().to_string(), "unix"),
+        };
+
+        let mut args = vec![
+            "dump".to_string(),
+            peer,
+            "--bearer".to_string(),
+            bearer.to_string(),
+        ];
+        if let Some(magic) = &self.magic {
+            args.push("--magic".to_string());
+            args.push(magic.clone());
+        }
+        if let Some(point) = since {
+            args.push("--since".to_string());
+            args.push(format!("{},{}", point.slot, point.hash));
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+// Parse newline-delimited JSON events from a reader and send them through the channel.
+// Returns the last block/rollback point read, to resume from after a restart
+async fn read_events<R: AsyncBufRead + Unpin>(
+    reader: R,
+    tx: &mpsc::Sender<OuraEvent>,
+    metrics: &Metrics,
+) -> Option<Point> {
+    let mut lines = reader.lines();
+    let mut sender = EventSender::new(tx, metrics);
 
-            // Parse Json Line
-            match serde_json::from_str::<OuraEvent>(&line) {
-                Ok(oura_event) => {
-                    // Send to channel for processing
-                    if let Err(e) = tx.send(oura_event) {
-                        // Channel is likely full or closed (no receivers)
-                        // This is normal when no WebSocket clients are connected
-                        warn!("Failed to send oura event (channel full/closed): {}", e);
+    while let Ok(Some(raw_line)) = lines.next_line().await {
+        // Tolerate a UTF-8 BOM and surrounding whitespace from some sink configurations
+        let line = raw_line.trim().trim_start_matches('\u{feff}').trim_start();
+
+        // Some sinks batch events into a JSON array; anything else that isn't an object is skipped
+        if line.starts_with('[') {
+            match serde_json::from_str::<Vec<serde_json::Value>>(line) {
+                Ok(elements) => {
+                    for element in elements {
+                        match serde_json::from_value::<OuraEvent>(element) {
+                            Ok(oura_event) => sender.send(oura_event).await,
+                            Err(e) => warn!("Failed to parse JSON array element: {}", e),
+                        }
                     }
                 }
-                Err(e) => {
-                    warn!(
-                        "Failed to parse JSON: {} - Line: {}",
-                        e,
-                        &line[..line.len().min(100)]
-                    );
-                }
+                Err(e) => warn!("Failed to parse JSON array: {} - Line: {}", e, preview(line)),
             }
+            continue;
+        }
+        if !line.starts_with('{') {
+            continue;
         }
 
-        // Wait for child process
-        let status = child.wait().await?;
-        error!("Oura process exited with status: {}", status);
+        // Parse Json Line
+        match serde_json::from_str::<OuraEvent>(line) {
+            Ok(oura_event) => sender.send(oura_event).await,
+            Err(e) => {
+                warn!("Failed to parse JSON: {} - Line: {}", e, preview(line));
+            }
+        }
+    }
 
-        Ok(())
+    sender.last_point
+}
+
+// Sends events to the processor, rate-limiting the warning for failed sends
+struct EventSender<'a> {
+    tx: &'a mpsc::Sender<OuraEvent>,
+    metrics: &'a Metrics,
+    last_warned: Option<Instant>,
+    // Failures since the last warning
+    suppressed: u64,
+    last_point: Option<Point>,
+}
+
+impl<'a> EventSender<'a> {
+    fn new(tx: &'a mpsc::Sender<OuraEvent>, metrics: &'a Metrics) -> Self {
+        Self {
+            tx,
+            metrics,
+            last_warned: None,
+            suppressed: 0,
+            last_point: None,
+        }
     }
-}
\ No newline at end of file
+
+    // Send to channel for processing, waiting while the processor is behind so a burst
+    // slows the reader down instead of dropping events
+    async fn send(&mut self, oura_event: OuraEvent) {
+        // Block and rollback points are valid intersections to resume from
+        if oura_event.record.block.is_some() || oura_event.record.roll_back.is_some() {
+            self.last_point = Some(oura_event.point.clone());
+        }
+
+        if self.tx.send(oura_event).await.is_ok() {
+            return;
+        }
+
+        // Channel is closed: the processor task has stopped
+        self.metrics.oura_send_failures.fetch_add(1, Ordering::Relaxed);
+        self.suppressed += 1;
+        if self
+            .last_warned
+            .is_none_or(|warned| warned.elapsed() >= SEND_FAILURE_WARN_INTERVAL)
+        {
+            warn!(
+                "Failed to send {} oura event(s): event processor has stopped",
+                self.suppressed
+            );
+            self.last_warned = Some(Instant::now());
+            self.suppressed = 0;
+        }
+    }
+}
+
+// First 100 characters of a line for parse-failure logs
+fn preview(line: &str) -> &str {
+    match line.char_indices().nth(100) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AppState;
+    use crate::services::EventProcessor;
+    use crate::websocket::protocol::Frame;
+    use tokio::sync::{broadcast, Mutex};
+
+    // A block and one of its transactions, as `oura dump` prints them
+    const SAMPLE_EVENTS: &str = r#"{"event":"apply","point":{"hash":"aa","slot":10},"record":{"context":{"block_hash":"aa","block_number":5,"slot":10,"timestamp":1700000000},"block":{"hash":"aa","number":5,"slot":10,"epoch":1,"epoch_slot":2,"era":"Babbage","body_size":3,"issuer_vkey":"bb","vrf_vkey":"cc","tx_count":1,"previous_hash":"dd"}}}
+{"event":"apply","point":{"hash":"aa","slot":10},"record":{"context":{"block_hash":"aa","block_number":5,"slot":10,"timestamp":1700000000,"tx_hash":"ee"},"transaction":{"hash":"ee","fee":170000,"size":300,"input_count":1,"output_count":2,"total_output":5000000,"mint_count":0,"collateral_input_count":0,"has_collateral_output":false}}}
+"#;
+
+    // `true` prints nothing and exits straight away, like an oura that can't reach its relay
+    fn exiting_reader(restart_delay: Duration) -> OuraReader {
+        let mut reader = OuraReader::new(CardanoConfig::preprod(), Arc::new(Metrics::new(8, 8)));
+        reader.oura_bin = "true".to_string();
+        reader.restart_delay = restart_delay;
+        reader
+    }
+
+    // Reader for `bearer` with the preprod magic and no OURA_EXTRA_ARGS, whatever the environment
+    fn reader_for(bearer: Bearer) -> OuraReader {
+        let mut config = CardanoConfig::preprod();
+        config.bearer = bearer;
+        let mut reader = OuraReader::new(config, Arc::new(Metrics::new(8, 8)));
+        reader.magic = Some("pre-prod".to_string());
+        reader.extra_args = Vec::new();
+        reader
+    }
+
+    #[test]
+    fn dump_args_for_each_bearer() {
+        assert_eq!(
+            reader_for(Bearer::Tcp).dump_args(None),
+            ["dump", "preprod-node.world.dev.cardano.org:30000", "--bearer", "tcp", "--magic", "pre-prod"]
+        );
+        assert_eq!(
+            reader_for(Bearer::Unix("/ipc/node.socket".into())).dump_args(None),
+            ["dump", "/ipc/node.socket", "--bearer", "unix", "--magic", "pre-prod"]
+        );
+    }
+
+    #[test]
+    fn no_magic_leaves_the_flag_off_the_command_line() {
+        let mut reader = reader_for(Bearer::Tcp);
+        reader.magic = None;
+        let since = Point { hash: "ab".repeat(32), slot: 42 };
+
+        assert_eq!(reader.dump_args(None), ["dump", "preprod-node.world.dev.cardano.org:30000", "--bearer", "tcp"]);
+        assert!(!reader.dump_args(Some(&since)).contains(&"--magic".to_string()));
+    }
+
+    #[test]
+    fn extra_args_come_last_in_order() {
+        let mut reader = reader_for(Bearer::Tcp);
+        reader.extra_args = ["--mode", "node", "--throttle", "10"].map(String::from).to_vec();
+        let since = Point { hash: "ab".repeat(32), slot: 42 };
+
+        assert_eq!(
+            reader.dump_args(Some(&since)),
+            [
+                "dump", "preprod-node.world.dev.cardano.org:30000", "--bearer", "tcp", "--magic", "pre-prod",
+                "--since", &format!("42,{}", "ab".repeat(32)), "--mode", "node", "--throttle", "10"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn file_source_events_reach_the_broadcast_channel() {
+        let path = std::env::temp_dir().join(format!("oura-sample-{}.jsonl", std::process::id()));
+        tokio::fs::write(&path, SAMPLE_EVENTS).await.unwrap();
+        let mut config = CardanoConfig::preprod();
+        config.source = EventSource::File(path.clone());
+        let reader = OuraReader::new(config, Arc::new(Metrics::new(8, 8)));
+        let processor = EventProcessor::new(Arc::new(Mutex::new(AppState::new(10))));
+        let (tx, mut rx) = mpsc::channel(8);
+        let (ws_tx, mut ws_rx) = broadcast::channel(16);
+
+        let read = reader.start(tx).await;
+        tokio::fs::remove_file(&path).await.unwrap();
+        read.unwrap();
+        while let Some(oura_event) = rx.recv().await {
+            processor.process_event(oura_event, &ws_tx).await.unwrap();
+        }
+
+        assert_eq!(
+            streamed(&mut ws_rx),
+            [("Block".into(), "aa".into()), ("Transaction".into(), "ee".into())]
+        );
+    }
+
+    // Type and hash of every broadcast event frame so far, leaving out stats
+    fn streamed(ws_rx: &mut broadcast::Receiver<Frame>) -> Vec<(serde_json::Value, serde_json::Value)> {
+        let mut streamed = Vec::new();
+        while let Ok(frame) = ws_rx.try_recv() {
+            let frame: serde_json::Value = serde_json::from_str(frame.json()).unwrap();
+            if frame["type"] != "stats" {
+                streamed.push((frame["type"].clone(), frame["hash"].clone()));
+            }
+        }
+        streamed
+    }
+
+    #[tokio::test]
+    async fn array_wrapped_batch_is_broadcast_event_by_event() {
+        let [block, transaction] = SAMPLE_EVENTS.lines().collect::<Vec<_>>()[..] else {
+            panic!("SAMPLE_EVENTS holds a block and a transaction");
+        };
+        // A BOM-prefixed batch, then a lone event with leading whitespace
+        let input = format!("\u{feff}[{},{}]\n  {}\n", block, transaction, transaction.replace("\"ee\"", "\"ff\""));
+        let processor = EventProcessor::new(Arc::new(Mutex::new(AppState::new(10))));
+        let (tx, mut rx) = mpsc::channel(8);
+        let (ws_tx, mut ws_rx) = broadcast::channel(16);
+
+        let last_point = read_events(input.as_bytes(), &tx, &Metrics::new(8, 8)).await;
+        drop(tx);
+        while let Some(oura_event) = rx.recv().await {
+            processor.process_event(oura_event, &ws_tx).await.unwrap();
+        }
+
+        assert_eq!(last_point.map(|point| point.slot), Some(10));
+        assert_eq!(
+            streamed(&mut ws_rx),
+            [
+                ("Block".into(), "aa".into()),
+                ("Transaction".into(), "ee".into()),
+                ("Transaction".into(), "ff".into())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn burst_through_a_small_channel_arrives_whole_and_in_order() {
+        let transaction = SAMPLE_EVENTS.lines().nth(1).unwrap();
+        let burst: String = (0..500)
+            .map(|i| transaction.replace("\"ee\"", &format!("\"{:04}\"", i)) + "\n")
+            .collect();
+        let metrics = Metrics::new(8, 4);
+        // Far smaller than the burst, so the reader has to wait for the processor
+        let (tx, mut rx) = mpsc::channel::<OuraEvent>(4);
+
+        let consumer = tokio::spawn(async move {
+            let mut hashes = Vec::new();
+            while let Some(oura_event) = rx.recv().await {
+                hashes.push(oura_event.record.transaction.unwrap().hash);
+                tokio::task::yield_now().await;
+            }
+            hashes
+        });
+        read_events(burst.as_bytes(), &tx, &metrics).await;
+        drop(tx);
+
+        let expected: Vec<_> = (0..500).map(|i| format!("{:04}", i)).collect();
+        assert_eq!(consumer.await.unwrap(), expected);
+        assert_eq!(metrics.oura_send_failures.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn restart_count_increments_after_the_child_exits() {
+        let reader = Arc::new(exiting_reader(Duration::from_millis(10)));
+        let status = reader.status();
+        let (tx, _rx) = mpsc::channel(8);
+
+        let task = tokio::spawn({
+            let reader = Arc::clone(&reader);
+            async move { reader.start(tx).await }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while status.lock().unwrap().restarts < 2 {
+            assert!(Instant::now() < deadline, "oura was not restarted: {:?}", status.lock().unwrap());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        task.abort();
+
+        let status = status.lock().unwrap();
+        assert!(status.last_restart.is_some());
+    }
+
+    #[tokio::test]
+    async fn zero_restart_delay_runs_the_child_once() {
+        let reader = exiting_reader(Duration::ZERO);
+        let (tx, _rx) = mpsc::channel(8);
+
+        reader.start(tx).await.unwrap();
+
+        let status = reader.status();
+        let status = status.lock().unwrap();
+        assert_eq!(status.restarts, 0);
+        assert_eq!((status.pid, status.connected), (None, false));
+    }
+
+    // Log output shared with the test, for counting warnings
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_send_failures_are_warned_about_once_per_interval() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt().with_ansi(false).with_writer(move || writer.clone()).finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let oura_event: OuraEvent = serde_json::from_str(SAMPLE_EVENTS.lines().next().unwrap()).unwrap();
+        let metrics = Metrics::new(8, 8);
+        // The processor has stopped: every send fails
+        let (tx, rx) = mpsc::channel(8);
+        drop(rx);
+        let mut sender = EventSender::new(&tx, &metrics);
+
+        for _ in 0..100 {
+            sender.send(oura_event.clone()).await;
+        }
+        // Once the interval has passed, the next failure reports the ones held back
+        sender.last_warned = sender.last_warned.map(|warned| warned - SEND_FAILURE_WARN_INTERVAL);
+        sender.send(oura_event).await;
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let warnings: Vec<_> = logs.lines().filter(|line| line.contains("Failed to send")).collect();
+        assert_eq!(warnings.len(), 2, "{}", logs);
+        assert!(warnings[0].contains("Failed to send 1 oura event(s)"), "{}", warnings[0]);
+        assert!(warnings[1].contains("Failed to send 100 oura event(s)"), "{}", warnings[1]);
+        assert_eq!(metrics.oura_send_failures.load(Ordering::Relaxed), 101);
+    }
+}
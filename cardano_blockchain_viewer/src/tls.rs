@@ -0,0 +1,101 @@
+// Optional HTTPS (and wss://) termination with rustls
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+use tracing::{debug, warn};
+
+/// TLS settings from `TLS_CERT_PATH` and `TLS_KEY_PATH` (PEM files).
+/// None when they aren't both set, in which case the server speaks plain HTTP
+pub fn config_from_env() -> Result<Option<Arc<ServerConfig>>, String> {
+    let cert_path = std::env::var("TLS_CERT_PATH").ok().filter(|p| !p.is_empty());
+    let key_path = std::env::var("TLS_KEY_PATH").ok().filter(|p| !p.is_empty());
+
+    let (cert_path, key_path) = match (cert_path, key_path) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) => {
+            warn!("⚠️  TLS_CERT_PATH/TLS_KEY_PATH not set, serving plain HTTP (put a TLS proxy in front when exposed)");
+            return Ok(None);
+        }
+        (Some(_), None) | (None, Some(_)) => {
+            warn!("⚠️  Only one of TLS_CERT_PATH/TLS_KEY_PATH is set, serving plain HTTP");
+            return Ok(None);
+        }
+    };
+
+    load_config(&cert_path, &key_path).map(Some)
+}
+
+/// Server config for a PEM certificate chain and private key, offering h2 and http/1.1
+pub fn load_config(cert_path: &str, key_path: &str) -> Result<Arc<ServerConfig>, String> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(open(cert_path)?))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid certificate in {}: {}", cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificate found in {}", cert_path));
+    }
+    let key = rustls_pemfile::private_key(&mut BufReader::new(open(key_path)?))
+        .map_err(|e| format!("Invalid private key in {}: {}", key_path, e))?
+        .ok_or_else(|| format!("No private key found in {}", key_path))?;
+
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Certificate and key don't form a valid TLS config: {}", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn open(path: &str) -> Result<std::fs::File, String> {
+    std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))
+}
+
+/// Accept TLS connections and serve the router on each, keeping HTTP upgrades (WebSockets) working
+pub async fn serve(listener: TcpListener, router: Router, config: Arc<ServerConfig>) -> std::io::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            // e.g. too many open files; keep accepting once it clears
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        // What into_make_service_with_connect_info does for plain HTTP, so ClientIp sees the peer
+        let router = router.clone();
+        let service = hyper::service::service_fn(
+            move |mut request: axum::http::Request<hyper::body::Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(peer));
+                router.clone().call(request)
+            },
+        );
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                // Scanners and plain-HTTP clients end up here
+                Err(e) => {
+                    debug!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Connection with {} ended with error: {}", peer, e);
+            }
+        });
+    }
+}
@@ -1,33 +1,147 @@
 use axum::{
-    extract::{ws::WebSocket, State, WebSocketUpgrade},
-    response::Response,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
 use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
-use tracing::{error, info};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
 
-use crate::models::AppState;
+use crate::api::extract::ApiQuery;
+use crate::auth::JwtManager;
+use crate::models::{AppState, Metrics};
+use super::protocol::{self, Frame, FrameFormat};
+use super::watch::WatchList;
 
 #[derive(Clone)]
 pub struct WebSocketState {
     pub app_state: Arc<Mutex<AppState>>,
-    pub ws_tx: broadcast::Sender<String>,
+    pub ws_tx: broadcast::Sender<Frame>,
+    pub jwt_manager: Arc<JwtManager>,
+    pub metrics: Arc<Metrics>,
+    // When true (WS_REQUIRE_AUTH), upgrades need a valid JWT
+    pub require_auth: bool,
+    // Open sockets, capped at max_connections (WS_MAX_CONNECTIONS)
+    pub active_connections: Arc<AtomicUsize>,
+    pub max_connections: usize,
+    // Close connections with no inbound or outbound frame for this long (WS_IDLE_TIMEOUT_SECS)
+    pub idle_timeout: Option<Duration>,
+}
+
+// Holds one of the max_connections slots; released when the socket closes or the upgrade fails
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn acquire(active: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < max).then_some(n + 1))
+            .ok()
+            .map(|_| Self(Arc::clone(active)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebSocketQuery {
+    pub token: Option<String>,
+    // Replay only the newest N buffered events on connect (0 = live only); the whole buffer when absent
+    pub replay: Option<usize>,
+    // json (default) or msgpack
+    #[serde(default)]
+    pub format: FrameFormat,
 }
 
 pub async fn websocket_handler(
-    ws: WebSocketUpgrade,
+    mut ws: WebSocketUpgrade,
     State(state): State<WebSocketState>,
+    ApiQuery(query): ApiQuery<WebSocketQuery>,
+    headers: HeaderMap,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    if state.require_auth {
+        // Browsers can't set an Authorization header on a WebSocket, so the token comes
+        // either as ?token=... or as one of the Sec-WebSocket-Protocol values
+        if let Some(token) = query.token.as_deref() {
+            if let Err(e) = state.jwt_manager.validate_token(token) {
+                return unauthorized(&e);
+            }
+        } else {
+            let protocol_token = headers
+                .get("sec-websocket-protocol")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|protocols| {
+                    protocols
+                        .split(',')
+                        .map(str::trim)
+                        .find(|p| state.jwt_manager.validate_token(p).is_ok())
+                        .map(str::to_string)
+                });
+
+            match protocol_token {
+                // The handshake must echo the accepted protocol back to the client
+                Some(token) => ws = ws.protocols([token]),
+                None => return unauthorized("Missing authorization token"),
+            }
+        }
+    }
+
+    let Some(slot) = ConnectionSlot::acquire(&state.active_connections, state.max_connections) else {
+        warn!("Rejecting WebSocket upgrade: {} connections open", state.max_connections);
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Too many WebSocket connections, try again later" })),
+        )
+            .into_response();
+    };
+
+    let replay = query.replay;
+    let format = query.format;
+    ws.on_upgrade(move |socket| async move {
+        handle_socket(socket, state, replay, format).await;
+        drop(slot);
+    })
 }
 
-async fn handle_socket(socket: WebSocket, state: WebSocketState) {
+fn unauthorized(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": message })),
+    )
+        .into_response()
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    state: WebSocketState,
+    replay: Option<usize>,
+    format: FrameFormat,
+) {
     let addr = "client"; // Axum doesn't provide peer addr in websocket upgrade
     info!("New WebSocket connection from: {}", addr);
 
     let (mut ws_sender, mut ws_receiver) = socket.split();
     let mut rx = state.ws_tx.subscribe();
+    let metrics = Arc::clone(&state.metrics);
+    let watch_list = WatchList::default();
+    // Replies to client messages, sent from the broadcast task so frames never interleave
+    let (reply_tx, mut reply_rx) = mpsc::channel::<String>(16);
+    // Last inbound frame, so the send task's idle timer also sees client activity
+    let last_received = Arc::new(std::sync::Mutex::new(Instant::now()));
+    let idle_timeout = state.idle_timeout;
 
     // Send current buffer to new client
     {
@@ -39,41 +153,93 @@ async fn handle_socket(socket: WebSocket, state: WebSocketState) {
             "type": "stats",
             "data": stats
         });
-        if let Ok(msg) = serde_json::to_string(&stats_msg) {
-            let _ = ws_sender
-                .send(axum::extract::ws::Message::Text(msg))
-                .await;
-        }
-
-        // Send buffered events
-        for event in &app_state.buffer {
-            if let Ok(json) = serde_json::to_string(&event) {
-                let _ = ws_sender
-                    .send(axum::extract::ws::Message::Text(json))
-                    .await;
+        if let Ok(msg) = protocol::encode(&stats_msg) {
+            let _ = ws_sender.send(Frame::from(msg).to_message(format)).await;
+        }
+
+        // Send the newest `replay` buffered events, oldest first
+        let buffered = app_state.buffer.len();
+        let skip = buffered.saturating_sub(replay.unwrap_or(buffered));
+        for event in app_state.buffer.iter().skip(skip) {
+            if let Ok(json) = protocol::encode(event) {
+                let _ = ws_sender.send(Frame::from(json).to_message(format)).await;
             }
         }
     }
 
     // Spawn task to send broadcasts to this client
+    let send_watch_list = watch_list.clone();
+    let send_last_received = Arc::clone(&last_received);
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if ws_sender
-                .send(axum::extract::ws::Message::Text(msg))
-                .await
-                .is_err()
-            {
-                break;
+        let mut last_sent = Instant::now();
+        loop {
+            let last_activity = last_sent.max(*send_last_received.lock().unwrap());
+            let idle = async {
+                match idle_timeout {
+                    Some(timeout) => tokio::time::sleep_until(last_activity + timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let msg = tokio::select! {
+                received = rx.recv() => match received {
+                    Ok(msg) => msg,
+                    // Slow client: the channel overwrote messages it hadn't read yet.
+                    // Tell it how many it missed and keep streaming instead of disconnecting.
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket client {} lagged, skipped {} messages", addr, skipped);
+                        metrics.ws_lag_events.fetch_add(1, Ordering::Relaxed);
+                        metrics.ws_lagged_messages.fetch_add(skipped, Ordering::Relaxed);
+                        protocol::encode(&serde_json::json!({ "type": "lag", "skipped": skipped }))
+                            .unwrap_or_default()
+                            .into()
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Some(reply) = reply_rx.recv() => reply.into(),
+                _ = idle => {
+                    // The client may have sent something while the timer ran; re-arm from it
+                    if *send_last_received.lock().unwrap() > last_activity {
+                        continue;
+                    }
+                    info!("Closing idle WebSocket client {}", addr);
+                    let _ = ws_sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::NORMAL,
+                            reason: "Idle timeout".into(),
+                        })))
+                        .await;
+                    return;
+                }
+            };
+
+            let watch_hit = send_watch_list.hit(msg.json()).map(Frame::from);
+            for frame in std::iter::once(msg).chain(watch_hit) {
+                if ws_sender
+                    .send(frame.to_message(format))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
             }
+            last_sent = Instant::now();
         }
     });
 
-    // Handle incoming messages (ping/pong)
+    // Handle incoming messages (ping/pong and watch requests)
     let mut recv_task = tokio::spawn(async move {
         while let Some(msg) = ws_receiver.next().await {
+            *last_received.lock().unwrap() = Instant::now();
             match msg {
-                Ok(axum::extract::ws::Message::Close(_)) => break,
-                Ok(axum::extract::ws::Message::Ping(_)) => {
+                Ok(Message::Close(_)) => break,
+                Ok(Message::Text(text)) => {
+                    let reply = watch_list.handle(&text);
+                    if reply_tx.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(Message::Ping(_)) => {
                     info!("Received ping from {}", addr);
                 }
                 Err(e) => {
@@ -97,3 +263,277 @@ async fn handle_socket(socket: WebSocket, state: WebSocketState) {
 
     info!("WebSocket connection closed: {}", addr);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockchainEvent;
+    use crate::websocket::msgpack;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+    type Client = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+    fn ws_state() -> WebSocketState {
+        WebSocketState {
+            app_state: Arc::new(Mutex::new(AppState::new(10))),
+            ws_tx: broadcast::channel(16).0,
+            jwt_manager: Arc::new(JwtManager::new(
+                "test-secret".to_string(),
+                "issuer".to_string(),
+                "audience".to_string(),
+            )),
+            metrics: Arc::new(Metrics::new(16, 16)),
+            require_auth: false,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+            max_connections: 10,
+            idle_timeout: None,
+        }
+    }
+
+    // Serve /ws on an ephemeral port and return its ws:// origin
+    async fn serve(state: WebSocketState) -> String {
+        let app = Router::new().route("/ws", get(websocket_handler)).with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        origin
+    }
+
+    async fn connect(url: &str) -> Client {
+        tokio_tungstenite::connect_async(url).await.unwrap().0
+    }
+
+    async fn next_frame(client: &mut Client) -> ClientMessage {
+        tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("no frame within 5s")
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn msgpack_client_decodes_a_broadcast_back_into_the_event() {
+        let state = ws_state();
+        let ws_tx = state.ws_tx.clone();
+        let origin = serve(state).await;
+        let mut binary = connect(&format!("{}/ws?format=msgpack", origin)).await;
+        let mut text = connect(&format!("{}/ws", origin)).await;
+
+        // Both start with the stats frame, in their own encoding. Connections subscribe to the
+        // broadcast before sending it, so neither can miss the event below
+        match next_frame(&mut binary).await {
+            ClientMessage::Binary(bytes) => assert_eq!(msgpack::decode(&bytes)["type"], "stats"),
+            other => panic!("expected a binary stats frame, got {:?}", other),
+        }
+        assert!(matches!(next_frame(&mut text).await, ClientMessage::Text(_)));
+
+        let event = BlockchainEvent::TxOutput {
+            tx_hash: "ab".repeat(32),
+            slot: 123_456,
+            address: "addr_test1vz".to_string(),
+            amount: 5_000_000,
+            timestamp: 1_700_000_000,
+            tx_idx: Some(0),
+            output_idx: None,
+        };
+        ws_tx.send(protocol::encode(&event).unwrap().into()).unwrap();
+
+        let ClientMessage::Binary(bytes) = next_frame(&mut binary).await else {
+            panic!("msgpack clients get binary frames");
+        };
+        let mut decoded = msgpack::decode(&bytes);
+        assert_eq!(decoded.as_object_mut().unwrap().remove("v"), Some(protocol::PROTOCOL_VERSION.into()));
+        assert_eq!(serde_json::from_value::<BlockchainEvent>(decoded).unwrap(), event);
+
+        // The default stays JSON text
+        let ClientMessage::Text(json) = next_frame(&mut text).await else {
+            panic!("JSON clients get text frames");
+        };
+        let decoded: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded["type"], "TxOutput");
+        assert_eq!(decoded["amount"], 5_000_000);
+    }
+
+    fn text(message: ClientMessage) -> serde_json::Value {
+        match message {
+            ClientMessage::Text(json) => serde_json::from_str(&json).unwrap(),
+            other => panic!("expected a text frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_client_gets_a_lag_notice_and_stays_connected() {
+        let mut state = ws_state();
+        state.ws_tx = broadcast::channel(2).0;
+        let ws_tx = state.ws_tx.clone();
+        let origin = serve(state).await;
+        let mut client = connect(&format!("{}/ws", origin)).await;
+        assert_eq!(text(next_frame(&mut client).await)["type"], "stats");
+
+        // The test runtime is single-threaded: the connection's send task can't read any of
+        // these until the loop is done, so the two-slot channel overwrites all but the last two
+        for n in 0..10 {
+            let frame = protocol::encode(&serde_json::json!({ "type": "stats", "n": n })).unwrap();
+            ws_tx.send(frame.into()).unwrap();
+        }
+
+        assert_eq!(text(next_frame(&mut client).await), serde_json::json!({ "v": 1, "type": "lag", "skipped": 8 }));
+        assert_eq!(text(next_frame(&mut client).await)["n"], 8);
+        assert_eq!(text(next_frame(&mut client).await)["n"], 9);
+    }
+
+    #[tokio::test]
+    async fn upgrade_without_a_token_is_rejected_when_auth_is_required() {
+        let mut state = ws_state();
+        state.require_auth = true;
+        let token = state.jwt_manager.generate_token("addr_test1vz".to_string(), None).unwrap();
+        let origin = serve(state).await;
+
+        for url in [format!("{}/ws", origin), format!("{}/ws?token=not-a-jwt", origin)] {
+            match tokio_tungstenite::connect_async(url.as_str()).await {
+                Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", url)
+                }
+                other => panic!("expected {} to be refused, got {:?}", url, other.map(|_| ())),
+            }
+        }
+
+        let mut client = connect(&format!("{}/ws?token={}", origin, token)).await;
+        assert_eq!(text(next_frame(&mut client).await)["type"], "stats");
+    }
+
+    #[tokio::test]
+    async fn replay_limits_the_buffered_events_sent_on_connect() {
+        let state = ws_state();
+        {
+            let mut app_state = state.app_state.lock().await;
+            for slot in [1, 2, 3] {
+                let output = BlockchainEvent::TxOutput {
+                    tx_hash: "ab".repeat(32),
+                    slot,
+                    address: "addr_test1vz".to_string(),
+                    amount: 1_000_000,
+                    timestamp: 0,
+                    tx_idx: None,
+                    output_idx: None,
+                };
+                app_state.add_event(output, 10);
+            }
+        }
+        let ws_tx = state.ws_tx.clone();
+        let origin = serve(state).await;
+        let mut none = connect(&format!("{}/ws?replay=0", origin)).await;
+        let mut newest = connect(&format!("{}/ws?replay=1", origin)).await;
+        assert_eq!(text(next_frame(&mut none).await)["type"], "stats");
+        assert_eq!(text(next_frame(&mut newest).await)["type"], "stats");
+
+        let live = protocol::encode(&serde_json::json!({ "type": "stats", "live": true })).unwrap();
+        ws_tx.send(live.into()).unwrap();
+
+        // Nothing historical precedes the live frame with replay=0
+        assert_eq!(text(next_frame(&mut none).await)["live"], true);
+        assert_eq!(text(next_frame(&mut newest).await)["slot"], 3);
+        assert_eq!(text(next_frame(&mut newest).await)["live"], true);
+    }
+
+    #[tokio::test]
+    async fn connection_past_the_limit_is_refused_until_one_closes() {
+        let mut state = ws_state();
+        state.max_connections = 2;
+        let active_connections = Arc::clone(&state.active_connections);
+        let origin = serve(state).await;
+        let url = format!("{}/ws", origin);
+        let mut first = connect(&url).await;
+        let _second = connect(&url).await;
+
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE)
+            }
+            other => panic!("expected the third connection to be refused, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(active_connections.load(Ordering::SeqCst), 2);
+
+        // Closing a connection frees its slot once the server side has wound down
+        first.close(None).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while active_connections.load(Ordering::SeqCst) > 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("slot not released within 5s");
+        let mut third = connect(&url).await;
+        assert_eq!(text(next_frame(&mut third).await)["type"], "stats");
+    }
+
+    #[tokio::test]
+    async fn watch_hit_goes_to_the_watching_client_only() {
+        let state = ws_state();
+        let ws_tx = state.ws_tx.clone();
+        let origin = serve(state).await;
+        let address = crate::test_support::bech32(&crate::test_support::enterprise_address(0, 1));
+        let mut watcher = connect(&format!("{}/ws", origin)).await;
+        let mut bystander = connect(&format!("{}/ws", origin)).await;
+        assert_eq!(text(next_frame(&mut watcher).await)["type"], "stats");
+        assert_eq!(text(next_frame(&mut bystander).await)["type"], "stats");
+
+        let watch = serde_json::json!({ "action": "watch", "address": address }).to_string();
+        watcher.send(ClientMessage::Text(watch)).await.unwrap();
+        assert_eq!(text(next_frame(&mut watcher).await), serde_json::json!({ "v": 1, "type": "watching", "addresses": [address] }));
+
+        let output = BlockchainEvent::TxOutput {
+            tx_hash: "ab".repeat(32),
+            slot: 123_456,
+            address: address.clone(),
+            amount: 5_000_000,
+            timestamp: 1_700_000_000,
+            tx_idx: Some(0),
+            output_idx: Some(1),
+        };
+        ws_tx.send(protocol::encode(&output).unwrap().into()).unwrap();
+        // Marks the end of what the output produced for each client
+        ws_tx.send(protocol::encode(&serde_json::json!({ "type": "stats", "n": 1 })).unwrap().into()).unwrap();
+
+        assert_eq!(text(next_frame(&mut watcher).await)["type"], "TxOutput");
+        let hit = text(next_frame(&mut watcher).await);
+        assert_eq!((hit["type"].as_str(), hit["address"].as_str()), (Some("watch_hit"), Some(address.as_str())));
+        assert_eq!((hit["slot"].clone(), hit["amount"].clone()), (serde_json::json!(123_456), serde_json::json!(5_000_000)));
+        assert_eq!(text(next_frame(&mut watcher).await)["n"], 1);
+
+        assert_eq!(text(next_frame(&mut bystander).await)["type"], "TxOutput");
+        assert_eq!(text(next_frame(&mut bystander).await)["n"], 1);
+    }
+
+    #[tokio::test]
+    async fn idle_connection_is_closed_after_the_timeout() {
+        let mut state = ws_state();
+        state.idle_timeout = Some(Duration::from_millis(300));
+        let origin = serve(state).await;
+        let mut idle = connect(&format!("{}/ws", origin)).await;
+        let mut active = connect(&format!("{}/ws", origin)).await;
+        assert_eq!(text(next_frame(&mut idle).await)["type"], "stats");
+        assert_eq!(text(next_frame(&mut active).await)["type"], "stats");
+        let connected = Instant::now();
+
+        // Inbound traffic alone keeps the other connection open past the timeout
+        let keep_active = async {
+            for _ in 0..6 {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                active.send(ClientMessage::Ping(Vec::new())).await.unwrap();
+            }
+        };
+        let (_, closed) = tokio::join!(keep_active, next_frame(&mut idle));
+
+        match closed {
+            ClientMessage::Close(Some(frame)) => assert_eq!(frame.reason, "Idle timeout"),
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+        assert!(connected.elapsed() >= Duration::from_millis(300));
+        while let Ok(Some(frame)) = tokio::time::timeout(Duration::from_millis(50), active.next()).await {
+            assert!(!matches!(frame.unwrap(), ClientMessage::Close(_)), "the active client was closed");
+        }
+    }
+}
@@ -1,6 +1,8 @@
 // WebSocket module - handles client connections
 
 pub mod axum_handler;
-pub mod handler;
+pub mod msgpack;
+pub mod protocol;
+pub mod watch;
 
 pub use axum_handler::{websocket_handler, WebSocketState};
@@ -0,0 +1,213 @@
+// Minimal MessagePack encoder for `?format=msgpack` clients
+//
+// Frames are built as JSON first (protocol::encode), so encoding a serde_json::Value covers
+// every message shape: null, bool, integers, f64, strings, arrays and string-keyed maps.
+// Integers use the smallest MessagePack int type that fits, as rmp-serde does.
+
+use serde_json::Value;
+
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(number) => {
+            if let Some(n) = number.as_u64() {
+                write_uint(out, n);
+            } else if let Some(n) = number.as_i64() {
+                write_int(out, n);
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&number.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(s) => write_str(out, s),
+        Value::Array(items) => {
+            write_len(out, items.len(), 0x90, 0xdc, 0xdd);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(map) => {
+            write_len(out, map.len(), 0x80, 0xde, 0xdf);
+            for (key, item) in map {
+                write_str(out, key);
+                write_value(out, item);
+            }
+        }
+    }
+}
+
+fn write_uint(out: &mut Vec<u8>, n: u64) {
+    if n < 0x80 {
+        out.push(n as u8);
+    } else if n <= u8::MAX as u64 {
+        out.extend_from_slice(&[0xcc, n as u8]);
+    } else if n <= u16::MAX as u64 {
+        out.push(0xcd);
+        out.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        out.push(0xce);
+        out.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+// Only called for negative values; non-negative ones go through write_uint
+fn write_int(out: &mut Vec<u8>, n: i64) {
+    if n >= -32 {
+        out.push(n as i8 as u8);
+    } else if n >= i8::MIN as i64 {
+        out.extend_from_slice(&[0xd0, n as i8 as u8]);
+    } else if n >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(n as i16).to_be_bytes());
+    } else if n >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(n as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        out.push(0xa0 | len as u8);
+    } else if len <= u8::MAX as usize {
+        out.extend_from_slice(&[0xd9, len as u8]);
+    } else if len <= u16::MAX as usize {
+        out.push(0xda);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(0xdb);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+// Array and map headers: a fix-size marker for up to 15 entries, then 16- and 32-bit lengths
+fn write_len(out: &mut Vec<u8>, len: usize, fix: u8, marker16: u8, marker32: u8) {
+    if len < 16 {
+        out.push(fix | len as u8);
+    } else if len <= u16::MAX as usize {
+        out.push(marker16);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(marker32);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+// Reads back what to_vec writes, so tests can check frames without a MessagePack dependency
+#[cfg(test)]
+pub fn decode(bytes: &[u8]) -> Value {
+    fn take<'a>(bytes: &mut &'a [u8], n: usize) -> &'a [u8] {
+        let (head, rest) = bytes.split_at(n);
+        *bytes = rest;
+        head
+    }
+    fn be(bytes: &mut &[u8], n: usize) -> u64 {
+        take(bytes, n).iter().fold(0, |acc, b| acc << 8 | *b as u64)
+    }
+    fn read(bytes: &mut &[u8]) -> Value {
+        let marker = take(bytes, 1)[0];
+        let string = |bytes: &mut &[u8], len: usize| {
+            Value::String(String::from_utf8(take(bytes, len).to_vec()).unwrap())
+        };
+        let array = |bytes: &mut &[u8], len: usize| Value::Array((0..len).map(|_| read(bytes)).collect());
+        let map = |bytes: &mut &[u8], len: usize| {
+            Value::Object(
+                (0..len)
+                    .map(|_| match read(bytes) {
+                        Value::String(key) => (key, read(bytes)),
+                        key => panic!("non-string map key {}", key),
+                    })
+                    .collect(),
+            )
+        };
+        match marker {
+            0x00..=0x7f => Value::from(marker),
+            0x80..=0x8f => map(bytes, (marker & 0x0f) as usize),
+            0x90..=0x9f => array(bytes, (marker & 0x0f) as usize),
+            0xa0..=0xbf => string(bytes, (marker & 0x1f) as usize),
+            0xc0 => Value::Null,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xcb => Value::from(f64::from_bits(be(bytes, 8))),
+            0xcc => Value::from(be(bytes, 1)),
+            0xcd => Value::from(be(bytes, 2)),
+            0xce => Value::from(be(bytes, 4)),
+            0xcf => Value::from(be(bytes, 8)),
+            0xd0 => Value::from(be(bytes, 1) as u8 as i8),
+            0xd1 => Value::from(be(bytes, 2) as u16 as i16),
+            0xd2 => Value::from(be(bytes, 4) as u32 as i32),
+            0xd3 => Value::from(be(bytes, 8) as i64),
+            // str8/16/32, then array16/32 and map16/32
+            0xd9..=0xdb => {
+                let len = be(bytes, 1 << (marker - 0xd9)) as usize;
+                string(bytes, len)
+            }
+            0xdc | 0xdd => {
+                let len = be(bytes, 2 << (marker - 0xdc)) as usize;
+                array(bytes, len)
+            }
+            0xde | 0xdf => {
+                let len = be(bytes, 2 << (marker - 0xde)) as usize;
+                map(bytes, len)
+            }
+            0xe0..=0xff => Value::from(marker as i8),
+            other => panic!("unexpected MessagePack marker {:#04x}", other),
+        }
+    }
+
+    let mut bytes = bytes;
+    let value = read(&mut bytes);
+    assert!(bytes.is_empty(), "{} trailing bytes", bytes.len());
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn uses_the_smallest_encoding_from_the_spec() {
+        assert_eq!(to_vec(&json!({ "a": 1 })), [0x81, 0xa1, b'a', 0x01]);
+        assert_eq!(to_vec(&json!(-1)), [0xff]);
+        assert_eq!(to_vec(&json!(-33)), [0xd0, 0xdf]);
+        assert_eq!(to_vec(&json!(200)), [0xcc, 0xc8]);
+        assert_eq!(to_vec(&json!(300)), [0xcd, 0x01, 0x2c]);
+        assert_eq!(to_vec(&json!(-200)), [0xd1, 0xff, 0x38]);
+        assert_eq!(to_vec(&json!(1_700_000_000u64)), [0xce, 0x65, 0x53, 0xf1, 0x00]);
+        assert_eq!(to_vec(&json!([true, null])), [0x92, 0xc3, 0xc0]);
+        assert_eq!(&to_vec(&json!("x".repeat(40)))[..2], [0xd9, 40]);
+    }
+
+    #[test]
+    fn round_trips_every_value_kind() {
+        let value = json!({
+            "v": 1,
+            "type": "Transaction",
+            "fee": 170_000,
+            "quantity": -5_000_000_000i64,
+            "big": u64::MAX,
+            "ratio": 0.25,
+            "hash": "ab".repeat(32),
+            "flags": [false, true, null],
+            "nested": { "list": (0..20).collect::<Vec<_>>() },
+        });
+
+        assert_eq!(decode(&to_vec(&value)), value);
+    }
+}
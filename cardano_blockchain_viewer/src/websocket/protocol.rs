@@ -0,0 +1,400 @@
+// WebSocket message contract
+//
+// Every text frame is a JSON object with a `type` tag and a top-level `v` protocol version:
+//   - `{"v":1,"type":"stats","data":{...BufferStats}}`
+//   - `{"v":1,"type":"Block"|"Transaction"|"TxInput"|"TxOutput"|"RollBack"|"Mint"|"Metadata"|
+//     "NativeScript"|"Certificate"|"Other",...}`
+//     (a serialized BlockchainEvent)
+//   - `{"v":1,"type":"buffer_cleared","strategy":"reset"|"cumulative","blocks_count":n,
+//     "transactions_count":n,"buffer_size":n}` just before the buffer is emptied; with "reset" the
+//     following stats start again from zero
+//   - `{"v":1,"type":"lag","skipped":n}` when the client fell behind and messages were dropped
+//   - `{"v":1,"type":"watching","addresses":[...]}` / `{"v":1,"type":"error","error":"..."}` in reply
+//     to a client `{"action":"watch"|"unwatch","address":"..."}` frame
+//   - `{"v":1,"type":"watch_hit","address":...,"tx_hash":...,"slot":n,"amount":n}` after a TxOutput
+//     paying a watched address (see watch.rs)
+//
+// Block and Transaction carry their remaining Oura fields flattened in next to the promoted ones,
+// so all keys sit at the top level and none appears twice, e.g.
+//   - `{"v":1,"type":"Block","slot":10,"hash":"..","number":5,"epoch":1,"era":"Babbage",
+//     "tx_count":0,"timestamp":1700000000,"body_size":3,"epoch_slot":2,"issuer_vkey":"..",
+//     "vrf_vkey":"..","previous_hash":".."}`
+//   - `{"v":1,"type":"Transaction","hash":"..","slot":10,"fee":170000,"inputs":1,"outputs":2,
+//     "total_output":100,"mint_count":0,"is_mint":false,"timestamp":1700000000,"size":300,
+//     "input_count":1,"output_count":2,"collateral_input_count":0,"has_collateral_output":false}`
+// Optional Oura fields (ttl, validity_interval_start, ...) are omitted rather than sent as null.
+// Event `timestamp`s are unix seconds (0 when Oura didn't report one), like REST `block_time`s.
+//
+// Connecting with `?format=msgpack` switches every server frame (replay, broadcasts, replies)
+// to a binary MessagePack encoding of the same object; client frames stay JSON text. A
+// broadcast is converted to MessagePack at most once, however many msgpack clients get it.
+//
+// Adding fields is not a breaking change. Bump PROTOCOL_VERSION only when a field is
+// removed, renamed or changes meaning, so clients can refuse shapes they don't understand.
+// The tests at the bottom pin every shape above; a snapshot that has to change for any reason
+// other than a new field means a version bump.
+
+use axum::extract::ws::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
+
+use super::msgpack;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Per-connection frame encoding, chosen with `?format=` on the upgrade request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+/// Serialize a message for the WebSocket, adding the `v` protocol version
+pub fn encode<T: Serialize>(message: &T) -> serde_json::Result<String> {
+    let mut value = serde_json::to_value(message)?;
+    if let Value::Object(map) = &mut value {
+        map.insert("v".to_string(), PROTOCOL_VERSION.into());
+    }
+    serde_json::to_string(&value)
+}
+
+/// An encoded server message, cheap to clone into every connection's broadcast receiver
+#[derive(Debug, Clone)]
+pub struct Frame(Arc<FrameInner>);
+
+#[derive(Debug)]
+struct FrameInner {
+    json: String,
+    // Filled in by the first msgpack connection to send this frame, then shared
+    msgpack: OnceLock<Option<Vec<u8>>>,
+}
+
+impl Frame {
+    pub fn json(&self) -> &str {
+        &self.0.json
+    }
+
+    /// The frame for a client: JSON in a text frame, or MessagePack in a binary one
+    pub fn to_message(&self, format: FrameFormat) -> Message {
+        let msgpack = match format {
+            FrameFormat::Json => None,
+            FrameFormat::Msgpack => self
+                .0
+                .msgpack
+                .get_or_init(|| serde_json::from_str::<Value>(&self.0.json).ok().map(|v| msgpack::to_vec(&v)))
+                .as_ref(),
+        };
+        match msgpack {
+            Some(bytes) => Message::Binary(bytes.clone()),
+            // Every frame comes from protocol::encode, so invalid JSON shouldn't happen
+            None => Message::Text(self.0.json.clone()),
+        }
+    }
+}
+
+impl From<String> for Frame {
+    fn from(json: String) -> Self {
+        Self(Arc::new(FrameInner {
+            json,
+            msgpack: OnceLock::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AppState, BlockchainEvent};
+    use crate::services::event_processor::broadcast_stats;
+    use serde_json::json;
+    use tokio::sync::broadcast;
+
+    const HASH: &str = "0f3bd7b8b3c1e8b11e71d6bb1fa0d3ef2a75d1ba0bde2b4f28b5a8d94b1f9c2e";
+
+    // The frame exactly as a JSON client receives it
+    fn wire(event: &BlockchainEvent) -> Value {
+        serde_json::from_str(&encode(event).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn block_frame_flattens_its_details() {
+        let event = BlockchainEvent::Block {
+            slot: 10,
+            hash: HASH.to_string(),
+            number: 5,
+            epoch: 1,
+            era: "Babbage".to_string(),
+            tx_count: 2,
+            timestamp: 1_700_000_000,
+            details: json!({
+                "body_size": 3,
+                "epoch_slot": 2,
+                "issuer_vkey": "aa",
+                "vrf_vkey": "bb",
+                "previous_hash": "cc"
+            }),
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "Block", "slot": 10, "hash": HASH, "number": 5, "epoch": 1,
+                "era": "Babbage", "tx_count": 2, "timestamp": 1_700_000_000, "body_size": 3,
+                "epoch_slot": 2, "issuer_vkey": "aa", "vrf_vkey": "bb", "previous_hash": "cc"
+            })
+        );
+    }
+
+    #[test]
+    fn transaction_frame_flattens_its_details() {
+        let event = BlockchainEvent::Transaction {
+            hash: HASH.to_string(),
+            slot: 10,
+            fee: 170_000,
+            inputs: 1,
+            outputs: 2,
+            total_output: 5_000_000,
+            mint_count: 1,
+            is_mint: true,
+            timestamp: 1_700_000_000,
+            details: json!({
+                "size": 300,
+                "input_count": 1,
+                "output_count": 2,
+                "ttl": 500,
+                "collateral_input_count": 0,
+                "has_collateral_output": false
+            }),
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "Transaction", "hash": HASH, "slot": 10, "fee": 170_000, "inputs": 1,
+                "outputs": 2, "total_output": 5_000_000, "mint_count": 1, "is_mint": true,
+                "timestamp": 1_700_000_000, "size": 300, "input_count": 1, "output_count": 2,
+                "ttl": 500, "collateral_input_count": 0, "has_collateral_output": false
+            })
+        );
+    }
+
+    #[test]
+    fn tx_input_frame() {
+        let event = BlockchainEvent::TxInput {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            input_tx_id: "ab".repeat(32),
+            input_index: 3,
+            timestamp: 1_700_000_000,
+            input_idx: Some(0),
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "TxInput", "tx_hash": HASH, "slot": 10, "input_tx_id": "ab".repeat(32),
+                "input_index": 3, "timestamp": 1_700_000_000, "input_idx": 0
+            })
+        );
+    }
+
+    #[test]
+    fn tx_output_frame_omits_unknown_positions() {
+        let event = BlockchainEvent::TxOutput {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            address: "addr_test1vz".to_string(),
+            amount: 2_000_000,
+            timestamp: 1_700_000_000,
+            tx_idx: Some(1),
+            output_idx: None,
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "TxOutput", "tx_hash": HASH, "slot": 10, "address": "addr_test1vz",
+                "amount": 2_000_000, "timestamp": 1_700_000_000, "tx_idx": 1
+            })
+        );
+    }
+
+    #[test]
+    fn rollback_frame() {
+        let event = BlockchainEvent::RollBack {
+            block_hash: HASH.to_string(),
+            block_slot: 9,
+            timestamp: 0,
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({ "v": 1, "type": "RollBack", "block_hash": HASH, "block_slot": 9, "timestamp": 0 })
+        );
+    }
+
+    #[test]
+    fn mint_frame_keeps_negative_burns() {
+        let event = BlockchainEvent::Mint {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            policy: "ff".repeat(28),
+            asset: "746f6b656e".to_string(),
+            quantity: -5,
+            timestamp: 1_700_000_000,
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "Mint", "tx_hash": HASH, "slot": 10, "policy": "ff".repeat(28),
+                "asset": "746f6b656e", "quantity": -5, "timestamp": 1_700_000_000
+            })
+        );
+    }
+
+    #[test]
+    fn metadata_frame() {
+        let event = BlockchainEvent::Metadata {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            label: "674".to_string(),
+            timestamp: 1_700_000_000,
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({ "v": 1, "type": "Metadata", "tx_hash": HASH, "slot": 10, "label": "674", "timestamp": 1_700_000_000 })
+        );
+    }
+
+    #[test]
+    fn native_script_frame() {
+        let event = BlockchainEvent::NativeScript {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            policy_id: "ff".repeat(28),
+            timestamp: 1_700_000_000,
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "NativeScript", "tx_hash": HASH, "slot": 10, "policy_id": "ff".repeat(28),
+                "timestamp": 1_700_000_000
+            })
+        );
+    }
+
+    #[test]
+    fn certificate_frame_with_and_without_a_pool() {
+        let delegation = BlockchainEvent::Certificate {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            kind: "stake_delegation".to_string(),
+            pool: Some("pool1xyz".to_string()),
+            timestamp: 1_700_000_000,
+        };
+        let registration = BlockchainEvent::Certificate {
+            tx_hash: HASH.to_string(),
+            slot: 10,
+            kind: "stake_registration".to_string(),
+            pool: None,
+            timestamp: 1_700_000_000,
+        };
+
+        assert_eq!(
+            wire(&delegation),
+            json!({
+                "v": 1, "type": "Certificate", "tx_hash": HASH, "slot": 10, "kind": "stake_delegation",
+                "pool": "pool1xyz", "timestamp": 1_700_000_000
+            })
+        );
+        assert_eq!(
+            wire(&registration),
+            json!({
+                "v": 1, "type": "Certificate", "tx_hash": HASH, "slot": 10, "kind": "stake_registration",
+                "timestamp": 1_700_000_000
+            })
+        );
+    }
+
+    #[test]
+    fn other_frame_flattens_its_details() {
+        let event = BlockchainEvent::Other {
+            event_type: "collateral".to_string(),
+            timestamp: 1_700_000_000,
+            details: json!({ "tx_id": "ab", "index": 0 }),
+        };
+
+        assert_eq!(
+            wire(&event),
+            json!({
+                "v": 1, "type": "Other", "event_type": "collateral", "timestamp": 1_700_000_000,
+                "tx_id": "ab", "index": 0
+            })
+        );
+    }
+
+    #[test]
+    fn stats_frame() {
+        let mut state = AppState::new(10);
+        state.add_event(
+            BlockchainEvent::Transaction {
+                hash: HASH.to_string(),
+                slot: 10,
+                fee: 170_000,
+                inputs: 1,
+                outputs: 2,
+                total_output: 5_000_000,
+                mint_count: 0,
+                is_mint: false,
+                timestamp: 1_700_000_000,
+                details: json!({}),
+            },
+            10,
+        );
+        let (ws_tx, mut ws_rx) = broadcast::channel(4);
+
+        broadcast_stats(&mut state, &ws_tx).unwrap();
+
+        let frame: Value = serde_json::from_str(ws_rx.try_recv().unwrap().json()).unwrap();
+        assert_eq!(
+            frame,
+            json!({
+                "v": 1,
+                "type": "stats",
+                "data": {
+                    "total_events": 1, "blocks_count": 0, "transactions_count": 1, "inputs_count": 0,
+                    "outputs_count": 0, "total_output_lovelace": 5_000_000, "total_fees_lovelace": 170_000,
+                    "buffer_size": 1, "last_block_number": 0, "last_slot": 0, "rollback_count": 0,
+                    "last_rollback_slot": null
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn msgpack_is_encoded_once_per_frame() {
+        let frame = Frame::from(encode(&serde_json::json!({ "type": "lag", "skipped": 3 })).unwrap());
+        let receivers = [frame.clone(), frame.clone()];
+
+        // JSON clients don't pay for the conversion
+        assert!(matches!(receivers[0].to_message(FrameFormat::Json), Message::Text(_)));
+        assert!(frame.0.msgpack.get().is_none());
+
+        let first = receivers[0].to_message(FrameFormat::Msgpack);
+        let second = receivers[1].to_message(FrameFormat::Msgpack);
+        assert!(frame.0.msgpack.get().is_some());
+        match (first, second) {
+            (Message::Binary(a), Message::Binary(b)) => {
+                assert_eq!(a, b);
+                assert_eq!(msgpack::decode(&a), serde_json::json!({ "type": "lag", "skipped": 3, "v": 1 }));
+            }
+            other => panic!("expected binary frames, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// Per-connection watched addresses
+//
+// A client sends `{"action":"watch","address":"addr..."}` (or `"unwatch"`) and gets an extra
+// `{"v":1,"type":"watch_hit","address":...,"tx_hash":...,"slot":...,"amount":...}` frame after
+// every TxOutput paying one of its watched addresses.
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use super::protocol;
+use crate::address;
+
+// Keeps the per-frame matching cheap
+pub const MAX_WATCHED_ADDRESSES: usize = 10;
+
+/// Text frames a client may send
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ClientMessage {
+    Watch { address: String },
+    Unwatch { address: String },
+}
+
+// The fields of a TxOutput frame a watch hit needs
+#[derive(Deserialize)]
+struct OutputFrame {
+    tx_hash: String,
+    slot: u64,
+    address: String,
+    amount: u64,
+}
+
+/// Bech32 addresses one client watches; shared by its receive and send tasks
+#[derive(Clone, Default)]
+pub struct WatchList(Arc<Mutex<HashSet<String>>>);
+
+impl WatchList {
+    // Handle a client frame and return the reply to send back
+    pub fn handle(&self, text: &str) -> String {
+        let reply = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Watch { address }) => self.watch(&address),
+            Ok(ClientMessage::Unwatch { address }) => self.unwatch(&address),
+            Err(e) => Err(format!("Unrecognized message: {}", e)),
+        };
+
+        let message = match reply {
+            Ok(watching) => serde_json::json!({ "type": "watching", "addresses": watching }),
+            Err(error) => serde_json::json!({ "type": "error", "error": error }),
+        };
+        protocol::encode(&message).unwrap_or_default()
+    }
+
+    fn watch(&self, address: &str) -> Result<Vec<String>, String> {
+        let normalized = normalize(address)?;
+        let mut watched = self.0.lock().unwrap();
+        if watched.len() >= MAX_WATCHED_ADDRESSES && !watched.contains(&normalized) {
+            return Err(format!(
+                "At most {} addresses can be watched per connection",
+                MAX_WATCHED_ADDRESSES
+            ));
+        }
+        watched.insert(normalized);
+        Ok(watched.iter().cloned().collect())
+    }
+
+    fn unwatch(&self, address: &str) -> Result<Vec<String>, String> {
+        let normalized = normalize(address)?;
+        let mut watched = self.0.lock().unwrap();
+        watched.remove(&normalized);
+        Ok(watched.iter().cloned().collect())
+    }
+
+    // The watch_hit frame for a broadcast message, if it is a TxOutput to a watched address
+    pub fn hit(&self, message: &str) -> Option<String> {
+        // Skip parsing for everything but outputs
+        if !message.contains(r#""type":"TxOutput""#) {
+            return None;
+        }
+        let watched = self.0.lock().unwrap();
+        if watched.is_empty() {
+            return None;
+        }
+        let output: OutputFrame = serde_json::from_str(message).ok()?;
+        if !watched.contains(&output.address) {
+            return None;
+        }
+
+        protocol::encode(&serde_json::json!({
+            "type": "watch_hit",
+            "address": output.address,
+            "tx_hash": output.tx_hash,
+            "slot": output.slot,
+            "amount": output.amount,
+        }))
+        .ok()
+    }
+}
+
+// Oura reports output addresses in bech32, so hex addresses from CIP-30 wallets are converted
+fn normalize(address: &str) -> Result<String, String> {
+    address::from_any(address)
+        .and_then(|parsed| address::to_bech32(&parsed))
+        .map_err(|e| format!("Invalid address: {}", e))
+}